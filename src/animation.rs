@@ -0,0 +1,150 @@
+//! Short animations drawn on top of a game without being part of its [crate::map::MapState].
+//!
+//! Animations advance with the ticks of the app, so snapshots and undo never see them.
+use crate::color::{darken, Color, ColorDepth};
+use crate::grid::{Cell, Grid};
+use crate::map::Facing;
+use crate::stripe::TILE_WIDTH;
+
+/// Ticks a hop is drawn for before the player settles on the new tile.
+const HOP_TICKS: u8 = 2;
+
+/// Ticks a score popup is drawn for.
+const POPUP_TICKS: u8 = 6;
+
+/// Ticks a score popup takes to rise by one row.
+const POPUP_RISE_TICKS: u8 = 2;
+
+/// Ticks a trail mark is drawn for.
+const TRAIL_TICKS: u8 = 10;
+
+/// The player lifted off the ground after a hop.
+///
+/// The first frame of a sideways hop draws the player one column toward the tile they came
+/// from.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Hop {
+    facing: Facing,
+    ticks_left: u8,
+}
+
+impl Hop {
+    pub fn new(facing: Facing) -> Hop {
+        Hop { facing, ticks_left: HOP_TICKS }
+    }
+
+    /// The hop one tick later, [None] once the player settled.
+    pub fn next(self) -> Option<Hop> {
+        let ticks_left = self.ticks_left.checked_sub(1).filter(|ticks| *ticks > 0)?;
+        Some(Hop { ticks_left, ..self })
+    }
+
+    /// Columns the player is drawn away from their tile.
+    pub fn shift(self) -> isize {
+        match self.facing {
+            _ if self.ticks_left < HOP_TICKS => 0,
+            Facing::Left => 1,
+            Facing::Right => -1,
+            Facing::Up | Facing::Down => 0,
+        }
+    }
+}
+
+/// Points a player just scored, floating up from the tile they scored on and fading out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Popup {
+    text: String,
+    /// Tile and row the points were scored on.
+    x: u8,
+    y: u64,
+    ticks_left: u8,
+}
+
+impl Popup {
+    pub fn new(points: u64, x: u8, y: u64) -> Popup {
+        Popup { text: format!("+{points}"), x, y, ticks_left: POPUP_TICKS }
+    }
+
+    /// The popup one tick later, [None] once it faded out.
+    pub fn next(self) -> Option<Popup> {
+        let ticks_left = self.ticks_left.checked_sub(1).filter(|ticks| *ticks > 0)?;
+        Some(Popup { ticks_left, ..self })
+    }
+
+    /// Draws the popup above its tile on `board`, whose first line shows row `top_y` and first
+    /// column tile `left`. Without `motion` it stays right above the tile instead of rising.
+    pub fn draw(&self, board: &mut Grid, top_y: u64, left: usize, motion: bool) {
+        let age = POPUP_TICKS - self.ticks_left;
+        let rise = if motion { (age / POPUP_RISE_TICKS) as u64 } else { 0 };
+        let Some(line) = top_y.checked_sub(self.y + 1 + rise) else { return };
+        let Some(x) = (self.x as usize).checked_sub(left) else { return };
+        let start = x * TILE_WIDTH;
+        let end = start + self.text.chars().count();
+        let fg = darken(Color::Yellow, (age as u16 * 60 / POPUP_TICKS as u16) as u8);
+        board.map_cells(|x, y, cell, next| {
+            if y as u64 != line {
+                return cell;
+            }
+            let bg = match cell.glyph {
+                '█' | '▓' | '▒' | '░' => cell.fg,
+                _ => cell.bg,
+            };
+            if let Some(glyph) = x.checked_sub(start).and_then(|idx| self.text.chars().nth(idx)) {
+                Cell { glyph, fg, bg }
+            } else if cell.is_continuation() && x == end || x + 1 == start && next.is_some_and(Cell::is_continuation) {
+                // Halves of wide glyphs next to the text.
+                Cell { glyph: ' ', fg: cell.fg, bg }
+            } else {
+                cell
+            }
+        });
+    }
+}
+
+/// A mark of a [crate::shop::Trail] left on the tile a player hopped away from, fading out.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct TrailMark {
+    glyph: char,
+    color: Color,
+    /// Tile and row the player hopped away from.
+    x: u8,
+    y: u64,
+    ticks_left: u8,
+}
+
+impl TrailMark {
+    pub fn new(glyph: char, color: Color, x: u8, y: u64) -> TrailMark {
+        TrailMark { glyph, color, x, y, ticks_left: TRAIL_TICKS }
+    }
+
+    /// The mark one tick later, [None] once it faded out.
+    pub fn next(self) -> Option<TrailMark> {
+        let ticks_left = self.ticks_left.checked_sub(1).filter(|ticks| *ticks > 0)?;
+        Some(TrailMark { ticks_left, ..self })
+    }
+
+    /// Tile and row the mark is on.
+    pub fn position(&self) -> (u8, u64) {
+        (self.x, self.y)
+    }
+
+    /// Draws the mark in the middle of its tile on `board`, whose first line shows row `top_y` and
+    /// first column tile `left`. Wide glyphs are left alone.
+    pub fn draw(&self, board: &mut Grid, top_y: u64, left: usize) {
+        let Some(line) = top_y.checked_sub(self.y) else { return };
+        let Some(x) = (self.x as usize).checked_sub(left) else { return };
+        let column = x * TILE_WIDTH + TILE_WIDTH / 2;
+        let age = TRAIL_TICKS - self.ticks_left;
+        let fg = ColorDepth::current().adapt(darken(self.color, (age as u16 * 60 / TRAIL_TICKS as u16) as u8));
+        board.map_cells(|x, y, cell, next| {
+            if y as u64 != line || x != column || cell.is_continuation() || next.is_some_and(Cell::is_continuation) {
+                return cell;
+            }
+            let bg = match cell.glyph {
+                '█' | '▓' | '▒' | '░' => cell.fg,
+                _ => cell.bg,
+            };
+            Cell { glyph: self.glyph, fg, bg }
+        });
+    }
+}
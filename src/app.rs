@@ -0,0 +1,1599 @@
+//! The complete application as a state machine, independent of any terminal.
+//!
+//! Frontends feed [Event]s into [App::handle_event] and display what [App::view] returns. The
+//! simulation only advances on [Event::Tick], so the frontend decides how fast the game runs.
+//! Timed modes assume [crate::map::TICKS_PER_SECOND] ticks per second.
+use std::path::Path;
+use crossterm::event::KeyCode;
+use crate::animation::{Hop, Popup, TrailMark};
+#[cfg(feature = "serde")]
+use crate::autosave::{Autosave, AUTOSAVE_TICKS};
+use crate::character::Character;
+use crate::debug::{self, Timings};
+use crate::difficulty::Difficulty;
+use crate::editor::LevelEditor;
+use crate::explorer::SeedExplorer;
+#[cfg(feature = "gif")]
+use crate::gif::GifRecording;
+use crate::grid::{text, Cell, Grid};
+use crate::hash::BoardHash;
+use crate::highscores::HighScores;
+use crate::keymap::{coop_player, Action, KeyboardLayout, Keymap};
+use crate::level::BUNDLED;
+use crate::locale::{Language, Text};
+#[cfg(feature = "logging")]
+use crate::logging;
+use crate::map::{DeathCause, Facing, GameEvent, MapState, CRASH_TICKS};
+use crate::menu::Menu;
+use crate::mode::{GameMode, PRACTICE_SNAPSHOT_ROWS, TIME_ATTACK_SECONDS};
+use crate::narration;
+use crate::net::Progress;
+use crate::profile::{self, Profile, MAX_NAME_LEN};
+use crate::screenshot;
+use crate::seed_code::SeedCode;
+use crate::shop::{Item, Wallet};
+use crate::sound::{Sound, Track, CHIME_SCORE};
+use crate::stats::{RunStats, Stats};
+use crate::stripe::{self, Block, TILE_WIDTH, VISIBLE_TILES};
+use crate::theme::{Glyphs, Theme};
+use crate::tutorial::{self, Lesson};
+use crate::unlocks::{Unlockable, Unlocks};
+use crate::versus::Versus;
+use crate::weather::Weather;
+
+/// Level file the editor opens when started from the main menu.
+pub const CUSTOM_LEVEL: &str = "custom.level";
+
+/// Ticks the summary screen ignores keys for, so a hop in the moment of death doesn't skip it.
+const SUMMARY_GRACE_TICKS: u32 = 5;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Event {
+    Key(KeyCode),
+    /// Advances the simulation by one step.
+    Tick,
+    Resize { width: u16, height: u16 },
+    /// Ctrl+C or a signal asking the process to end, see [App::shutdown].
+    Interrupt,
+    /// Ctrl+Z or SIGTSTP suspending the process, which pauses the running game. Stopping the
+    /// process is up to the frontend.
+    Suspend,
+}
+
+/// Everything a frontend needs to display the current state.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    pub grid: Grid,
+    pub title: String,
+    /// Progress toward the best score in percent, if there is something to progress toward.
+    pub progress: Option<u8>,
+    /// Rows the current run scrolled forward, which moves the [crate::backdrop] around the frame.
+    pub scroll: u64,
+}
+
+#[derive(Clone)]
+enum MainMenuEntry {
+    Play(GameMode, Difficulty),
+    /// An endless run for two players.
+    Coop,
+    /// A race of two players on boards side by side.
+    Versus,
+    Explore,
+    Editor,
+    Layout,
+    Character,
+    Profile,
+    Shop,
+    Quit,
+}
+
+#[derive(Clone)]
+enum ProfileEntry {
+    /// Switches to the profile of this name, [None] for the default one.
+    Select(Option<String>),
+    /// Asks for the name of a new profile.
+    New,
+}
+
+struct Game {
+    map: MapState,
+    mode: GameMode,
+    /// The high score table the run is submitted to, fixed when the run starts.
+    table: Option<String>,
+    /// The state a practice run can be rewound to after dying.
+    snapshot: Option<MapState>,
+    /// The state before the last move of a casual run, until the next tick.
+    undo: Option<MapState>,
+    /// Description of the surroundings after the last move, see [narration].
+    narration: String,
+    /// The hops the players are in the middle of, indexed by player.
+    hops: Vec<Option<Hop>>,
+    /// The points floating up from where they were scored.
+    popups: Vec<Popup>,
+    /// The marks of the trail the first player wears, see [Wallet::trail].
+    trails: Vec<TrailMark>,
+    /// The last progress of the other player in a race over the network.
+    remote: Option<Progress>,
+    /// What the run adds to the stats, counted so far.
+    stats: RunStats,
+    /// What the player is asked to do next in the tutorial.
+    lesson: Option<Lesson>,
+    /// The simulation speed if it isn't the default one, see [Profile::speed].
+    speed: Option<f32>,
+    /// Code to play the same board again, taken when the run started.
+    seed_code: Option<SeedCode>,
+    /// Every frame of the board so far, until it is saved from the summary.
+    #[cfg(feature = "gif")]
+    recording: Option<GifRecording>,
+}
+
+impl Game {
+    fn new(mode: GameMode, map: MapState, speed: Option<f32>) -> Box<Game> {
+        let snapshot = (mode == GameMode::Practice).then(|| map.clone());
+        let table = mode.high_score_table(&map.difficulty())
+            .map(|table| if map.auto_scrolls() { format!("{table}-auto-scroll") } else { table })
+            .map(|table| if map.lives().is_some() { format!("{table}-lives") } else { table })
+            .map(|table| if map.player_count() > 1 { format!("{table}-coop") } else { table })
+            .map(|table| if map.weather().slippery() { format!("{table}-{}", map.weather().name()) } else { table })
+            .map(|table| match speed {
+                Some(speed) => format!("{table}-speed{speed}"),
+                None => table,
+            });
+        let narration = narration::describe(&map);
+        let hops = vec![None; map.player_count()];
+        let lesson = (mode == GameMode::Tutorial).then(Lesson::default);
+        let seed_code = SeedCode::of(&map);
+        Box::new(Game {
+            table, map, mode, snapshot, undo: None, narration, hops, popups: Vec::new(), trails: Vec::new(), remote: None, stats: RunStats::default(), lesson, speed, seed_code,
+            #[cfg(feature = "gif")]
+            recording: Some(GifRecording::new()),
+        })
+    }
+
+    /// Moves on to the next lesson of the tutorial if the player did what the current one asks,
+    /// `hop` is the direction they just hopped toward. A player who died starts over on a fresh
+    /// board, keeping the lesson.
+    fn update_lesson(&mut self, hop: Option<Facing>) {
+        let Some(lesson) = self.lesson else { return };
+        if !self.map.alive {
+            self.map = tutorial::map().with_character(self.map.character());
+            self.hops.fill(None);
+            self.update_narration();
+        }
+        self.lesson = Some(lesson.advance(hop, &self.map));
+    }
+
+    /// The board with the players mid-hop, the cosmetics of the first player from `wallet` and
+    /// the score popups.
+    fn board(&self, theme: &Theme, wallet: &Wallet) -> Grid {
+        let mut board = self.map.render_animated(theme, &self.hops);
+        let top_y = self.map.visible_rows().end - 1;
+        let left = self.map.visible_columns().start;
+        if self.map.alive && !self.map.finished {
+            let occupied = (0..self.map.player_count()).map(|player| (self.map.player_x_of(player), self.map.player_y_of(player))).collect::<Vec<_>>();
+            for mark in self.trails.iter().filter(|mark| !occupied.contains(&mark.position())) {
+                mark.draw(&mut board, top_y, left);
+            }
+            if let Some(skin) = wallet.skin && let Some(line) = top_y.checked_sub(self.map.player_y_of(0))
+                && let Some(x) = (self.map.player_x_of(0) as usize).checked_sub(left) {
+                let shift = self.hops[0].map_or(0, Hop::shift);
+                let start = (x * TILE_WIDTH).saturating_add_signed(shift).min(board.width().saturating_sub(TILE_WIDTH));
+                skin.paint(&mut board, theme, line as usize, start, self.map.player_y_of(0));
+            }
+        }
+        for popup in &self.popups {
+            popup.draw(&mut board, top_y, left, !theme.accessibility.reduced_motion);
+        }
+        board
+    }
+
+    /// Describes the surroundings again after the player moved.
+    fn update_narration(&mut self) {
+        self.narration = narration::describe(&self.map);
+    }
+
+    /// Remembers the current state in practice runs whenever the player reached a new multiple
+    /// of [PRACTICE_SNAPSHOT_ROWS] rows.
+    fn update_snapshot(&mut self) {
+        let rows = self.map.rows_crossed();
+        if let Some(snapshot) = &mut self.snapshot
+            && self.map.alive
+            && rows.is_multiple_of(PRACTICE_SNAPSHOT_ROWS)
+            && rows > snapshot.rows_crossed() {
+            *snapshot = self.map.clone();
+        }
+    }
+
+    /// The mode, board and how to play the run again, for [logging::start_run].
+    #[cfg(feature = "logging")]
+    fn description(&self) -> Vec<String> {
+        let mut lines = vec![
+            format!("Mode {}, seed {}", self.title(Language::English), self.map.seed()),
+        ];
+        if let Some(code) = self.seed_code {
+            lines.push(format!("Play it again with --seed-code {code} --input-script <this file>"));
+        }
+        lines
+    }
+
+    /// The mode followed by the difficulty if it isn't the normal one, whether the camera
+    /// scrolls on its own, whether the player has lives, whether two players play, the weather and
+    /// the simulation speed.
+    fn title(&self, language: Language) -> String {
+        let mut options = Vec::new();
+        if self.map.difficulty() != Difficulty::NORMAL {
+            options.push(self.map.difficulty().name.to_string());
+        }
+        if self.map.auto_scrolls() {
+            options.push(language.text(Text::AutoScroll).to_string());
+        }
+        if self.map.lives().is_some() {
+            options.push(language.text(Text::Lives).to_string());
+        }
+        if self.map.player_count() > 1 {
+            options.push(language.text(Text::Coop).to_lowercase());
+        }
+        if self.map.weather() != Weather::Clear {
+            options.push(self.map.weather().name().to_string());
+        }
+        if let Some(speed) = self.speed {
+            options.push(format!("{speed}x"));
+        }
+        if options.is_empty() {
+            self.mode.title(language)
+        } else {
+            format!("{} ({})", self.mode.title(language), options.join(", "))
+        }
+    }
+}
+
+enum Screen {
+    MainMenu,
+    LayoutPrompt {
+        menu: Menu<KeyboardLayout>,
+        next: Box<Screen>,
+    },
+    CharacterPrompt(Menu<Character>),
+    ProfilePrompt(Menu<ProfileEntry>),
+    /// Asks for the name of a new profile, holding what was typed so far.
+    ProfileName(String),
+    Shop(Menu<Item>),
+    /// Asks whether to resume the saved game, [None] discards it.
+    #[cfg(feature = "serde")]
+    ResumePrompt(Menu<Option<Box<Autosave>>>),
+    Game(Box<Game>),
+    Paused(Box<Game>),
+    /// The keys and colors drawn over the frozen game.
+    Help {
+        game: Box<Game>,
+        /// Whether the game was paused before, to return there instead of continuing it.
+        paused: bool,
+    },
+    /// The board frozen in the moment the player crashed, before the summary.
+    Dying {
+        game: Box<Game>,
+        ticks: u32,
+    },
+    Summary {
+        game: Box<Game>,
+        ticks: u32,
+        /// Position of the run in its high score table.
+        rank: Option<usize>,
+    },
+    Versus(Box<Versus>),
+    VersusResults {
+        versus: Box<Versus>,
+        ticks: u32,
+    },
+    Explorer(SeedExplorer),
+    Editor(Box<LevelEditor>),
+}
+
+#[cfg(feature = "logging")]
+impl Screen {
+    /// Identifies the screen in the log.
+    fn name(&self) -> &'static str {
+        match self {
+            Screen::MainMenu => "main-menu",
+            Screen::LayoutPrompt { .. } => "layout-prompt",
+            Screen::CharacterPrompt(_) => "character-prompt",
+            Screen::ProfilePrompt(_) => "profile-prompt",
+            Screen::ProfileName(_) => "profile-name",
+            Screen::Shop(_) => "shop",
+            #[cfg(feature = "serde")]
+            Screen::ResumePrompt(_) => "resume-prompt",
+            Screen::Game(_) => "game",
+            Screen::Paused(_) => "paused",
+            Screen::Help { .. } => "help",
+            Screen::Dying { .. } => "dying",
+            Screen::Summary { .. } => "summary",
+            Screen::Versus(_) => "versus",
+            Screen::VersusResults { .. } => "versus-results",
+            Screen::Explorer(_) => "explorer",
+            Screen::Editor(_) => "editor",
+        }
+    }
+
+    /// Whether a run is going on, maybe paused.
+    fn in_run(&self) -> bool {
+        matches!(self, Screen::Game(_) | Screen::Paused(_) | Screen::Help { .. } | Screen::Dying { .. })
+    }
+}
+
+pub struct App {
+    profile: Profile,
+    high_scores: HighScores,
+    unlocks: Unlocks,
+    wallet: Wallet,
+    keymap: Keymap,
+    screen: Screen,
+    main_menu: Menu<MainMenuEntry>,
+    /// Quit when leaving the screen the app started with instead of returning to the main menu.
+    single_screen: bool,
+    /// Played by someone connected to a server, who may not change the files of the host.
+    guest: bool,
+    /// Whether finished runs go into the stats and high scores.
+    records: bool,
+    should_quit: bool,
+    width: u16,
+    height: u16,
+    clipboard: Option<String>,
+    /// Shown in the main menu, e.g. when saving the profile failed.
+    status: String,
+    /// Whether [App::view] changed since the last [App::take_dirty].
+    dirty: bool,
+    /// Sounds to play since the last [App::take_sounds].
+    sounds: Vec<Sound>,
+    /// Whether the debug overlay is shown, toggled with F3.
+    debug: bool,
+    /// What the frontend last measured, see [App::set_timings].
+    timings: Option<Timings>,
+    /// The score of the last run that ended, see [App::last_score].
+    last_score: Option<u64>,
+    /// Ticks the current game ran since it was last saved, see [App::autosave].
+    #[cfg(feature = "serde")]
+    autosave_ticks: u64,
+}
+
+impl App {
+    /// Offers to resume an interrupted run if one was saved, otherwise starts at the main menu, or
+    /// with the tutorial if no game was played yet.
+    pub fn new(profile: Profile) -> App {
+        #[cfg(feature = "serde")]
+        if let Some(autosave) = Autosave::load() {
+            let screen = resume_prompt(autosave, profile.theme.language);
+            return Self::starting_at(profile, screen, false);
+        }
+        Self::fresh(profile)
+    }
+
+    /// Starts at the main menu, or with the tutorial if no game was played yet.
+    fn fresh(profile: Profile) -> App {
+        let screen = first_screen(&profile);
+        Self::starting_at(profile, screen, false)
+    }
+
+    /// Starts a run of `mode` on `map` and quits once the run is over.
+    pub fn play(profile: Profile, mode: GameMode, map: MapState) -> App {
+        let game = Game::new(mode, map.with_character(profile.character), profile.speed);
+        Self::starting_at(profile, Screen::Game(game), true)
+    }
+
+    /// Starts an endless run on `seed` against a player over the network and quits once it is
+    /// over, see [App::set_remote_progress].
+    pub fn race(profile: Profile, seed: u64) -> App {
+        let mut game = Game::new(GameMode::Endless, MapState::with_seed(seed).with_character(profile.character), profile.speed);
+        game.remote = Some(Progress::default());
+        Self::starting_at(profile, Screen::Game(game), true)
+    }
+
+    /// Starts a versus race and quits once it is over.
+    pub fn versus(profile: Profile) -> App {
+        let versus = Versus::new([profile.character, profile.character.partner()]);
+        Self::starting_at(profile, Screen::Versus(Box::new(versus)), true)
+    }
+
+    /// Starts at the seed explorer and quits once the explored seed was played.
+    pub fn explore(profile: Profile) -> App {
+        Self::starting_at(profile, Screen::Explorer(SeedExplorer::new()), true)
+    }
+
+    /// Starts at the level editor and quits when leaving it.
+    pub fn edit(profile: Profile, editor: LevelEditor) -> App {
+        Self::starting_at(profile, Screen::Editor(Box::new(editor)), true)
+    }
+
+    /// Starts at the main menu for someone connected to a server. Their profile isn't saved and
+    /// the level editor is not available.
+    pub fn guest(profile: Profile) -> App {
+        App { guest: true, ..Self::fresh(profile) }
+    }
+
+    /// Keeps finished runs out of the stats and high scores, e.g. when replaying one.
+    pub fn without_records(mut self) -> App {
+        self.records = false;
+        self
+    }
+
+    fn starting_at(profile: Profile, screen: Screen, single_screen: bool) -> App {
+        #[cfg(feature = "logging")]
+        if let Screen::Game(game) = &screen {
+            logging::start_run(game.description());
+        }
+        let screen = match profile.layout {
+            Some(_) => screen,
+            None => layout_prompt(screen, profile.theme.language),
+        };
+        let unlocks = Unlocks::load_with(&Stats::load());
+        let wallet = Wallet::load();
+        App {
+            keymap: Keymap::for_layout(profile.layout.unwrap_or(KeyboardLayout::Qwerty)),
+            main_menu: main_menu(profile.theme.language, &unlocks, &wallet),
+            profile,
+            high_scores: HighScores::load(),
+            unlocks,
+            wallet,
+            screen,
+            single_screen,
+            guest: false,
+            records: true,
+            should_quit: false,
+            width: 80,
+            height: 24,
+            clipboard: None,
+            status: String::new(),
+            dirty: true,
+            sounds: Vec::new(),
+            debug: false,
+            timings: None,
+            last_score: None,
+            #[cfg(feature = "serde")]
+            autosave_ticks: 0,
+        }
+    }
+
+    pub fn profile(&self) -> &Profile {
+        &self.profile
+    }
+
+    /// Whether the frontend should shut down.
+    pub fn should_quit(&self) -> bool {
+        self.should_quit
+    }
+
+    /// Text the player asked to copy to the clipboard since the last call.
+    pub fn take_clipboard(&mut self) -> Option<String> {
+        self.clipboard.take()
+    }
+
+    /// Sound effects triggered since the last call.
+    pub fn take_sounds(&mut self) -> Vec<Sound> {
+        std::mem::take(&mut self.sounds)
+    }
+
+    /// The music of the current game and whether it is playing, [None] outside of games.
+    pub fn music(&self) -> Option<(Track, bool)> {
+        match &self.screen {
+            Screen::Game(game) => Some((Track::for_mode(&game.mode), true)),
+            Screen::Paused(game) | Screen::Help { game, .. } => Some((Track::for_mode(&game.mode), false)),
+            _ => None,
+        }
+    }
+
+    /// The progress to send to the other player of a race over the network.
+    pub fn race_progress(&self) -> Option<Progress> {
+        let game = self.race_game()?;
+        Some(Progress { y: game.map.player_y(), score: game.map.score(), alive: game.map.alive })
+    }
+
+    /// Shows the progress the other player of a race over the network sent.
+    pub fn set_remote_progress(&mut self, progress: Progress) {
+        if let Screen::Game(game) | Screen::Paused(game) | Screen::Help { game, .. } | Screen::Dying { game, .. } | Screen::Summary { game, .. } = &mut self.screen
+            && game.remote.is_some() {
+            game.remote = Some(progress);
+            self.dirty = true;
+        }
+    }
+
+    /// The state hash of the current game, see [MapState::state_hash].
+    pub fn state_hash(&self) -> Option<BoardHash> {
+        match &self.screen {
+            Screen::Game(game) | Screen::Paused(game) | Screen::Help { game, .. } | Screen::Dying { game, .. } | Screen::Summary { game, .. } => Some(game.map.state_hash()),
+            _ => None,
+        }
+    }
+
+    /// The code to play the board of the current game again.
+    pub fn seed_code(&self) -> Option<SeedCode> {
+        match &self.screen {
+            Screen::Game(game) | Screen::Paused(game) | Screen::Help { game, .. } | Screen::Dying { game, .. } | Screen::Summary { game, .. } => game.seed_code,
+            _ => None,
+        }
+    }
+
+    /// The score of the last run that ended, whether by dying, finishing or quitting.
+    pub fn last_score(&self) -> Option<u64> {
+        self.last_score
+    }
+
+    /// The game of the current race over the network.
+    fn race_game(&self) -> Option<&Game> {
+        match &self.screen {
+            Screen::Game(game) | Screen::Paused(game) | Screen::Help { game, .. } | Screen::Dying { game, .. } | Screen::Summary { game, .. } => {
+                game.remote.is_some().then_some(&**game)
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether the frame changed since the last call, so frontends only draw when needed.
+    pub fn take_dirty(&mut self) -> bool {
+        std::mem::take(&mut self.dirty)
+    }
+
+    /// Shows how fast the frontend runs in the debug overlay.
+    pub fn set_timings(&mut self, timings: Timings) {
+        self.timings = Some(timings);
+        self.dirty |= self.debug;
+    }
+
+    pub fn handle_event(&mut self, event: Event) {
+        #[cfg(feature = "logging")]
+        let before = (self.screen.name(), self.screen.in_run());
+        self.handle_event_inner(event);
+        #[cfg(feature = "logging")]
+        match (event, &self.screen) {
+            (Event::Tick, Screen::Game(game)) => logging::record_tick(Some(&game.map)),
+            (Event::Tick, _) => logging::record_tick(None),
+            (Event::Key(key), _) => logging::record_key(key),
+            _ => {}
+        }
+        #[cfg(feature = "logging")]
+        if self.screen.name() != before.0 {
+            tracing::info!("Screen {} -> {}", before.0, self.screen.name());
+            if let Screen::Game(game) = &self.screen && !before.1 {
+                logging::start_run(game.description());
+            }
+        }
+    }
+
+    fn handle_event_inner(&mut self, event: Event) {
+        match event {
+            Event::Resize { width, height } => {
+                self.width = width;
+                self.height = height;
+                self.dirty = true;
+                if self.tile_len().is_none() {
+                    let screen = std::mem::replace(&mut self.screen, Screen::MainMenu);
+                    self.screen = match screen {
+                        Screen::Game(game) => Screen::Paused(game),
+                        screen => screen,
+                    };
+                }
+            }
+            Event::Tick => self.tick(),
+            Event::Interrupt => self.shutdown(),
+            Event::Suspend => {
+                let screen = std::mem::replace(&mut self.screen, Screen::MainMenu);
+                self.screen = match screen {
+                    Screen::Game(game) => Screen::Paused(game),
+                    screen => screen,
+                };
+                self.dirty = true;
+            }
+            Event::Key(KeyCode::F(3)) => {
+                self.debug = !self.debug;
+                self.dirty = true;
+            }
+            Event::Key(KeyCode::F(12)) => self.screenshot(),
+            Event::Key(key) => {
+                self.key(key);
+                self.dirty = true;
+            }
+        }
+    }
+
+    /// Only games change on their own, all other screens wait for keys.
+    fn tick(&mut self) {
+        let screen = std::mem::replace(&mut self.screen, Screen::MainMenu);
+        self.screen = match screen {
+            Screen::Game(mut game) => {
+                game.undo = None;
+                game.map.update();
+                self.dirty |= game.map.take_dirty();
+                for hop in game.hops.iter_mut().filter(|hop| hop.is_some()) {
+                    *hop = hop.and_then(Hop::next);
+                    self.dirty = true;
+                }
+                self.dirty |= !game.popups.is_empty() || !game.trails.is_empty();
+                game.popups = std::mem::take(&mut game.popups).into_iter().filter_map(Popup::next).collect();
+                game.trails = std::mem::take(&mut game.trails).into_iter().filter_map(TrailMark::next).collect();
+                self.handle_events(&mut game);
+                game.update_lesson(None);
+                #[cfg(feature = "gif")]
+                if let Some(mut recording) = game.recording.take() {
+                    recording.record(&game.board(&self.profile.theme, &self.wallet));
+                    game.recording = Some(recording);
+                }
+                #[cfg(feature = "serde")]
+                {
+                    self.autosave_ticks += 1;
+                    if self.autosave_ticks >= AUTOSAVE_TICKS && game.map.alive && !game.map.finished {
+                        self.save_game(&game);
+                    }
+                }
+                if !game.map.alive {
+                    self.dirty = true;
+                    Screen::Dying { game, ticks: 0 }
+                } else if game.map.finished {
+                    self.dirty = true;
+                    self.finish_game(game)
+                } else {
+                    Screen::Game(game)
+                }
+            }
+            #[cfg_attr(not(feature = "gif"), allow(unused_mut))]
+            Screen::Dying { mut game, ticks } => {
+                self.dirty = true;
+                #[cfg(feature = "gif")]
+                if let Some(recording) = &mut game.recording {
+                    recording.record(&game.map.render_crash(&self.profile.theme, ticks));
+                }
+                if ticks + 1 >= CRASH_TICKS {
+                    self.finish_game(game)
+                } else {
+                    Screen::Dying { game, ticks: ticks + 1 }
+                }
+            }
+            Screen::Summary { game, ticks, rank } => Screen::Summary { game, ticks: ticks + 1, rank },
+            Screen::Versus(mut versus) => {
+                versus.update();
+                self.dirty |= versus.take_dirty();
+                self.sounds.extend(versus.take_events().into_iter().filter_map(sound_of));
+                if versus.is_over() {
+                    self.dirty = true;
+                    Screen::VersusResults { versus, ticks: 0 }
+                } else {
+                    Screen::Versus(versus)
+                }
+            }
+            Screen::VersusResults { versus, ticks } => Screen::VersusResults { versus, ticks: ticks + 1 },
+            screen => screen,
+        };
+    }
+
+    fn key(&mut self, key: KeyCode) {
+        let screen = std::mem::replace(&mut self.screen, Screen::MainMenu);
+        self.screen = match screen {
+            Screen::MainMenu => self.main_menu_key(key),
+            Screen::LayoutPrompt { mut menu, next } => match key {
+                KeyCode::Up => {
+                    menu.up();
+                    Screen::LayoutPrompt { menu, next }
+                }
+                KeyCode::Down => {
+                    menu.down();
+                    Screen::LayoutPrompt { menu, next }
+                }
+                KeyCode::Enter => {
+                    self.profile.layout = Some(menu.selected());
+                    self.keymap = Keymap::for_layout(menu.selected());
+                    self.save_profile();
+                    *next
+                }
+                _ => Screen::LayoutPrompt { menu, next },
+            },
+            Screen::CharacterPrompt(mut menu) => match key {
+                KeyCode::Up => {
+                    menu.up();
+                    Screen::CharacterPrompt(menu)
+                }
+                KeyCode::Down => {
+                    menu.down();
+                    Screen::CharacterPrompt(menu)
+                }
+                KeyCode::Enter if menu.is_selected_locked() => Screen::CharacterPrompt(menu),
+                KeyCode::Enter => {
+                    self.profile.character = menu.selected();
+                    self.save_profile();
+                    Screen::MainMenu
+                }
+                KeyCode::Esc | KeyCode::Char('q') => Screen::MainMenu,
+                _ => Screen::CharacterPrompt(menu),
+            },
+            Screen::ProfilePrompt(mut menu) => match key {
+                KeyCode::Up => {
+                    menu.up();
+                    Screen::ProfilePrompt(menu)
+                }
+                KeyCode::Down => {
+                    menu.down();
+                    Screen::ProfilePrompt(menu)
+                }
+                KeyCode::Enter => match menu.selected() {
+                    ProfileEntry::Select(name) => self.switch_profile(name),
+                    ProfileEntry::New => Screen::ProfileName(String::new()),
+                },
+                KeyCode::Esc | KeyCode::Char('q') => Screen::MainMenu,
+                _ => Screen::ProfilePrompt(menu),
+            },
+            Screen::ProfileName(mut name) => match key {
+                KeyCode::Char(c) if name.len() < MAX_NAME_LEN && profile::is_valid_name(&c.to_string()) => {
+                    name.push(c);
+                    Screen::ProfileName(name)
+                }
+                KeyCode::Backspace => {
+                    name.pop();
+                    Screen::ProfileName(name)
+                }
+                KeyCode::Enter if profile::is_valid_name(&name) => self.switch_profile(Some(name)),
+                KeyCode::Esc => Screen::MainMenu,
+                _ => Screen::ProfileName(name),
+            },
+            Screen::Shop(mut menu) => match key {
+                KeyCode::Up => {
+                    menu.up();
+                    Screen::Shop(menu)
+                }
+                KeyCode::Down => {
+                    menu.down();
+                    Screen::Shop(menu)
+                }
+                KeyCode::Enter if menu.is_selected_locked() => Screen::Shop(menu),
+                KeyCode::Enter => self.shop(menu.selected(), menu.selected_index()),
+                KeyCode::Esc | KeyCode::Char('q') => Screen::MainMenu,
+                _ => Screen::Shop(menu),
+            },
+            #[cfg(feature = "serde")]
+            Screen::ResumePrompt(mut menu) => match key {
+                KeyCode::Up => {
+                    menu.up();
+                    Screen::ResumePrompt(menu)
+                }
+                KeyCode::Down => {
+                    menu.down();
+                    Screen::ResumePrompt(menu)
+                }
+                KeyCode::Enter => match menu.selected() {
+                    Some(autosave) => {
+                        let mut game = Game::new(autosave.mode, autosave.map, self.profile.speed);
+                        game.stats = autosave.stats;
+                        Screen::Game(game)
+                    }
+                    None => {
+                        self.clear_autosave();
+                        first_screen(&self.profile)
+                    }
+                },
+                // Keeps the saved game to be offered again on the next start.
+                KeyCode::Esc | KeyCode::Char('q') => first_screen(&self.profile),
+                _ => Screen::ResumePrompt(menu),
+            },
+            Screen::Game(mut game) => match self.keymap.action(key) {
+                Some(Action::Quit) => self.finish_game(game),
+                Some(Action::Pause) => Screen::Paused(game),
+                Some(Action::Help) => Screen::Help { game, paused: false },
+                Some(Action::Undo) => {
+                    if let Some(map) = game.undo.take() {
+                        game.map = map;
+                        game.hops.fill(None);
+                        game.popups.clear();
+                        game.trails.clear();
+                        game.update_narration();
+                    }
+                    Screen::Game(game)
+                }
+                action => {
+                    if game.mode == GameMode::Casual && action.is_some() {
+                        game.undo = Some(game.map.clone());
+                    }
+                    let player = if game.map.player_count() > 1 { coop_player(key) } else { 0 };
+                    let position = (game.map.player_x_of(player), game.map.player_y_of(player));
+                    let direction = match action {
+                        Some(Action::Up) => Some(Facing::Up),
+                        Some(Action::Right) => Some(Facing::Right),
+                        Some(Action::Down) => Some(Facing::Down),
+                        Some(Action::Left) => Some(Facing::Left),
+                        _ => None,
+                    };
+                    if let Some(direction) = direction {
+                        game.map.hop(player, direction);
+                    }
+                    if action.is_some() {
+                        self.sounds.push(Sound::Hop);
+                    }
+                    if let Some(direction) = direction
+                        && position != (game.map.player_x_of(player), game.map.player_y_of(player)) {
+                        game.hops[player] = Some(Hop::new(direction));
+                        if let Some(trail) = self.wallet.trail && player == 0 {
+                            let glyph = trail.glyph(self.profile.theme.glyphs);
+                            game.trails.push(TrailMark::new(glyph, trail.color(), position.0, position.1));
+                        }
+                    }
+                    self.handle_events(&mut game);
+                    game.update_lesson(direction);
+                    game.update_snapshot();
+                    game.update_narration();
+                    if game.map.alive {
+                        Screen::Game(game)
+                    } else {
+                        Screen::Dying { game, ticks: 0 }
+                    }
+                }
+            },
+            Screen::Dying { game, .. } if self.keymap.action(key) == Some(Action::Quit) => self.finish_game(game),
+            screen @ Screen::Dying { .. } => screen,
+            Screen::Paused(game) => match self.keymap.action(key) {
+                Some(Action::Pause) => Screen::Game(game),
+                Some(Action::Help) => Screen::Help { game, paused: true },
+                Some(Action::Quit) => self.finish_game(game),
+                _ => Screen::Paused(game),
+            },
+            Screen::Help { game, paused: true } => Screen::Paused(game),
+            Screen::Help { game, paused: false } => Screen::Game(game),
+            Screen::Summary { game, ticks, rank } if ticks < SUMMARY_GRACE_TICKS => Screen::Summary { game, ticks, rank },
+            Screen::Summary { mut game, .. } if key == KeyCode::Char('z') && game.snapshot.is_some() => {
+                game.map = game.snapshot.clone().unwrap();
+                game.update_narration();
+                Screen::Game(game)
+            }
+            #[cfg(feature = "gif")]
+            Screen::Summary { mut game, ticks, rank } if key == KeyCode::Char('g') && game.recording.is_some() && !self.guest => {
+                self.save_gif(&mut game);
+                Screen::Summary { game, ticks, rank }
+            }
+            Screen::Summary { .. } => self.leave(),
+            Screen::Versus(_) if self.keymap.action(key) == Some(Action::Quit) => self.leave(),
+            Screen::Versus(mut versus) => {
+                let player = coop_player(key);
+                let direction = match self.keymap.action(key) {
+                    Some(Action::Up) => Some(Facing::Up),
+                    Some(Action::Right) => Some(Facing::Right),
+                    Some(Action::Down) => Some(Facing::Down),
+                    Some(Action::Left) => Some(Facing::Left),
+                    _ => None,
+                };
+                if let Some(direction) = direction {
+                    versus.hop(player, direction);
+                    self.sounds.push(Sound::Hop);
+                    self.sounds.extend(versus.take_events().into_iter().filter_map(sound_of));
+                }
+                if versus.is_over() {
+                    Screen::VersusResults { versus, ticks: 0 }
+                } else {
+                    Screen::Versus(versus)
+                }
+            }
+            Screen::VersusResults { versus, ticks } if ticks < SUMMARY_GRACE_TICKS => Screen::VersusResults { versus, ticks },
+            Screen::VersusResults { .. } => self.leave(),
+            Screen::Explorer(_) if key == KeyCode::Char('q') => self.leave(),
+            Screen::Explorer(explorer) if key == KeyCode::Char('p') => {
+                let map = MapState::with_seed(explorer.seed()).with_character(self.profile.character);
+                Screen::Game(Game::new(GameMode::Endless, map, self.profile.speed))
+            }
+            Screen::Explorer(mut explorer) => {
+                match key {
+                    KeyCode::Char('r') => explorer.randomize(),
+                    KeyCode::Char('c') => self.clipboard = Some(explorer.seed().to_string()),
+                    KeyCode::Char(c) => explorer.push_digit(c),
+                    KeyCode::Backspace => explorer.pop_digit(),
+                    KeyCode::Enter => explorer.apply_input(),
+                    KeyCode::Up => explorer.scroll_up(),
+                    KeyCode::Down => explorer.scroll_down(),
+                    _ => {}
+                }
+                Screen::Explorer(explorer)
+            }
+            Screen::Editor(_) if key == KeyCode::Char('q') => self.leave(),
+            Screen::Editor(mut editor) => {
+                match key {
+                    KeyCode::Char('s') => editor.save(),
+                    KeyCode::Char('a') => editor.insert_row(),
+                    KeyCode::Char('d') => editor.delete_row(),
+                    KeyCode::Char('g') => editor.set_green(),
+                    KeyCode::Char('r') => editor.set_road(),
+                    KeyCode::Char('t') => editor.set_rail(),
+                    KeyCode::Char('e') => editor.set_empty(),
+                    KeyCode::Char(' ') => editor.toggle_cell(),
+                    KeyCode::Char('f') => editor.flip_direction(),
+                    KeyCode::Char('+') => editor.adjust_cycle(5),
+                    KeyCode::Char('-') => editor.adjust_cycle(-5),
+                    KeyCode::Up => editor.cursor_up(),
+                    KeyCode::Down => editor.cursor_down(),
+                    KeyCode::Left => editor.cursor_left(),
+                    KeyCode::Right => editor.cursor_right(),
+                    _ => {}
+                }
+                Screen::Editor(editor)
+            }
+        };
+    }
+
+    fn main_menu_key(&mut self, key: KeyCode) -> Screen {
+        match key {
+            KeyCode::Char('q') => self.should_quit = true,
+            KeyCode::Up => self.main_menu.up(),
+            KeyCode::Down => self.main_menu.down(),
+            KeyCode::Enter if self.main_menu.is_selected_locked() => {}
+            KeyCode::Enter => return match self.main_menu.selected() {
+                MainMenuEntry::Play(mode, difficulty) => match mode.create_map(Stats::load().adapt(difficulty)) {
+                    Ok(map) => Screen::Game(Game::new(mode, map.with_character(self.profile.character), self.profile.speed)),
+                    Err(err) => {
+                        self.status = err.to_string();
+                        Screen::MainMenu
+                    }
+                },
+                MainMenuEntry::Coop => {
+                    let character = self.profile.character;
+                    let map = MapState::new().with_character(character).with_second_player(character.partner());
+                    Screen::Game(Game::new(GameMode::Endless, map, self.profile.speed))
+                }
+                MainMenuEntry::Versus => {
+                    let character = self.profile.character;
+                    Screen::Versus(Box::new(Versus::new([character, character.partner()])))
+                }
+                MainMenuEntry::Explore => Screen::Explorer(SeedExplorer::new()),
+                MainMenuEntry::Editor if self.guest => {
+                    self.status = self.profile.theme.language.text(Text::EditorUnavailable).to_string();
+                    Screen::MainMenu
+                }
+                MainMenuEntry::Editor => match LevelEditor::open(Path::new(CUSTOM_LEVEL)) {
+                    Ok(editor) => Screen::Editor(Box::new(editor)),
+                    Err(err) => {
+                        self.status = err.to_string();
+                        Screen::MainMenu
+                    }
+                },
+                MainMenuEntry::Layout => layout_prompt(Screen::MainMenu, self.profile.theme.language),
+                MainMenuEntry::Character => character_prompt(self.profile.character, self.profile.theme.language, &self.unlocks),
+                MainMenuEntry::Profile if self.guest => {
+                    self.status = self.profile.theme.language.text(Text::ProfilesUnavailable).to_string();
+                    Screen::MainMenu
+                }
+                MainMenuEntry::Profile => profile_prompt(self.profile.theme.language),
+                MainMenuEntry::Shop if self.guest => {
+                    self.status = self.profile.theme.language.text(Text::ShopUnavailable).to_string();
+                    Screen::MainMenu
+                }
+                MainMenuEntry::Shop => shop(self.profile.theme.language, &self.wallet, 0),
+                MainMenuEntry::Quit => {
+                    self.should_quit = true;
+                    Screen::MainMenu
+                }
+            },
+            _ => {}
+        }
+        Screen::MainMenu
+    }
+
+    /// Switches to the profile called `name`, [None] for the default one, and starts over like
+    /// the app does with it. Returns the screen to start at.
+    fn switch_profile(&mut self, name: Option<String>) -> Screen {
+        profile::select(name.as_deref());
+        let mut app = App::new(Profile::load());
+        let screen = std::mem::replace(&mut app.screen, Screen::MainMenu);
+        let language = app.profile.theme.language;
+        let shown = name.unwrap_or_else(|| language.text(Text::DefaultProfile).to_string());
+        *self = App {
+            records: self.records,
+            width: self.width,
+            height: self.height,
+            debug: self.debug,
+            timings: self.timings,
+            status: language.format(Text::PlayingAs, &[&shown]),
+            ..app
+        };
+        screen
+    }
+
+    /// Buys `item` if it isn't owned yet and puts it on or takes it off, staying in the shop at
+    /// entry `selected`.
+    fn shop(&mut self, item: Item, selected: usize) -> Screen {
+        let bought = self.wallet.buy(item);
+        self.wallet.toggle(item);
+        if let Err(err) = self.wallet.save() {
+            self.status = format!("Saving the shop failed: {err}");
+        } else if bought {
+            let language = self.profile.theme.language;
+            self.status = language.format(Text::Bought, &[&item.title(language)]);
+        }
+        self.refresh_main_menu(&Stats::load());
+        shop(self.profile.theme.language, &self.wallet, selected)
+    }
+
+    /// Plays the sounds for what happened in `game` since the last call and logs it for the
+    /// stats.
+    fn handle_events(&mut self, game: &mut Game) {
+        for event in game.map.take_events() {
+            #[cfg(feature = "logging")]
+            log_event(event, &game.map);
+            if let Some(sound) = sound_of(event) {
+                self.sounds.push(sound);
+            }
+            if let GameEvent::RowCrossed { player, y, points, .. } = event && points > 0 {
+                game.popups.push(Popup::new(points, game.map.player_x_of(player), y));
+            }
+            game.stats.count(event);
+        }
+    }
+
+    /// Records the result of `game` and shows the summary if the run is over.
+    fn finish_game(&mut self, game: Box<Game>) -> Screen {
+        #[cfg(feature = "serde")]
+        if self.autosaves(&game) {
+            self.clear_autosave();
+        }
+        if let Err(err) = game.map.write_rng_audit() {
+            self.status = format!("Writing the rng audit log failed: {err}");
+        }
+        if self.records {
+            let mut stats = Stats::load();
+            stats.record(&game.map, &game.stats);
+            if let Err(err) = stats.save() {
+                self.status = format!("Saving the stats failed: {err}");
+            }
+            if game.table.is_some() {
+                self.earn(game.map.score());
+            }
+            self.unlock(&stats);
+        }
+        self.last_score = Some(game.map.score());
+        let rank = game.table.as_ref().filter(|_| self.records).and_then(|table| self.high_scores.submit(table, game.map.score()));
+        if rank.is_some() && let Err(err) = self.high_scores.save() {
+            self.status = format!("Saving the high scores failed: {err}");
+        }
+        if !game.map.alive || game.map.finished {
+            Screen::Summary { game, ticks: 0, rank }
+        } else {
+            self.leave()
+        }
+    }
+
+    /// Unlocks what `stats` earned and updates the main menu with what is left to do.
+    fn unlock(&mut self, stats: &Stats) {
+        let unlocked = self.unlocks.update(stats);
+        if let Err(err) = self.unlocks.save() {
+            self.status = format!("Saving the unlocks failed: {err}");
+        } else if !unlocked.is_empty() {
+            let language = self.profile.theme.language;
+            let titles = unlocked.iter().map(|item| item.title(language)).collect::<Vec<_>>();
+            self.status = language.format(Text::Unlocked, &[&titles.join(", ")]);
+        }
+        self.refresh_main_menu(stats);
+    }
+
+    /// Adds the coins a run reaching `score` earned.
+    fn earn(&mut self, score: u64) {
+        let coins = self.wallet.earn(score);
+        if coins == 0 {
+            return;
+        }
+        if let Err(err) = self.wallet.save() {
+            self.status = format!("Saving the coins failed: {err}");
+        } else {
+            self.status = self.profile.theme.language.format(Text::Earned, &[&coins]);
+        }
+    }
+
+    /// Updates the main menu with what is left to unlock according to `stats` and the coins,
+    /// keeping the selected entry.
+    fn refresh_main_menu(&mut self, stats: &Stats) {
+        let selected = self.main_menu.selected_index();
+        self.main_menu = main_menu_with(self.profile.theme.language, &self.unlocks, &self.wallet, stats).with_selected(selected);
+    }
+
+    /// The screen to show after leaving the current one.
+    fn leave(&mut self) -> Screen {
+        if self.single_screen {
+            self.should_quit = true;
+        }
+        Screen::MainMenu
+    }
+
+    /// Saves the current frame, unless played on a server.
+    fn screenshot(&mut self) {
+        if self.guest {
+            return;
+        }
+        self.status = match screenshot::save(&self.view().grid) {
+            Ok(path) => format!("Saved a screenshot to {}", path.display()),
+            Err(err) => format!("Saving the screenshot failed: {err}"),
+        };
+    }
+
+    /// Saves the frames recorded in `game` as a GIF, once.
+    #[cfg(feature = "gif")]
+    fn save_gif(&mut self, game: &mut Game) {
+        let Some(recording) = game.recording.take() else { return };
+        self.status = match recording.save() {
+            Ok(path) => format!("Saved a GIF of the run to {}", path.display()),
+            Err(err) => format!("Saving the GIF failed: {err}"),
+        };
+    }
+
+    /// Quits right away, keeping the current game: it is saved to be resumed where that is
+    /// possible, otherwise it ends like a quit run and goes into the stats and high scores.
+    pub fn shutdown(&mut self) {
+        let screen = std::mem::replace(&mut self.screen, Screen::MainMenu);
+        if let Screen::Game(game) | Screen::Paused(game) | Screen::Help { game, .. } | Screen::Dying { game, .. } = screen {
+            #[cfg(feature = "serde")]
+            if game.map.alive && self.autosaves(&game) {
+                self.save_game(&game);
+                self.should_quit = true;
+                return;
+            }
+            self.finish_game(game);
+        }
+        self.should_quit = true;
+    }
+
+    /// Saves the current game, so it can be resumed if the app doesn't get to finish it. Frontends
+    /// call this before shutting down, games save themselves every [AUTOSAVE_TICKS] on top.
+    #[cfg(feature = "serde")]
+    pub fn autosave(&mut self) {
+        let screen = std::mem::replace(&mut self.screen, Screen::MainMenu);
+        if let Screen::Game(game) | Screen::Paused(game) | Screen::Help { game, .. } = &screen {
+            self.save_game(game);
+        }
+        self.screen = screen;
+    }
+
+    /// Whether `game` is saved to be resumed. Replays, games of guests, races over the network and
+    /// the tutorial aren't, and neither are games before the first move.
+    #[cfg(feature = "serde")]
+    fn autosaves(&self, game: &Game) -> bool {
+        self.records && !self.guest && game.remote.is_none() && game.mode != GameMode::Tutorial && game.map.started()
+    }
+
+    #[cfg(feature = "serde")]
+    fn save_game(&mut self, game: &Game) {
+        self.autosave_ticks = 0;
+        if !self.autosaves(game) {
+            return;
+        }
+        let autosave = Autosave { mode: game.mode.clone(), map: game.map.clone(), stats: game.stats };
+        if let Err(err) = autosave.save() {
+            self.status = format!("Saving the game failed: {err}");
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    fn clear_autosave(&mut self) {
+        if let Err(err) = Autosave::clear() {
+            self.status = format!("Removing the saved game failed: {err}");
+        }
+    }
+
+    fn save_profile(&mut self) {
+        if self.guest {
+            return;
+        }
+        if let Err(err) = self.profile.save() {
+            self.status = format!("Saving the profile failed: {err}");
+        }
+    }
+
+    /// Columns per tile of the boards that fit the terminal, [None] if not even one column does.
+    fn tile_len(&self) -> Option<usize> {
+        (1..=TILE_WIDTH).rev().find(|len| VISIBLE_TILES * len <= self.width as usize)
+    }
+
+    /// Whether the terminal is too narrow for a bordered board, which shrinks the HUD.
+    fn is_narrow(&self) -> bool {
+        (self.width as usize) < VISIBLE_TILES * TILE_WIDTH + 2
+    }
+
+    /// The theme of boards, without emoji if they are narrowed, see [stripe::narrow].
+    fn board_theme(&self) -> Theme {
+        let mut theme = self.profile.theme;
+        if self.tile_len().is_some_and(|len| len < TILE_WIDTH) && theme.glyphs == Glyphs::Emoji {
+            theme.glyphs = Glyphs::Blocks;
+        }
+        theme
+    }
+
+    /// `board` narrowed to fit the terminal.
+    fn fit(&self, board: Grid) -> Grid {
+        stripe::narrow(board, self.tile_len().unwrap_or(1))
+    }
+
+    /// The status of `game` above `board` and `hints` below it. In narrow terminals the board
+    /// has no border and the hints only show the keys.
+    fn hud(&self, game: &Game, board: Grid, hints: &[(String, String)]) -> Grid {
+        let language = self.profile.theme.language;
+        let mut status = vec![language.format(Text::Score, &[&game.map.score()])];
+        if let Some(best) = game.table.as_ref().and_then(|table| self.high_scores.best(table)) {
+            status.push(language.format(Text::Best, &[&best]));
+        }
+        if let Some(seconds) = game.map.seconds_left() {
+            status.push(language.format(Text::TimeLeft, &[&seconds]));
+        }
+        if let Some(combo) = game.map.combo() {
+            status.push(language.format(Text::Combo, &[&combo]));
+        }
+        if let Some(remote) = game.remote {
+            status.push(opponent(remote, language));
+        }
+        let separator = if self.is_narrow() { "\n" } else { "  " };
+        let mut grid = Grid::from_text(&status.join(separator));
+        let glyphs = self.profile.theme.glyphs;
+        let mut effects = Vec::new();
+        if let Some(lives) = game.map.lives() {
+            let heart = if glyphs == Glyphs::Ascii { "<3" } else { "♥" };
+            effects.push(format!("{heart} {lives}"));
+        }
+        effects.extend(game.map.effects().into_iter()
+            .map(|(effect, seconds)| format!("{} {} {seconds}s", effect.icon(glyphs), effect.name())));
+        grid.push_text(&effects.join(separator));
+        if self.profile.screen_reader || self.is_narrow() {
+            grid.append(board);
+        } else {
+            grid.append(board.bordered(self.profile.theme.glyphs == Glyphs::Ascii));
+        }
+        if !hints.is_empty() && self.is_narrow() {
+            let keys = hints.iter().map(|(key, _)| key.as_str()).collect::<Vec<_>>();
+            grid.push_text(&keys.join(" "));
+        } else if !hints.is_empty() {
+            let hints = hints.iter().map(|(key, action)| format!("{key}: {action}")).collect::<Vec<_>>();
+            grid.push_text(&hints.join("  "));
+        }
+        grid
+    }
+
+    /// The final board of `game` with the code to play it again, or why there is none, followed
+    /// by the other players score in races over the network.
+    fn summary_board(&self, game: &Game) -> Grid {
+        let mut grid = game.map.render(&self.profile.theme);
+        if let Some(code) = game.seed_code {
+            grid.push_text(&self.profile.theme.language.format(Text::SeedCode, &[&code]));
+        } else if !game.map.is_level() {
+            grid.push_text(self.profile.theme.language.text(Text::NoSeedCode));
+        }
+        if let Some(remote) = game.remote {
+            grid.push_text(&opponent(remote, self.profile.theme.language));
+        }
+        #[cfg(feature = "gif")]
+        match &game.recording {
+            Some(recording) if !recording.is_empty() && !self.guest => grid.push_text(self.profile.theme.language.text(Text::GifPrompt)),
+            Some(_) => {}
+            None => grid.push_text(&self.status),
+        }
+        grid
+    }
+
+    /// The keys of `game`, what the colors of the board mean and the mode with its options.
+    fn help(&self, game: &Game) -> Grid {
+        let language = self.profile.theme.language;
+        let mut keys = if game.map.player_count() > 1 {
+            self.player_hints(language)
+        } else {
+            vec![(self.movement_keys(), language.text(Text::Move).to_string())]
+        };
+        keys.push((self.keymap.key_name(Action::Pause), language.text(Text::Pause).to_string()));
+        if game.mode == GameMode::Casual {
+            keys.push((self.keymap.key_name(Action::Undo), language.text(Text::Undo).to_string()));
+        }
+        keys.push((self.keymap.key_name(Action::Help), language.text(Text::Help).to_string()));
+        keys.push((self.keymap.key_name(Action::Quit), language.text(Text::Quit).to_lowercase()));
+        let mut grid = Grid::from_text(language.text(Text::HelpKeys));
+        for (key, action) in keys {
+            grid.push_text(&format!("  {key}: {action}"));
+        }
+        grid.push_text(&format!("\n{}", language.text(Text::HelpColors)));
+        for block in Block::ALL {
+            let mut line = text("  ");
+            line.extend([Cell::colored('█', self.profile.theme.color(block)); 2]);
+            line.extend(text(&format!(" {}", language.text(Text::Legend(block)))));
+            grid.push_line(line);
+        }
+        grid.push_text(&format!("\n{}", language.format(Text::HelpMode, &[&game.title(language)])));
+        grid.push_text(language.text(Text::HelpClose));
+        grid
+    }
+
+    /// Which keys move which player in co-op and versus.
+    fn player_hints(&self, language: Language) -> Vec<(String, String)> {
+        vec![
+            (self.movement_keys(), language.format(Text::Player, &[&1])),
+            (language.text(Text::ArrowKeys).to_string(), language.format(Text::Player, &[&2])),
+        ]
+    }
+
+    /// The keys for hopping up, left, down and right, e.g. `wasd`.
+    fn movement_keys(&self) -> String {
+        [Action::Up, Action::Left, Action::Down, Action::Right].map(|action| self.keymap.key_name(action)).concat()
+    }
+
+    pub fn view(&self) -> Frame {
+        let language = self.profile.theme.language;
+        let mut frame = Frame {
+            grid: Grid::new(),
+            title: "Crossy Terminal".to_string(),
+            progress: None,
+            scroll: 0,
+        };
+        frame.grid = match &self.screen {
+            Screen::MainMenu => {
+                let mut grid = self.main_menu.render(language);
+                grid.push_text(&self.status);
+                grid
+            }
+            Screen::LayoutPrompt { menu, .. } => menu.render(language),
+            Screen::CharacterPrompt(menu) => menu.render(language),
+            Screen::ProfilePrompt(menu) => menu.render(language),
+            Screen::Shop(menu) => menu.render(language),
+            Screen::ProfileName(name) => Grid::from_text(&format!("{}\n\n> {name}_\n\n{}", language.text(Text::ProfileName), language.text(Text::ProfileNameHint))),
+            #[cfg(feature = "serde")]
+            Screen::ResumePrompt(menu) => menu.render(language),
+            Screen::Game(game) => {
+                frame.title = format!("Crossy Terminal - {} - {}", game.title(language), language.format(Text::Score, &[&game.map.score()]));
+                if game.mode.shows_progress() && let Some(table) = &game.table
+                    && let Some(best) = self.high_scores.best(table).filter(|best| *best > 0) {
+                    frame.progress = Some((game.map.score() * 100 / best).min(100) as u8);
+                }
+                let mut hints = vec![(self.movement_keys(), language.text(Text::Move).to_string())];
+                if game.map.player_count() > 1 {
+                    hints = self.player_hints(language);
+                }
+                hints.extend([
+                    (self.keymap.key_name(Action::Pause), language.text(Text::Pause).to_string()),
+                    (self.keymap.key_name(Action::Help), language.text(Text::Help).to_string()),
+                    (self.keymap.key_name(Action::Quit), language.text(Text::Quit).to_lowercase()),
+                ]);
+                if game.mode == GameMode::Casual {
+                    hints.insert(hints.len() - 1, (self.keymap.key_name(Action::Undo), language.text(Text::Undo).to_string()));
+                }
+                let board = if self.profile.screen_reader {
+                    Grid::from_text(&game.narration)
+                } else if let Some(remote) = game.remote {
+                    let markers = remote.marker_column(game.map.visible_rows(), self.profile.theme.glyphs);
+                    self.fit(game.board(&self.board_theme(), &self.wallet)).beside(markers, 1)
+                } else {
+                    self.fit(game.board(&self.board_theme(), &self.wallet))
+                };
+                let mut grid = self.hud(game, board, &hints);
+                if let Some(lesson) = game.lesson {
+                    let keys = [Action::Up, Action::Left, Action::Down, Action::Right].map(|action| self.keymap.key_name(action));
+                    grid.push_text(&lesson.prompt(&keys));
+                }
+                grid
+            }
+            Screen::Dying { game, ticks } => {
+                frame.title = format!("Crossy Terminal - {} - {}", game.title(language), language.format(Text::Score, &[&game.map.score()]));
+                let board = if self.profile.screen_reader {
+                    Grid::from_text(&game.narration)
+                } else {
+                    self.fit(game.map.render_crash(&self.board_theme(), *ticks))
+                };
+                self.hud(game, board, &[])
+            }
+            Screen::Paused(game) => {
+                frame.title = format!("Crossy Terminal - {} - {}", game.title(language), language.text(Text::Paused));
+                let hints = [
+                    (self.keymap.key_name(Action::Pause), language.text(Text::Continue).to_string()),
+                    (self.keymap.key_name(Action::Help), language.text(Text::Help).to_string()),
+                    (self.keymap.key_name(Action::Quit), language.text(Text::Quit).to_lowercase()),
+                ];
+                self.hud(game, self.fit(game.map.render(&self.board_theme())), &hints)
+            }
+            Screen::Help { game, .. } => {
+                frame.title = format!("Crossy Terminal - {} - {}", game.title(language), language.text(Text::Paused));
+                let mut grid = self.hud(game, self.fit(game.map.render(&self.board_theme())), &[]);
+                let help = self.help(game).bordered(self.profile.theme.glyphs == Glyphs::Ascii);
+                let x = grid.width().saturating_sub(help.width()) / 2;
+                grid.overlay(&help, x, 2);
+                grid
+            }
+            Screen::Summary { game, .. } if game.snapshot.is_some() => {
+                let mut grid = self.summary_board(game);
+                grid.push_text(&format!("\n{}", language.text(Text::RewindPrompt)));
+                grid
+            }
+            Screen::Summary { game, .. } if game.table.is_none() => {
+                let mut grid = self.summary_board(game);
+                grid.push_text(&format!("\n{}", language.text(Text::ContinuePrompt)));
+                grid
+            }
+            Screen::Summary { game, rank, .. } => {
+                let table = game.table.as_deref().unwrap_or_default();
+                let mut grid = self.summary_board(game);
+                grid.push_text(&format!("\n{}", language.format(Text::HighScores, &[&game.title(language)])));
+                for (idx, score) in self.high_scores.table(table).iter().enumerate() {
+                    let marker = if Some(idx) == *rank { "> " } else { "  " };
+                    grid.push_text(&format!("{marker}{:>2}. {score}", idx + 1));
+                }
+                grid.push_text(&format!("\n{}", language.text(Text::ContinuePrompt)));
+                grid
+            }
+            Screen::Versus(versus) => {
+                frame.title = format!("Crossy Terminal - {}", language.text(Text::Versus));
+                let mut grid = versus.render(&self.profile.theme, self.profile.theme.glyphs == Glyphs::Ascii);
+                let mut hints = self.player_hints(language);
+                hints.push((self.keymap.key_name(Action::Quit), language.text(Text::Quit).to_lowercase()));
+                let hints = hints.iter().map(|(key, action)| format!("{key}: {action}")).collect::<Vec<_>>();
+                grid.push_text(&hints.join("  "));
+                grid
+            }
+            Screen::VersusResults { versus, .. } => {
+                frame.title = format!("Crossy Terminal - {}", language.text(Text::Versus));
+                let mut grid = versus.render(&self.profile.theme, self.profile.theme.glyphs == Glyphs::Ascii);
+                grid.push_text(&format!("\n{}\n\n{}", versus.results(language), language.text(Text::ContinuePrompt)));
+                grid
+            }
+            Screen::Explorer(explorer) => explorer.render(self.height as usize, &self.profile.theme),
+            Screen::Editor(editor) => editor.render(self.height as usize, &self.profile.theme),
+        };
+        if let Screen::Game(game) | Screen::Paused(game) | Screen::Help { game, .. } | Screen::Dying { game, .. } = &self.screen
+            && !self.profile.theme.accessibility.reduced_motion {
+            frame.scroll = game.map.visible_rows().start;
+        }
+        if self.debug {
+            let map = match &self.screen {
+                Screen::Game(game) | Screen::Paused(game) | Screen::Help { game, .. } | Screen::Dying { game, .. } | Screen::Summary { game, .. } => Some(&game.map),
+                _ => None,
+            };
+            let grid = std::mem::take(&mut frame.grid);
+            frame.grid = grid.beside(debug::overlay(self.timings, map), 2);
+        }
+        if self.is_narrow() {
+            let in_run = matches!(self.screen, Screen::Game(_) | Screen::Paused(_) | Screen::Help { .. } | Screen::Dying { .. });
+            if self.tile_len().is_none() && in_run {
+                frame.grid = Grid::from_text(&language.format(Text::TooNarrow, &[&VISIBLE_TILES]).replace(' ', "\n"));
+            }
+            frame.grid.clip(self.width as usize);
+        }
+        frame
+    }
+}
+
+fn main_menu(language: Language, unlocks: &Unlocks, wallet: &Wallet) -> Menu<MainMenuEntry> {
+    main_menu_with(language, unlocks, wallet, &Stats::load())
+}
+
+/// The main menu with the entries `unlocks` lacks greyed out, with what is left to do according
+/// to `stats`, and the coins in `wallet`.
+fn main_menu_with(language: Language, unlocks: &Unlocks, wallet: &Wallet, stats: &Stats) -> Menu<MainMenuEntry> {
+    let mut modes = vec![GameMode::Endless];
+    modes.extend(TIME_ATTACK_SECONDS.map(|seconds| GameMode::TimeAttack { seconds }));
+    modes.push(GameMode::daily());
+    modes.push(GameMode::Zen);
+    modes.push(GameMode::Practice);
+    modes.push(GameMode::Casual);
+    modes.push(GameMode::Night);
+    modes.push(GameMode::Tutorial);
+    let mut entries = modes.into_iter()
+        .map(|mode| (mode.title(language), MainMenuEntry::Play(mode, Difficulty::NORMAL)))
+        .collect::<Vec<_>>();
+    let text = |text| language.text(text).to_string();
+    entries.insert(1, (text(Text::Hardcore), MainMenuEntry::Play(GameMode::Endless, Difficulty::HARDCORE)));
+    entries.insert(2, (text(Text::Adaptive), MainMenuEntry::Play(GameMode::Endless, Difficulty::ADAPTIVE)));
+    entries.insert(3, (text(Text::Coop), MainMenuEntry::Coop));
+    entries.insert(4, (text(Text::Versus), MainMenuEntry::Versus));
+    for (name, _) in BUNDLED {
+        let mode = GameMode::Level(name.to_string());
+        entries.push((language.format(Text::Level, &[&name]), MainMenuEntry::Play(mode, Difficulty::NORMAL)));
+    }
+    entries.push((text(Text::SeedExplorer), MainMenuEntry::Explore));
+    entries.push((language.format(Text::LevelEditor, &[&CUSTOM_LEVEL]), MainMenuEntry::Editor));
+    entries.push((text(Text::KeyboardLayout), MainMenuEntry::Layout));
+    entries.push((text(Text::Character), MainMenuEntry::Character));
+    let name = profile::selected().unwrap_or_else(|| text(Text::DefaultProfile));
+    entries.push((language.format(Text::Profile, &[&name]), MainMenuEntry::Profile));
+    entries.push((language.format(Text::Shop, &[&wallet.coins]), MainMenuEntry::Shop));
+    entries.push((text(Text::Quit), MainMenuEntry::Quit));
+    Menu::new("Crossy Terminal", entries).with_locks(|entry| {
+        let MainMenuEntry::Play(mode, difficulty) = entry else { return None };
+        [mode.name().map(Unlockable::Mode), Some(Unlockable::Difficulty(difficulty.name))].into_iter()
+            .flatten()
+            .find_map(|item| unlocks.requirement(item))
+            .map(|requirement| requirement.describe(stats, language))
+    })
+}
+
+/// Logs `event` of a run on `map` and dumps the run if it ended with it.
+#[cfg(feature = "logging")]
+fn log_event(event: GameEvent, map: &MapState) {
+    tracing::info!("{event:?}");
+    if let GameEvent::Death { cause, .. } = event && !map.alive
+        && let Err(err) = logging::dump(&format!("death ({cause:?})")) {
+        tracing::error!("Writing the dump failed: {err}");
+    }
+}
+
+/// The sound played when `event` happens.
+fn sound_of(event: GameEvent) -> Option<Sound> {
+    match event {
+        GameEvent::RowCrossed { score, points, .. } if (score - points) / CHIME_SCORE < score / CHIME_SCORE => Some(Sound::Chime),
+        GameEvent::TrainWarning => Some(Sound::TrainHorn),
+        GameEvent::Death { cause: DeathCause::HitByCar | DeathCause::HitByTrain, .. } => Some(Sound::Crash),
+        _ => None,
+    }
+}
+
+/// The score of the other player in a race over the network.
+fn opponent(remote: Progress, language: Language) -> String {
+    if remote.alive {
+        language.format(Text::Opponent, &[&remote.score])
+    } else {
+        language.format(Text::OpponentOut, &[&remote.score])
+    }
+}
+
+/// Asks which keyboard layout movement keys should be chosen for, then continues with `next`.
+fn layout_prompt(next: Screen, language: Language) -> Screen {
+    let entries = KeyboardLayout::ALL.iter()
+        .map(|layout| {
+            let keys = layout.movement_keys().iter().collect::<String>().to_uppercase();
+            (format!("{} ({keys})", layout.name()), *layout)
+        })
+        .collect();
+    let detected = KeyboardLayout::detect()
+        .and_then(|detected| KeyboardLayout::ALL.iter().position(|layout| *layout == detected))
+        .unwrap_or_default();
+    Screen::LayoutPrompt {
+        menu: Menu::new(language.text(Text::ChooseLayout), entries).with_selected(detected),
+        next: Box::new(next),
+    }
+}
+
+/// The main menu, or the tutorial if no game was played yet.
+fn first_screen(profile: &Profile) -> Screen {
+    if Stats::load().games_played == 0 {
+        Screen::Game(Game::new(GameMode::Tutorial, tutorial::map().with_character(profile.character), profile.speed))
+    } else {
+        Screen::MainMenu
+    }
+}
+
+/// Asks whether to resume the game in `autosave` or discard it.
+#[cfg(feature = "serde")]
+fn resume_prompt(autosave: Autosave, language: Language) -> Screen {
+    let resume = language.format(Text::Resume, &[&autosave.mode.title(language), &autosave.map.score()]);
+    let entries = vec![(resume, Some(Box::new(autosave))), (language.text(Text::Discard).to_string(), None)];
+    Screen::ResumePrompt(Menu::new(language.text(Text::InterruptedRun), entries))
+}
+
+/// Asks which profile to play as, starting at the current one, or for a new one.
+fn profile_prompt(language: Language) -> Screen {
+    let mut entries = vec![(language.text(Text::DefaultProfile).to_string(), ProfileEntry::Select(None))];
+    entries.extend(profile::names().into_iter().map(|name| (name.clone(), ProfileEntry::Select(Some(name)))));
+    let selected = entries.iter().position(|(_, entry)| matches!(entry, ProfileEntry::Select(name) if *name == profile::selected())).unwrap_or_default();
+    entries.push((language.text(Text::NewProfile).to_string(), ProfileEntry::New));
+    Screen::ProfilePrompt(Menu::new(language.text(Text::ChooseProfile), entries).with_selected(selected))
+}
+
+/// Offers the cosmetics of the shop, starting at entry `selected`. Items `wallet` can't afford are
+/// greyed out.
+fn shop(language: Language, wallet: &Wallet, selected: usize) -> Screen {
+    let entries = Item::ALL.into_iter()
+        .map(|item| {
+            let title = item.title(language);
+            let label = if wallet.is_worn(item) {
+                language.format(Text::ItemWorn, &[&title])
+            } else if wallet.owns(item) {
+                language.format(Text::ItemOwned, &[&title])
+            } else {
+                language.format(Text::ItemPrice, &[&title, &item.price()])
+            };
+            (label, item)
+        })
+        .collect();
+    let menu = Menu::new(&language.format(Text::ShopTitle, &[&wallet.coins]), entries)
+        .with_locks(|item| (!wallet.owns(*item) && wallet.coins < item.price())
+            .then(|| language.format(Text::RequireCoins, &[&(item.price() - wallet.coins)])))
+        .with_selected(selected);
+    Screen::Shop(menu)
+}
+
+/// Asks which character to cross the road as, starting at the `current` one.
+fn character_prompt(current: Character, language: Language, unlocks: &Unlocks) -> Screen {
+    let entries = Character::ALL.iter()
+        .map(|character| (character.title().to_string(), *character))
+        .collect();
+    let selected = Character::ALL.iter().position(|character| *character == current).unwrap_or_default();
+    let stats = Stats::load();
+    let menu = Menu::new(language.text(Text::ChooseCharacter), entries)
+        .with_locks(|character| unlocks.requirement(Unlockable::Character(*character)).map(|requirement| requirement.describe(&stats, language)))
+        .with_selected(selected);
+    Screen::CharacterPrompt(menu)
+}
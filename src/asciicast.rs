@@ -0,0 +1,65 @@
+//! Turns recorded runs into asciinema recordings.
+//!
+//! A run is recorded as an input script with `--record`, see [crate::input]. Playing the script
+//! again on the same seed gives the same run, so [export] simulates it without a terminal and
+//! writes every frame in the [asciicast v2](https://docs.asciinema.org/manual/asciicast/v2/)
+//! format, which `asciinema play` and the asciinema web player understand.
+use std::fmt::Write as _;
+use std::io::Write;
+use std::time::Duration;
+use crate::app::{App, Event};
+use crate::diff::DiffRenderer;
+use crate::input::{InputSource, ScriptedInput};
+use crate::map::TICKS_PER_SECOND;
+
+/// How long the recording goes on after the last key, to show how the run ended.
+const OUTRO_TICKS: u64 = 3 * TICKS_PER_SECOND;
+
+/// Plays `script` in `app` on a terminal of `width` × `height` cells and writes the frames to
+/// `out`. Stops once the app quits or [OUTRO_TICKS] after the last key, and fails if the run
+/// differs from its recording.
+pub fn export(app: &mut App, script: &mut ScriptedInput, width: u16, height: u16, out: &mut impl Write) -> std::io::Result<()> {
+    writeln!(out, "{{\"version\":2,\"width\":{width},\"height\":{height},\"title\":\"Crossy Terminal\"}}")?;
+    app.handle_event(Event::Resize { width, height });
+    let mut renderer = DiffRenderer::new();
+    let mut tick = 0;
+    let mut outro = OUTRO_TICKS;
+    loop {
+        while let Some(event) = script.next_event(tick, Duration::ZERO)? {
+            app.handle_event(event);
+        }
+        if app.should_quit() || outro == 0 {
+            break;
+        }
+        app.handle_event(Event::Tick);
+        if app.take_dirty() {
+            let mut output = Vec::new();
+            renderer.draw(&mut output, &app.view().grid)?;
+            let time = tick as f64 / TICKS_PER_SECOND as f64;
+            writeln!(out, "[{time:.3},\"o\",{}]", json_string(&String::from_utf8_lossy(&output)))?;
+        }
+        if script.is_finished() {
+            outro -= 1;
+        }
+        tick += 1;
+        script.verify(tick, app.state_hash()).map_err(std::io::Error::other)?;
+    }
+    out.flush()
+}
+
+/// `text` as a JSON string literal.
+fn json_string(text: &str) -> String {
+    let mut escaped = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            c if (c as u32) < 0x20 => write!(escaped, "\\u{:04x}", c as u32).unwrap(),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
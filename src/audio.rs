@@ -0,0 +1,71 @@
+//! Plays [Sound]s on the default audio device with [rodio].
+//!
+//! The sounds are synthesized from sine waves and the music from square waves, so no audio
+//! files have to be shipped.
+use std::time::Duration;
+use rodio::source::{self, SineWave, SquareWave};
+use rodio::{OutputStream, OutputStreamBuilder, Sink, Source};
+use crate::sound::{Sound, SoundSettings, Track};
+
+/// How much quieter music is than sound effects.
+const MUSIC_VOLUME: f32 = 0.3;
+
+pub struct Audio {
+    stream: OutputStream,
+    /// Amplification of every sound, 0 to 1.
+    volume: f32,
+    /// Whether [Audio::set_music] plays anything.
+    music_enabled: bool,
+    /// The track that is looping and the sink playing it.
+    music: Option<(Track, Sink)>,
+}
+
+impl Audio {
+    /// Opens the default output device, [None] if muted or there is none.
+    pub fn open(settings: SoundSettings) -> Option<Audio> {
+        if settings.muted {
+            return None;
+        }
+        let mut stream = OutputStreamBuilder::open_default_stream().ok()?;
+        stream.log_on_drop(false);
+        Some(Audio {
+            stream,
+            volume: settings.volume as f32 / 100.0,
+            music_enabled: settings.music,
+            music: None,
+        })
+    }
+
+    /// Starts playing `sound` without waiting for it to end.
+    pub fn play(&self, sound: Sound) {
+        let volume = self.volume;
+        let notes = sound.notes().iter().map(move |(frequency, millis)| {
+            SineWave::new(*frequency)
+                .take_duration(Duration::from_millis(*millis))
+                .amplify(volume)
+        });
+        self.stream.mixer().add(source::from_iter(notes));
+    }
+
+    /// Loops `track`, paused if it isn't playing, or stops the music for [None].
+    ///
+    /// The track restarts only when it changes, so pausing and resuming continues where it was.
+    pub fn set_music(&mut self, music: Option<(Track, bool)>) {
+        let Some((track, playing)) = music.filter(|_| self.music_enabled) else {
+            self.music = None;
+            return;
+        };
+        if self.music.as_ref().is_none_or(|(current, _)| *current != track) {
+            let sink = Sink::connect_new(self.stream.mixer());
+            sink.set_volume(self.volume * MUSIC_VOLUME);
+            let notes = track.notes().iter().map(|(frequency, millis)| {
+                SquareWave::new(*frequency).take_duration(Duration::from_millis(*millis))
+            });
+            sink.append(source::from_iter(notes).repeat_infinite());
+            self.music = Some((track, sink));
+        }
+        if let Some((_, sink)) = &self.music {
+            if playing { sink.play() } else { sink.pause() }
+        }
+    }
+}
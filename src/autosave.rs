@@ -0,0 +1,54 @@
+//! Keeps the running game on disk, so a run that was interrupted can be resumed on the next start.
+//!
+//! The app saves its game every [AUTOSAVE_TICKS] and when the frontend asks it to before shutting
+//! down, into [AUTOSAVE_FILE] inside [profile_dir]. The file is removed once the run is over.
+use serde::{Deserialize, Serialize};
+use crate::map::{MapState, TICKS_PER_SECOND};
+use crate::mode::GameMode;
+use crate::profile::profile_dir;
+use crate::serialization::{from_bytes, to_bytes};
+use crate::stats::RunStats;
+
+pub const AUTOSAVE_FILE: &str = "autosave.bin";
+
+/// Ticks between two autosaves.
+pub const AUTOSAVE_TICKS: u64 = 5 * TICKS_PER_SECOND;
+
+/// Written before the saved game, so saves of other versions are ignored instead of misread.
+const MAGIC: &[u8] = b"crossy-autosave 3\n";
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Autosave {
+    pub mode: GameMode,
+    pub map: MapState,
+    /// What the run added to the stats so far.
+    pub stats: RunStats,
+}
+
+impl Autosave {
+    /// The saved game, [None] if there is none or it can't be read.
+    pub fn load() -> Option<Autosave> {
+        let bytes = std::fs::read(profile_dir().join(AUTOSAVE_FILE)).ok()?;
+        from_bytes(bytes.strip_prefix(MAGIC)?).ok()
+    }
+
+    /// Replaces the saved game. Boards with scripted stripes can't be saved.
+    pub fn save(&self) -> std::io::Result<()> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend(to_bytes(self).map_err(std::io::Error::other)?);
+        std::fs::create_dir_all(profile_dir())?;
+        // Written next to the save first, so being killed in the middle keeps the last one.
+        let path = profile_dir().join(AUTOSAVE_FILE);
+        let partial = path.with_extension("partial");
+        std::fs::write(&partial, bytes)?;
+        std::fs::rename(partial, path)
+    }
+
+    /// Removes the saved game, if there is one.
+    pub fn clear() -> std::io::Result<()> {
+        match std::fs::remove_file(profile_dir().join(AUTOSAVE_FILE)) {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err),
+            _ => Ok(()),
+        }
+    }
+}
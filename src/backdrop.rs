@@ -0,0 +1,66 @@
+//! The background [crate::backend::CrosstermBackend] draws around frames.
+//!
+//! Far away stars and nearer clouds drift down as a run moves forward, slower than the board and
+//! at different speeds, so they read as depth without drawing attention. They are a layer of
+//! their own below the frame, which always covers them.
+use crate::color::Color;
+use crate::grid::{Cell, Grid};
+use crate::hash::hash_bytes;
+
+/// Background of the screen around frames.
+pub const COLOR: Color = Color::Rgb { r: 14, g: 14, b: 20 };
+
+const STAR: Color = Color::Rgb { r: 80, g: 80, b: 100 };
+
+const BRIGHT_STAR: Color = Color::Rgb { r: 140, g: 140, b: 160 };
+
+const CLOUD: Color = Color::Rgb { r: 24, g: 24, b: 34 };
+
+/// Rows the board scrolls per row of stars.
+const STAR_SLOWDOWN: u64 = 4;
+
+/// Rows the board scrolls per row of clouds.
+const CLOUD_SLOWDOWN: u64 = 2;
+
+/// One in this many cells holds a star.
+const STAR_RARITY: u64 = 60;
+
+/// Columns of the slots clouds may fill, one in [CLOUD_RARITY] does.
+const CLOUD_WIDTH: usize = 12;
+
+const CLOUD_RARITY: u64 = 6;
+
+/// The background of a screen of `width` × `height` cells after the board scrolled by `scroll`
+/// rows. The bottom right cell stays empty, since writing it scrolls some terminals.
+pub fn render(width: usize, height: usize, scroll: u64) -> Grid {
+    let mut grid = Grid::new();
+    for y in 0..height {
+        // Rows further up the screen are further ahead, like on the board.
+        let above = (height - y) as u64;
+        let star_row = above + scroll / STAR_SLOWDOWN;
+        let cloud_row = (above + scroll / CLOUD_SLOWDOWN) / 2;
+        let len = if y + 1 == height { width.saturating_sub(1) } else { width };
+        grid.push_line((0..len).map(|x| cell(x, star_row, cloud_row)).collect());
+    }
+    grid
+}
+
+fn cell(x: usize, star_row: u64, cloud_row: u64) -> Cell {
+    let slot = (x / CLOUD_WIDTH) as u64;
+    let cloudy = noise(1, slot, cloud_row).is_multiple_of(CLOUD_RARITY)
+        && (1..CLOUD_WIDTH - 1).contains(&(x % CLOUD_WIDTH));
+    let bg = if cloudy { CLOUD } else { COLOR };
+    match noise(0, x as u64, star_row) % STAR_RARITY {
+        0 => Cell { glyph: '.', fg: STAR, bg },
+        1 if !cloudy => Cell { glyph: '+', fg: BRIGHT_STAR, bg },
+        _ => Cell { glyph: ' ', fg: Color::Reset, bg },
+    }
+}
+
+/// A stable pseudo random number for column `x` of `row` in `layer`.
+fn noise(layer: u8, x: u64, row: u64) -> u64 {
+    let hash = hash_bytes(std::iter::once(layer).chain(x.to_le_bytes()).chain(row.to_le_bytes())).0;
+    // FNV leaves the low bits of neighboring columns alike, which would pair up stars.
+    let hash = (hash ^ (hash >> 31)).wrapping_mul(0xbf58476d1ce4e5b9);
+    hash ^ (hash >> 29)
+}
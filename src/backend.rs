@@ -0,0 +1,275 @@
+//! Ways of getting a [Grid] on the screen.
+//!
+//! The [DiffRenderer] works in every terminal. Builds with the `graphics` feature draw the board
+//! as pixels instead where the terminal supports it, see [crate::graphics].
+//!
+//! A [TerminalBackend] owns the whole terminal around that: entering and leaving the game's
+//! screen, drawing [Frame]s and reading [Event]s. [CrosstermBackend] drives the real terminal,
+//! centering frames on a [backdrop], [DummyBackend] stands in for it where there is none.
+use std::collections::VecDeque;
+use std::io::{Stdout, Write};
+use std::sync::OnceLock;
+use std::time::Duration;
+use crossterm::terminal::SetTitle;
+use crossterm::{cursor, event, terminal, ExecutableCommand, QueueableCommand};
+use crate::app::{Event, Frame};
+use crate::backdrop;
+use crate::color::{is_legacy_console, Color};
+use crate::diff::DiffRenderer;
+use crate::grid::{Cell, Grid};
+use crate::multiplexer::{Multiplexer, Passthrough, SetClipboard};
+use crate::title::{PopTitle, PushTitle, SetProgress};
+
+pub trait Backend {
+    /// Draws the next frame from scratch, e.g. because the terminal was resized.
+    fn invalidate(&mut self);
+
+    /// Queues the commands turning the previous frame into `grid`.
+    fn draw(&mut self, out: &mut dyn Write, grid: &Grid) -> std::io::Result<()>;
+}
+
+/// The best backend the current terminal supports.
+pub fn detect() -> Box<dyn Backend> {
+    #[cfg(feature = "graphics")]
+    if let Some(protocol) = crate::graphics::GraphicsProtocol::current() {
+        return Box::new(crate::graphics::GraphicsRenderer::new(protocol));
+    }
+    Box::new(DiffRenderer::new())
+}
+
+/// Whether the terminal understands synchronized updates, detected once. Frames are wrapped in
+/// them where it does, so the terminal never shows half drawn ones.
+///
+/// The Linux console and dumb or VT terminals print garbage for unknown private modes, everything
+/// else ignores them if it doesn't implement them.
+pub fn supports_synchronized_updates() -> bool {
+    static CURRENT: OnceLock<bool> = OnceLock::new();
+    *CURRENT.get_or_init(|| {
+        let term = std::env::var("TERM").unwrap_or_default();
+        Multiplexer::current().supports_synchronized_updates() && !is_legacy_console()
+            && !(term == "linux" || term == "dumb" || term.starts_with("vt"))
+    })
+}
+
+/// Whether `key` is Ctrl+C, which raw mode delivers as a key instead of SIGINT.
+pub fn is_interrupt(key: &event::KeyEvent) -> bool {
+    key.code == event::KeyCode::Char('c') && key.modifiers.contains(event::KeyModifiers::CONTROL)
+}
+
+/// Whether `key` is Ctrl+Z, which raw mode delivers as a key instead of SIGTSTP.
+pub fn is_suspend(key: &event::KeyEvent) -> bool {
+    key.code == event::KeyCode::Char('z') && key.modifiers.contains(event::KeyModifiers::CONTROL)
+}
+
+/// Everything the game needs from a terminal.
+pub trait TerminalBackend {
+    /// Prepares the terminal for the game, e.g. enables raw mode.
+    fn enter(&mut self) -> std::io::Result<()>;
+
+    /// Restores the terminal to how [TerminalBackend::enter] found it.
+    fn leave(&mut self) -> std::io::Result<()>;
+
+    /// Width and height in cells.
+    fn size(&self) -> std::io::Result<(u16, u16)>;
+
+    fn draw(&mut self, frame: &Frame) -> std::io::Result<()>;
+
+    /// The next event, waiting at most `timeout` for one. Only [Event::Key], [Event::Resize],
+    /// [Event::Interrupt] and [Event::Suspend] are returned, ticks are up to the caller.
+    fn poll_event(&mut self, timeout: Duration) -> std::io::Result<Option<Event>>;
+
+    fn set_clipboard(&mut self, text: &str) -> std::io::Result<()>;
+
+    /// Rings the terminal bell.
+    fn bell(&mut self) -> std::io::Result<()>;
+
+    /// Restores the terminal and stops the process like Ctrl+Z in a shell, then takes the terminal
+    /// back once the process continues. The next frame is drawn from scratch.
+    fn suspend(&mut self) -> std::io::Result<()>;
+
+    /// Takes the terminal back after the process was stopped without [TerminalBackend::suspend],
+    /// e.g. by SIGSTOP, and draws the next frame from scratch.
+    fn resume(&mut self) -> std::io::Result<()>;
+}
+
+/// The terminal the process runs in, drawn to with the best [Backend] it supports.
+pub struct CrosstermBackend {
+    out: Stdout,
+    renderer: Box<dyn Backend>,
+}
+
+impl Default for CrosstermBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CrosstermBackend {
+    pub fn new() -> Self {
+        CrosstermBackend { out: std::io::stdout(), renderer: detect() }
+    }
+
+    /// Makes panics leave raw mode and show the cursor again before they are reported, so the
+    /// message is readable and the shell usable after a crash.
+    pub fn leave_on_panic() {
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            let _ = terminal::disable_raw_mode();
+            let _ = std::io::stdout().execute(cursor::Show);
+            previous(info);
+        }));
+    }
+}
+
+impl TerminalBackend for CrosstermBackend {
+    fn enter(&mut self) -> std::io::Result<()> {
+        self.out.execute(cursor::Hide)?;
+        self.out.execute(PushTitle)?;
+        terminal::enable_raw_mode()
+    }
+
+    fn leave(&mut self) -> std::io::Result<()> {
+        terminal::disable_raw_mode()?;
+        self.out.queue(cursor::Show)?;
+        self.out.queue(Passthrough(SetProgress::Hidden))?;
+        self.out.execute(PopTitle)?;
+        Ok(())
+    }
+
+    fn size(&self) -> std::io::Result<(u16, u16)> {
+        terminal::size()
+    }
+
+    fn draw(&mut self, frame: &Frame) -> std::io::Result<()> {
+        let synchronized = supports_synchronized_updates();
+        if synchronized {
+            self.out.queue(terminal::BeginSynchronizedUpdate)?;
+        }
+        self.out.queue(SetTitle(&frame.title))?;
+        self.out.queue(Passthrough(match frame.progress {
+            Some(progress) => SetProgress::Normal(progress),
+            None => SetProgress::Hidden,
+        }))?;
+        let (width, height) = terminal::size()?;
+        let background = backdrop::render(width as usize, height as usize, frame.scroll);
+        let fill = Cell { glyph: ' ', fg: Color::Reset, bg: backdrop::COLOR };
+        let grid = frame.grid.clone().centered(background, fill);
+        self.renderer.draw(&mut self.out, &grid)?;
+        if synchronized {
+            self.out.queue(terminal::EndSynchronizedUpdate)?;
+        }
+        self.out.flush()
+    }
+
+    fn poll_event(&mut self, timeout: Duration) -> std::io::Result<Option<Event>> {
+        if !event::poll(timeout)? {
+            return Ok(None);
+        }
+        Ok(match event::read()? {
+            // Windows reports releasing keys as well, which would move the player twice.
+            event::Event::Key(key) if key.kind == event::KeyEventKind::Release => None,
+            event::Event::Key(key) if is_interrupt(&key) => Some(Event::Interrupt),
+            event::Event::Key(key) if is_suspend(&key) => Some(Event::Suspend),
+            event::Event::Key(key) => Some(Event::Key(key.code)),
+            event::Event::Resize(width, height) => {
+                self.renderer.invalidate();
+                Some(Event::Resize { width, height })
+            }
+            _ => None,
+        })
+    }
+
+    fn set_clipboard(&mut self, text: &str) -> std::io::Result<()> {
+        self.out.queue(SetClipboard(text))?;
+        Ok(())
+    }
+
+    fn bell(&mut self) -> std::io::Result<()> {
+        write!(self.out, "\x07")
+    }
+
+    fn suspend(&mut self) -> std::io::Result<()> {
+        // Other platforms have no job control to hand the terminal back to.
+        #[cfg(unix)]
+        {
+            self.leave()?;
+            self.out.flush()?;
+            signal_hook::low_level::emulate_default_handler(signal_hook::consts::SIGTSTP)?;
+            self.enter()?;
+            self.renderer.invalidate();
+        }
+        Ok(())
+    }
+
+    fn resume(&mut self) -> std::io::Result<()> {
+        self.out.execute(cursor::Hide)?;
+        terminal::enable_raw_mode()?;
+        self.renderer.invalidate();
+        Ok(())
+    }
+}
+
+/// A terminal of fixed size that replays queued events and keeps the frames drawn to it, e.g. to
+/// drive an [crate::app::App] without a terminal with [crate::input::drive].
+#[derive(Debug, Clone, Default)]
+pub struct DummyBackend {
+    pub width: u16,
+    pub height: u16,
+    /// Returned by [TerminalBackend::poll_event] in order.
+    pub events: VecDeque<Event>,
+    /// Every frame drawn, oldest first.
+    pub frames: Vec<Frame>,
+    pub clipboard: Option<String>,
+    /// How often the bell rang.
+    pub bells: usize,
+    /// How often the process was suspended.
+    pub suspends: usize,
+}
+
+impl DummyBackend {
+    pub fn new(width: u16, height: u16) -> Self {
+        DummyBackend { width, height, ..Self::default() }
+    }
+}
+
+impl TerminalBackend for DummyBackend {
+    fn enter(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    fn leave(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    fn size(&self) -> std::io::Result<(u16, u16)> {
+        Ok((self.width, self.height))
+    }
+
+    fn draw(&mut self, frame: &Frame) -> std::io::Result<()> {
+        self.frames.push(frame.clone());
+        Ok(())
+    }
+
+    fn poll_event(&mut self, _timeout: Duration) -> std::io::Result<Option<Event>> {
+        Ok(self.events.pop_front())
+    }
+
+    fn set_clipboard(&mut self, text: &str) -> std::io::Result<()> {
+        self.clipboard = Some(text.to_string());
+        Ok(())
+    }
+
+    fn bell(&mut self) -> std::io::Result<()> {
+        self.bells += 1;
+        Ok(())
+    }
+
+    fn suspend(&mut self) -> std::io::Result<()> {
+        self.suspends += 1;
+        Ok(())
+    }
+
+    fn resume(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,74 @@
+//! Regions of the board with their own look and mix of rows.
+//!
+//! The biome changes every [BIOME_ROWS] rows, cycling through [Biome::ALL]. It scales the
+//! generation weights of the [Difficulty](crate::difficulty::Difficulty), the amount of trees on
+//! grass and tints grass and trees.
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use crate::color::Color;
+
+/// Rows until the next biome starts.
+pub const BIOME_ROWS: u64 = 50;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Biome {
+    /// The original board.
+    #[default]
+    Meadow,
+    /// Sand with few cacti and lots of roads.
+    Desert,
+    /// Snow with more railroads.
+    Snow,
+    /// Dense thickets and few railroads.
+    Swamp,
+}
+
+impl Biome {
+    pub const ALL: [Biome; 4] = [Biome::Meadow, Biome::Desert, Biome::Snow, Biome::Swamp];
+
+    /// The biome of row `y`.
+    pub fn at(y: u64) -> Self {
+        Self::ALL[(y / BIOME_ROWS) as usize % Self::ALL.len()]
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Biome::Meadow => "meadow",
+            Biome::Desert => "desert",
+            Biome::Snow => "snow",
+            Biome::Swamp => "swamp",
+        }
+    }
+
+    /// `weights` of grass, railroad and road rows adjusted for this biome.
+    pub fn stripe_weights(self, weights: [u32; 3]) -> [u32; 3] {
+        let percent = match self {
+            Biome::Meadow => [100, 100, 100],
+            Biome::Desert => [60, 100, 160],
+            Biome::Snow => [100, 160, 60],
+            Biome::Swamp => [160, 60, 100],
+        };
+        std::array::from_fn(|idx| weights[idx] * percent[idx] / 100)
+    }
+
+    /// Chance of a tree on each tile of a grass row, in percent.
+    pub fn tree_percent(self) -> u32 {
+        match self {
+            Biome::Meadow => 50,
+            Biome::Desert => 20,
+            Biome::Snow => 40,
+            Biome::Swamp => 65,
+        }
+    }
+
+    /// The colors grass and trees are tinted toward, [None] to keep the themes colors.
+    pub fn tint(self) -> Option<(Color, Color)> {
+        match self {
+            Biome::Meadow => None,
+            Biome::Desert => Some((Color::Rgb { r: 0xd8, g: 0xc0, b: 0x78 }, Color::Rgb { r: 0x5a, g: 0x8a, b: 0x3a })),
+            Biome::Snow => Some((Color::Rgb { r: 0xee, g: 0xf4, b: 0xf8 }, Color::Rgb { r: 0x2e, g: 0x5e, b: 0x3e })),
+            Biome::Swamp => Some((Color::Rgb { r: 0x6b, g: 0x7a, b: 0x3a }, Color::Rgb { r: 0x3a, g: 0x4a, b: 0x2a })),
+        }
+    }
+}
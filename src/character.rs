@@ -0,0 +1,85 @@
+//! The characters the player can cross the road as.
+//!
+//! Every character is drawn as the white player tile with an arrow in its own style and color
+//! pointing where it last hopped, or as its own emoji. The frog hops two rows at once.
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use crate::map::Facing;
+use crate::stripe::Block;
+use crate::theme::Glyphs;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Character {
+    #[default]
+    Chicken,
+    /// Hops two rows forward at once.
+    Frog,
+    Duck,
+    Robot,
+}
+
+impl Character {
+    pub const ALL: [Character; 4] = [Character::Chicken, Character::Frog, Character::Duck, Character::Robot];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Character::Chicken => "chicken",
+            Character::Frog => "frog",
+            Character::Duck => "duck",
+            Character::Robot => "robot",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|character| character.name() == name)
+    }
+
+    /// Shown in the character menu.
+    pub fn title(self) -> &'static str {
+        match self {
+            Character::Chicken => "Chicken",
+            Character::Frog => "Frog (hops two rows at once)",
+            Character::Duck => "Duck",
+            Character::Robot => "Robot",
+        }
+    }
+
+    /// The arrow drawn in the middle of the player tile while facing `facing` and the block it
+    /// is colored like.
+    pub fn face(self, glyphs: Glyphs, facing: Facing) -> (char, Block) {
+        let arrows = match (self, glyphs) {
+            (_, Glyphs::Ascii) => ['^', '>', 'v', '<'],
+            (Character::Chicken, _) => ['▲', '▶', '▼', '◀'],
+            (Character::Frog, _) => ['△', '▷', '▽', '◁'],
+            (Character::Duck, _) => ['▴', '▸', '▾', '◂'],
+            (Character::Robot, _) => ['⇑', '⇒', '⇓', '⇐'],
+        };
+        let block = match self {
+            Character::Chicken => Block::Gray,
+            Character::Frog => Block::Green,
+            Character::Duck => Block::DarkYellow,
+            Character::Robot => Block::Blue,
+        };
+        (arrows[facing as usize], block)
+    }
+
+    pub fn emoji(self) -> char {
+        match self {
+            Character::Chicken => '🐔',
+            Character::Frog => '🐸',
+            Character::Duck => '🦆',
+            Character::Robot => '🤖',
+        }
+    }
+
+    /// The character of the second player in co-op runs, so both players look different.
+    pub fn partner(self) -> Character {
+        if self == Character::Duck { Character::Chicken } else { Character::Duck }
+    }
+
+    /// Rows a hop forward covers.
+    pub fn hop_rows(self) -> u8 {
+        if self == Character::Frog { 2 } else { 1 }
+    }
+}
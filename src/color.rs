@@ -0,0 +1,206 @@
+//! Colors the engine draws with and the color support of the terminal.
+//!
+//! Terminals that support 24 bit colors get shaded rows. Others get RGB colors replaced by the
+//! closest one of the 256 color palette or the 16 standard colors, and with `NO_COLOR` set (see
+//! <https://no-color.org>) no colors are sent at all.
+use std::sync::OnceLock;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::multiplexer::Multiplexer;
+
+/// A color of a [crate::grid::Cell]: the terminals default, one of the 16 standard colors, an
+/// entry of the 256 color palette or an RGB color. Terminal frontends hand it to crossterm, which
+/// isn't available on wasm32, where pages draw [to_rgb] themselves.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Color {
+    Reset,
+    Black,
+    DarkGrey,
+    Red,
+    DarkRed,
+    Green,
+    DarkGreen,
+    Yellow,
+    DarkYellow,
+    Blue,
+    DarkBlue,
+    Magenta,
+    DarkMagenta,
+    Cyan,
+    DarkCyan,
+    White,
+    Grey,
+    Rgb { r: u8, g: u8, b: u8 },
+    AnsiValue(u8),
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl From<Color> for crossterm::style::Color {
+    fn from(color: Color) -> Self {
+        use crossterm::style::Color as TermColor;
+        match color {
+            Color::Reset => TermColor::Reset,
+            Color::Black => TermColor::Black,
+            Color::DarkGrey => TermColor::DarkGrey,
+            Color::Red => TermColor::Red,
+            Color::DarkRed => TermColor::DarkRed,
+            Color::Green => TermColor::Green,
+            Color::DarkGreen => TermColor::DarkGreen,
+            Color::Yellow => TermColor::Yellow,
+            Color::DarkYellow => TermColor::DarkYellow,
+            Color::Blue => TermColor::Blue,
+            Color::DarkBlue => TermColor::DarkBlue,
+            Color::Magenta => TermColor::Magenta,
+            Color::DarkMagenta => TermColor::DarkMagenta,
+            Color::Cyan => TermColor::Cyan,
+            Color::DarkCyan => TermColor::DarkCyan,
+            Color::White => TermColor::White,
+            Color::Grey => TermColor::Grey,
+            Color::Rgb { r, g, b } => TermColor::Rgb { r, g, b },
+            Color::AnsiValue(value) => TermColor::AnsiValue(value),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ColorDepth {
+    /// Only the terminals default colors, e.g. because the user set `NO_COLOR`.
+    NoColor,
+    /// The 16 standard colors.
+    Ansi16,
+    /// The 256 color palette of xterm.
+    Ansi256,
+    /// 24 bit RGB colors.
+    TrueColor,
+}
+
+impl ColorDepth {
+    /// The color depth detected on first use.
+    pub fn current() -> Self {
+        static CURRENT: OnceLock<ColorDepth> = OnceLock::new();
+        *CURRENT.get_or_init(Self::detect)
+    }
+
+    /// Honors a non-empty `NO_COLOR` and `TERM=dumb` before looking at what the terminal
+    /// advertises. Legacy Windows consoles only get the 16 colors their console API knows.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn detect() -> Self {
+        let term = std::env::var("TERM").unwrap_or_default();
+        if std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty()) || term == "dumb" {
+            ColorDepth::NoColor
+        } else if is_legacy_console() {
+            ColorDepth::Ansi16
+        } else if Multiplexer::current().supports_truecolor() {
+            ColorDepth::TrueColor
+        } else if term.contains("256color") {
+            ColorDepth::Ansi256
+        } else {
+            ColorDepth::Ansi16
+        }
+    }
+
+    /// Pages embedding the engine draw every color as RGB.
+    #[cfg(target_arch = "wasm32")]
+    pub fn detect() -> Self {
+        ColorDepth::TrueColor
+    }
+
+    /// `color` as this color depth can display it.
+    pub fn adapt(self, color: Color) -> Color {
+        match self {
+            ColorDepth::NoColor => Color::Reset,
+            ColorDepth::Ansi16 => to_ansi16(color),
+            ColorDepth::Ansi256 => to_ansi256(color),
+            ColorDepth::TrueColor => color,
+        }
+    }
+}
+
+/// Whether this is a Windows console without virtual terminal processing, like conhost before
+/// Windows 10. crossterm drives it through the console API, which knows 16 colors and nothing
+/// like synchronized updates, and its raster fonts lack most box glyphs.
+#[cfg(windows)]
+pub fn is_legacy_console() -> bool {
+    !crossterm::ansi_support::supports_ansi()
+}
+
+/// Only Windows has consoles without escape sequences.
+#[cfg(not(windows))]
+pub fn is_legacy_console() -> bool {
+    false
+}
+
+/// The standard colors and how xterm draws them by default.
+const ANSI16: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::DarkRed, (205, 0, 0)),
+    (Color::DarkGreen, (0, 205, 0)),
+    (Color::DarkYellow, (205, 205, 0)),
+    (Color::DarkBlue, (0, 0, 238)),
+    (Color::DarkMagenta, (205, 0, 205)),
+    (Color::DarkCyan, (0, 205, 205)),
+    (Color::Grey, (229, 229, 229)),
+    (Color::DarkGrey, (127, 127, 127)),
+    (Color::Red, (255, 0, 0)),
+    (Color::Green, (0, 255, 0)),
+    (Color::Yellow, (255, 255, 0)),
+    (Color::Blue, (92, 92, 255)),
+    (Color::Magenta, (255, 0, 255)),
+    (Color::Cyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+
+/// The RGB value of `color`, [None] for the terminals default and 256 color palette entries.
+pub fn to_rgb(color: Color) -> Option<(u8, u8, u8)> {
+    match color {
+        Color::Rgb { r, g, b } => Some((r, g, b)),
+        color => ANSI16.iter().find(|(ansi, _)| *ansi == color).map(|(_, rgb)| *rgb),
+    }
+}
+
+/// The closest standard color to an RGB color, other colors are kept.
+pub fn to_ansi16(color: Color) -> Color {
+    let Color::Rgb { r, g, b } = color else { return color };
+    let distance = |(ar, ag, ab): (u8, u8, u8)| {
+        let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+        d(ar, r) + d(ag, g) + d(ab, b)
+    };
+    ANSI16.iter().min_by_key(|(_, rgb)| distance(*rgb)).map(|(ansi, _)| *ansi).unwrap()
+}
+
+/// The closest entry of the 256 color palette to an RGB color, from the 6x6x6 color cube or the
+/// gray ramp. Other colors are kept.
+pub fn to_ansi256(color: Color) -> Color {
+    let Color::Rgb { r, g, b } = color else { return color };
+    const LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+    let closest_level = |c: u8| (0..LEVELS.len()).min_by_key(|idx| (LEVELS[*idx] as i32 - c as i32).abs()).unwrap();
+    let (cr, cg, cb) = (closest_level(r), closest_level(g), closest_level(b));
+    let cube = (LEVELS[cr], LEVELS[cg], LEVELS[cb]);
+    let average = (r as u32 + g as u32 + b as u32) / 3;
+    let gray_idx = (average.saturating_sub(3) / 10).min(23) as u8;
+    let gray = 8 + gray_idx * 10;
+    let distance = |(ar, ag, ab): (u8, u8, u8)| {
+        let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+        d(ar, r) + d(ag, g) + d(ab, b)
+    };
+    if distance((gray, gray, gray)) < distance(cube) {
+        Color::AnsiValue(232 + gray_idx)
+    } else {
+        Color::AnsiValue(16 + 36 * cr as u8 + 6 * cg as u8 + cb as u8)
+    }
+}
+
+/// `color` darkened by `percent`, or unchanged if it has no known RGB value.
+pub fn darken(color: Color, percent: u8) -> Color {
+    let Some((r, g, b)) = to_rgb(color) else { return color };
+    let scale = |c: u8| (c as u32 * (100 - percent.min(100) as u32) / 100) as u8;
+    Color::Rgb { r: scale(r), g: scale(g), b: scale(b) }
+}
+
+/// `color` moved `percent` of the way toward `target`, or unchanged if either has no known RGB
+/// value.
+pub fn mix(color: Color, target: Color, percent: u8) -> Color {
+    let (Some((r, g, b)), Some((tr, tg, tb))) = (to_rgb(color), to_rgb(target)) else { return color };
+    let percent = percent.min(100) as i32;
+    let blend = |c: u8, t: u8| (c as i32 + (t as i32 - c as i32) * percent / 100) as u8;
+    Color::Rgb { r: blend(r, tr), g: blend(g, tg), b: blend(b, tb) }
+}
@@ -0,0 +1,56 @@
+//! The debug overlay toggled with F3, for tuning the generator and for bug reports.
+//!
+//! It shows how fast the frontend ticks and draws, how many rows were generated of which kind,
+//! where the players are, the seed and the internal state of every visible row. The timings are
+//! measured by the frontend, the app itself never looks at the clock.
+use std::collections::BTreeMap;
+use std::time::Duration;
+use crate::grid::Grid;
+use crate::map::MapState;
+
+/// How fast the frontend runs, see [crate::app::App::set_timings].
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct Timings {
+    /// Ticks simulated per second.
+    pub tick_rate: f32,
+    /// Average time it took to build and draw a frame.
+    pub frame_time: Duration,
+}
+
+/// The overlay for `map`, the board of the current game if there is one.
+pub fn overlay(timings: Option<Timings>, map: Option<&MapState>) -> Grid {
+    let mut lines = vec!["Debug (F3 to close)".to_string()];
+    match timings {
+        Some(timings) => {
+            lines.push(format!("tick rate: {:.1}/s", timings.tick_rate));
+            lines.push(format!("frame time: {:.2}ms", timings.frame_time.as_secs_f64() * 1000.0));
+        }
+        None => lines.push("timings: not measured".to_string()),
+    }
+    if let Some(map) = map {
+        lines.extend(describe(map));
+    }
+    Grid::from_text(&lines.join("\n"))
+}
+
+fn describe(map: &MapState) -> Vec<String> {
+    let visible = map.visible_rows();
+    let mut lines = vec![
+        format!("seed: {}", map.seed()),
+        format!("difficulty: {}, biome: {}, weather: {}", map.difficulty().name, map.biome().name(), map.weather().name()),
+        format!("rows generated: {} ({} ahead)", visible.end + map.lookahead().len() as u64, map.lookahead().len()),
+    ];
+    let mut kinds = BTreeMap::new();
+    for row in map.rows().chain(map.lookahead()) {
+        *kinds.entry(row.kind()).or_insert(0) += 1;
+    }
+    lines.push(kinds.iter().map(|(kind, count)| format!("{count} {kind}")).collect::<Vec<_>>().join(", "));
+    for player in 0..map.player_count() {
+        lines.push(format!("player {}: x {}, y {}", player + 1, map.player_x_of(player), map.player_y_of(player)));
+    }
+    lines.push(String::new());
+    for (idx, row) in map.rows().enumerate().rev() {
+        lines.push(format!("{:>5} {}", visible.start + idx as u64, row.debug_state()));
+    }
+    lines
+}
@@ -0,0 +1,106 @@
+//! Draws frames by only updating the cells that changed since the previous frame.
+//!
+//! Clearing the screen and writing every frame from scratch flickers on slow terminals. The
+//! [DiffRenderer] compares the cells of a [Grid] with the cells it drew last time.
+use std::io::Write;
+use crossterm::style::{SetBackgroundColor, SetForegroundColor};
+use crossterm::{cursor, terminal, QueueableCommand};
+use crate::backend::Backend;
+use crate::color::{Color, ColorDepth};
+use crate::grid::{Cell, Grid};
+
+#[derive(Debug, Default)]
+pub struct DiffRenderer {
+    /// The cells on screen, one vector per line.
+    previous: Vec<Vec<Cell>>,
+    /// Whether the screen has to be cleared before the next frame.
+    invalid: bool,
+}
+
+impl DiffRenderer {
+    pub fn new() -> Self {
+        DiffRenderer { previous: Vec::new(), invalid: true }
+    }
+
+    /// Draws the next frame from scratch, e.g. because the terminal was resized.
+    pub fn invalidate(&mut self) {
+        self.invalid = true;
+    }
+
+    /// Redraws every cell of line `y` in the next frame, e.g. because something was drawn over it.
+    pub fn invalidate_line(&mut self, y: usize) {
+        if let Some(line) = self.previous.get_mut(y) {
+            line.clear();
+        }
+    }
+
+    /// Queues the commands turning the previous frame into `grid`.
+    pub fn draw(&mut self, out: &mut impl Write, grid: &Grid) -> std::io::Result<()> {
+        if self.invalid {
+            out.queue(cursor::MoveTo(0, 0))?;
+            out.queue(terminal::Clear(terminal::ClearType::All))?;
+            self.previous.clear();
+            self.invalid = false;
+        }
+        let lines = grid.lines();
+        for (y, line) in lines.iter().enumerate() {
+            let previous = self.previous.get(y).map(Vec::as_slice).unwrap_or_default();
+            draw_line(out, y as u16, previous, line)?;
+        }
+        for y in lines.len()..self.previous.len() {
+            out.queue(cursor::MoveTo(0, y as u16))?;
+            out.queue(terminal::Clear(terminal::ClearType::CurrentLine))?;
+        }
+        self.previous = lines.to_vec();
+        Ok(())
+    }
+}
+
+impl Backend for DiffRenderer {
+    fn invalidate(&mut self) {
+        DiffRenderer::invalidate(self);
+    }
+
+    fn draw(&mut self, mut out: &mut dyn Write, grid: &Grid) -> std::io::Result<()> {
+        DiffRenderer::draw(self, &mut out, grid)
+    }
+}
+
+/// Queues the changed runs of cells in a line and clears what is left of a longer previous line.
+///
+/// Colors are compared as the grid holds them and only adapted to the [ColorDepth] when queued.
+fn draw_line(out: &mut impl Write, y: u16, previous: &[Cell], line: &[Cell]) -> std::io::Result<()> {
+    let depth = ColorDepth::current();
+    let mut colors = (Color::Reset, Color::Reset);
+    let mut x = 0;
+    while x < line.len() {
+        if previous.get(x) == Some(&line[x]) {
+            x += 1;
+            continue;
+        }
+        out.queue(cursor::MoveTo(x as u16, y))?;
+        while x < line.len() && previous.get(x) != Some(&line[x]) {
+            let cell = line[x];
+            if cell.fg != colors.0 {
+                out.queue(SetForegroundColor(depth.adapt(cell.fg).into()))?;
+            }
+            if cell.bg != colors.1 {
+                out.queue(SetBackgroundColor(depth.adapt(cell.bg).into()))?;
+            }
+            colors = (cell.fg, cell.bg);
+            if !cell.is_continuation() {
+                write!(out, "{}", cell.glyph)?;
+            }
+            x += 1;
+        }
+    }
+    if colors != (Color::Reset, Color::Reset) {
+        out.queue(SetForegroundColor(Color::Reset.into()))?;
+        out.queue(SetBackgroundColor(Color::Reset.into()))?;
+    }
+    if line.len() < previous.len() {
+        out.queue(cursor::MoveTo(line.len() as u16, y))?;
+        out.queue(terminal::Clear(terminal::ClearType::UntilNewLine))?;
+    }
+    Ok(())
+}
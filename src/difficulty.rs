@@ -0,0 +1,216 @@
+//! Tunables of the simulation that make a run easier or harder.
+use std::ops::RangeInclusive;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use crate::stripe::TRAIN_WARN_STEPS;
+
+/// The name of a difficulty. Serde takes a `&'static str` field for one borrowed from the input,
+/// which only `'static` input could fill. It doesn't look through this alias, so
+/// [difficulty_name] can look the name up instead.
+type Name = &'static str;
+
+/// Levels [Difficulty::adapted] takes, from the easiest to the hardest.
+pub const ADAPTIVE_LEVELS: RangeInclusive<i8> = -3..=3;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Difficulty {
+    /// Identifier used on the command line and for high score tables.
+    #[cfg_attr(feature = "serde", serde(deserialize_with = "difficulty_name"))]
+    pub name: Name,
+    /// How many steps the world advances per tick.
+    pub speed: u32,
+    /// Safe grass rows the board starts with, counted down from the row the player stands on.
+    pub start_rows: usize,
+    /// Relative chance of generating grass, railroad and road rows.
+    pub stripe_weights: [u32; 3],
+    /// Cycle lengths of generated railroads are picked from `min_rail_cycle..max_rail_cycle`.
+    pub min_rail_cycle: usize,
+    pub max_rail_cycle: usize,
+    /// Relative chance of spawning bikes, cars and trucks on roads.
+    pub vehicle_weights: [u32; 3],
+    /// Relative chance of generating slow, normal and fast roads.
+    pub lane_speed_weights: [u32; 3],
+    /// Chance in percent that a generated road starts a highway of several lanes.
+    pub highway_percent: u32,
+    /// Chance in percent that a generated road outside of highways has a traffic light.
+    pub traffic_light_percent: u32,
+    /// Chance in percent that a generated road outside of highways has a zebra crossing.
+    pub crossing_percent: u32,
+    /// Chance in percent that a row picked to be grass is a river instead.
+    pub river_percent: u32,
+    /// Chance in percent of a grass tile without a tree to hold a rock, a bush or flowers.
+    pub grass_tile_percents: [u32; 3],
+    /// Empty tiles at least left between two vehicles on a road.
+    pub min_vehicle_gap: u8,
+    /// Steps railroads warn before a train arrives.
+    pub train_warn_steps: usize,
+    /// Steps between the phases of the wall of death, it advances a row every five phases.
+    pub wall_of_death_steps: u64,
+}
+
+impl Difficulty {
+    /// Slower lanes with wider gaps, longer railroad warnings, more grass and a slower wall of
+    /// death.
+    pub const EASY: Difficulty = Difficulty {
+        name: "easy",
+        speed: 1,
+        start_rows: 4,
+        stripe_weights: [6, 2, 4],
+        min_rail_cycle: 30,
+        max_rail_cycle: 60,
+        vehicle_weights: [2, 6, 1],
+        lane_speed_weights: [2, 2, 1],
+        highway_percent: 15,
+        traffic_light_percent: 30,
+        crossing_percent: 25,
+        river_percent: 10,
+        grass_tile_percents: [4, 6, 12],
+        min_vehicle_gap: 3,
+        train_warn_steps: TRAIN_WARN_STEPS + 3,
+        wall_of_death_steps: 7,
+    };
+
+    pub const NORMAL: Difficulty = Difficulty {
+        name: "normal",
+        speed: 1,
+        start_rows: 4,
+        stripe_weights: [5, 3, 5],
+        min_rail_cycle: 20,
+        max_rail_cycle: 50,
+        vehicle_weights: [1, 6, 2],
+        lane_speed_weights: [1, 2, 1],
+        highway_percent: 25,
+        traffic_light_percent: 20,
+        crossing_percent: 15,
+        river_percent: 15,
+        grass_tile_percents: [6, 8, 10],
+        min_vehicle_gap: 2,
+        train_warn_steps: TRAIN_WARN_STEPS,
+        wall_of_death_steps: 5,
+    };
+
+    /// As fast as normal, but with more roads, fast lanes, trucks and highways, shorter railroad
+    /// cycles and a faster wall of death.
+    pub const HARD: Difficulty = Difficulty {
+        name: "hard",
+        speed: 1,
+        start_rows: 2,
+        stripe_weights: [4, 3, 6],
+        min_rail_cycle: 16,
+        max_rail_cycle: 40,
+        vehicle_weights: [1, 5, 3],
+        lane_speed_weights: [1, 2, 2],
+        highway_percent: 35,
+        traffic_light_percent: 15,
+        crossing_percent: 10,
+        river_percent: 20,
+        grass_tile_percents: [8, 10, 8],
+        min_vehicle_gap: 2,
+        train_warn_steps: TRAIN_WARN_STEPS,
+        wall_of_death_steps: 4,
+    };
+
+    /// Twice as fast, shorter railroad cycles, more trucks and fast lanes and only the players own
+    /// row is safe at the start.
+    pub const HARDCORE: Difficulty = Difficulty {
+        name: "hardcore",
+        speed: 2,
+        start_rows: 1,
+        stripe_weights: [5, 3, 5],
+        min_rail_cycle: 14,
+        max_rail_cycle: 30,
+        vehicle_weights: [1, 5, 3],
+        lane_speed_weights: [1, 2, 3],
+        highway_percent: 40,
+        traffic_light_percent: 10,
+        crossing_percent: 5,
+        river_percent: 20,
+        grass_tile_percents: [10, 12, 5],
+        min_vehicle_gap: 2,
+        train_warn_steps: TRAIN_WARN_STEPS,
+        wall_of_death_steps: 5,
+    };
+
+    /// Starts out like [Difficulty::NORMAL] and follows the player from there, see
+    /// [crate::stats::Stats::adapt].
+    pub const ADAPTIVE: Difficulty = Difficulty { name: "adaptive", ..Difficulty::NORMAL };
+
+    pub const ALL: [Difficulty; 5] = [
+        Difficulty::EASY,
+        Difficulty::NORMAL,
+        Difficulty::HARD,
+        Difficulty::HARDCORE,
+        Difficulty::ADAPTIVE,
+    ];
+
+    pub fn from_name(name: &str) -> Option<Difficulty> {
+        Self::ALL.into_iter().find(|difficulty| difficulty.name == name)
+    }
+
+    /// The difficulty `level` steps of [ADAPTIVE_LEVELS] away from this one. Every easier step
+    /// leaves a tile more between vehicles and makes railroads warn longer in longer cycles, every
+    /// harder one shortens railroad cycles and adds fast lanes and highways.
+    pub fn adapted(mut self, level: i8) -> Difficulty {
+        let steps = level.clamp(*ADAPTIVE_LEVELS.start(), *ADAPTIVE_LEVELS.end()).unsigned_abs();
+        if level < 0 {
+            self.min_vehicle_gap += steps;
+            self.train_warn_steps += 3 * steps as usize;
+            self.min_rail_cycle += 5 * steps as usize;
+            self.max_rail_cycle += 5 * steps as usize;
+        } else {
+            self.min_rail_cycle -= 2 * steps as usize;
+            self.max_rail_cycle -= 5 * steps as usize;
+            self.lane_speed_weights[2] += steps as u32;
+            self.highway_percent += 5 * steps as u32;
+        }
+        self
+    }
+}
+
+impl Default for Difficulty {
+    fn default() -> Self {
+        Difficulty::NORMAL
+    }
+}
+
+/// Reads the name of a difficulty, which every difficulty shares with one in [Difficulty::ALL].
+#[cfg(feature = "serde")]
+fn difficulty_name<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Name, D::Error> {
+    let name = String::deserialize(deserializer)?;
+    Difficulty::from_name(&name).map(|difficulty| difficulty.name)
+        .ok_or_else(|| serde::de::Error::unknown_variant(&name, &[]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn adapted_levels_are_clamped() {
+        for difficulty in Difficulty::ALL {
+            assert_eq!(difficulty.adapted(0), difficulty);
+            assert_eq!(difficulty.adapted(i8::MAX), difficulty.adapted(*ADAPTIVE_LEVELS.end()));
+            assert_eq!(difficulty.adapted(i8::MIN), difficulty.adapted(*ADAPTIVE_LEVELS.start()));
+        }
+    }
+
+    #[test]
+    fn adapted_levels_get_harder() {
+        for difficulty in Difficulty::ALL {
+            for level in *ADAPTIVE_LEVELS.start()..*ADAPTIVE_LEVELS.end() {
+                let (easier, harder) = (difficulty.adapted(level), difficulty.adapted(level + 1));
+                assert!(harder.min_vehicle_gap <= easier.min_vehicle_gap, "{} {level}", difficulty.name);
+                assert!(harder.train_warn_steps <= easier.train_warn_steps, "{} {level}", difficulty.name);
+                assert!(harder.min_rail_cycle <= easier.min_rail_cycle, "{} {level}", difficulty.name);
+                assert!(harder.max_rail_cycle <= easier.max_rail_cycle, "{} {level}", difficulty.name);
+                assert!(harder.lane_speed_weights[2] >= easier.lane_speed_weights[2], "{} {level}", difficulty.name);
+                assert!(harder.highway_percent >= easier.highway_percent, "{} {level}", difficulty.name);
+                assert_ne!(harder, easier, "{} {level}", difficulty.name);
+            }
+            let hardest = difficulty.adapted(*ADAPTIVE_LEVELS.end());
+            assert!(hardest.min_rail_cycle < hardest.max_rail_cycle, "{}", difficulty.name);
+            assert!(hardest.highway_percent <= 100, "{}", difficulty.name);
+        }
+    }
+}
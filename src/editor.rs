@@ -0,0 +1,180 @@
+use std::path::{Path, PathBuf};
+use crate::grid::{text, Grid};
+use crate::level::{Level, LevelError};
+use crate::stripe::{Block, GreenStripe, Railroad, Road, Stripe, WallOfDeathPhase, STRIPE_LENGTH};
+use crate::theme::Theme;
+
+const MIN_RAIL_CYCLE: usize = 10;
+const MAX_RAIL_CYCLE: usize = 60;
+const DEFAULT_RAIL_CYCLE: usize = 30;
+
+/// Screen to build levels row by row and save them in the level file format.
+pub struct LevelEditor {
+    path: PathBuf,
+    name: Option<String>,
+    /// Rows from bottom to top, without the finish line.
+    rows: Vec<Stripe>,
+    cursor_row: usize,
+    cursor_x: usize,
+    /// Feedback for the last action, e.g. that saving worked.
+    message: String,
+}
+
+impl LevelEditor {
+    /// Opens the level at `path`, or starts an empty one if it doesn't exist yet.
+    pub fn open(path: &Path) -> Result<Self, LevelError> {
+        let (name, rows) = if path.exists() {
+            let level = Level::load(&path.to_string_lossy())?;
+            let rows = level.rows.into_iter()
+                .filter(|row| !matches!(row, Stripe::Finish))
+                .collect();
+            (level.name, rows)
+        } else {
+            (None, vec![Stripe::Green(GreenStripe::new([false; STRIPE_LENGTH])); 4])
+        };
+        Ok(LevelEditor {
+            path: path.to_path_buf(),
+            name,
+            rows,
+            cursor_row: 0,
+            cursor_x: STRIPE_LENGTH / 2,
+            message: String::new(),
+        })
+    }
+
+    pub fn level(&self) -> Level {
+        let mut rows = self.rows.clone();
+        rows.push(Stripe::Finish);
+        Level { name: self.name.clone(), rows }
+    }
+
+    pub fn save(&mut self) {
+        self.message = match std::fs::write(&self.path, self.level().to_source()) {
+            Ok(()) => format!("Saved to {}", self.path.display()),
+            Err(err) => format!("Saving failed: {err}"),
+        };
+    }
+
+    pub fn cursor_up(&mut self) {
+        if self.cursor_row + 1 < self.rows.len() {
+            self.cursor_row += 1;
+        }
+    }
+
+    pub fn cursor_down(&mut self) {
+        self.cursor_row = self.cursor_row.saturating_sub(1);
+    }
+
+    pub fn cursor_left(&mut self) {
+        self.cursor_x = self.cursor_x.saturating_sub(1);
+    }
+
+    pub fn cursor_right(&mut self) {
+        if self.cursor_x + 1 < STRIPE_LENGTH {
+            self.cursor_x += 1;
+        }
+    }
+
+    /// Inserts an empty grass row above the cursor and selects it.
+    pub fn insert_row(&mut self) {
+        let row = Stripe::Green(GreenStripe::new([false; STRIPE_LENGTH]));
+        if self.rows.is_empty() {
+            self.rows.push(row);
+        } else {
+            self.rows.insert(self.cursor_row + 1, row);
+            self.cursor_row += 1;
+        }
+    }
+
+    pub fn delete_row(&mut self) {
+        if self.rows.len() > 1 {
+            self.rows.remove(self.cursor_row);
+            self.cursor_row = self.cursor_row.min(self.rows.len() - 1);
+        }
+    }
+
+    pub fn set_green(&mut self) {
+        self.rows[self.cursor_row] = Stripe::Green(GreenStripe::new([false; STRIPE_LENGTH]));
+    }
+
+    pub fn set_road(&mut self) {
+        self.rows[self.cursor_row] = Stripe::Road(Road::new(true, [false; STRIPE_LENGTH]));
+    }
+
+    pub fn set_rail(&mut self) {
+        self.rows[self.cursor_row] = Stripe::Rail(Railroad::new(DEFAULT_RAIL_CYCLE));
+    }
+
+    pub fn set_empty(&mut self) {
+        self.rows[self.cursor_row] = Stripe::Empty;
+    }
+
+    /// Toggles the tree or car under the cursor.
+    pub fn toggle_cell(&mut self) {
+        let x = self.cursor_x;
+        let row = &mut self.rows[self.cursor_row];
+        match row {
+            Stripe::Green(green) => {
+                let mut trees = green.trees();
+                trees[x] = !trees[x];
+                *row = Stripe::Green(GreenStripe::new(trees));
+            }
+            Stripe::Road(road) => {
+                let mut cars = road.cars();
+                cars[x] = !cars[x];
+                *row = Stripe::Road(Road::new(road.left(), cars));
+            }
+            _ => {}
+        }
+    }
+
+    /// Flips the direction of a road under the cursor.
+    pub fn flip_direction(&mut self) {
+        if let Stripe::Road(road) = self.rows[self.cursor_row] {
+            self.rows[self.cursor_row] = Stripe::Road(Road::new(!road.left(), road.cars()));
+        }
+    }
+
+    /// Changes the cycle length of a railroad under the cursor by `delta`.
+    pub fn adjust_cycle(&mut self, delta: isize) {
+        if let Stripe::Rail(rail) = self.rows[self.cursor_row] {
+            let cycle_length = rail.cycle_length().saturating_add_signed(delta)
+                .clamp(MIN_RAIL_CYCLE, MAX_RAIL_CYCLE);
+            self.rows[self.cursor_row] = Stripe::Rail(Railroad::new(cycle_length));
+        }
+    }
+
+    /// Renders the help text and the rows around the cursor in `height` lines.
+    pub fn render(&self, height: usize, theme: &Theme) -> Grid {
+        let mut grid = Grid::from_text(&format!("Editing {}  {}", self.path.display(), self.message));
+        grid.push_text("←↑↓→ move, g/r/t/e grass/road/rail/empty, space toggle, f flip, +/- cycle");
+        grid.push_text("a add row, d delete row, s save, q back");
+        let visible = height.saturating_sub(grid.lines().len()).max(1);
+        let bottom = (self.cursor_row + 1).saturating_sub(visible);
+        let top = (bottom + visible).min(self.rows.len());
+        for idx in (bottom..top).rev() {
+            let mut render = self.rows[idx].visualize();
+            if idx == self.cursor_row {
+                render.add_overlay(self.cursor_x, Block::White);
+            }
+            let marker = if idx == self.cursor_row { '>' } else { ' ' };
+            let mut line = text(&format!("{marker}{idx:>3} "));
+            line.extend(render.render(WallOfDeathPhase::Normal, theme, idx as u64, 0..STRIPE_LENGTH));
+            line.extend(text(&format!(" {}", describe(&self.rows[idx]))));
+            grid.push_line(line);
+        }
+        grid
+    }
+}
+
+fn describe(row: &Stripe) -> String {
+    match row {
+        Stripe::Road(road) => format!("road {}", if road.left() { "←" } else { "→" }),
+        Stripe::TrafficRoad(traffic) => {
+            format!("traffic {} {}", if traffic.road().left() { "←" } else { "→" }, traffic.cycle_length())
+        }
+        Stripe::Rail(rail) => format!("rail {}", rail.cycle_length()),
+        Stripe::Green(_) => "grass".to_string(),
+        _ => String::new(),
+    }
+}
@@ -0,0 +1,98 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use crate::difficulty::Difficulty;
+use crate::grid::{text, Grid};
+use crate::hash::{hash_rows, BoardHash};
+use crate::map::generate_rows;
+use crate::stripe::{Stripe, WallOfDeathPhase, STRIPE_LENGTH};
+use crate::theme::Theme;
+
+/// Amount of rows generated for the preview.
+pub const PREVIEW_ROWS: usize = 40;
+
+/// Screen to look at the rows a seed generates before playing it.
+pub struct SeedExplorer {
+    seed: u64,
+    /// Digits typed by the user that have not been applied yet.
+    input: String,
+    rows: Vec<Stripe>,
+    /// Index of the lowest visible row.
+    scroll: usize,
+}
+
+impl Default for SeedExplorer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SeedExplorer {
+    pub fn new() -> Self {
+        Self::with_seed(rand::random())
+    }
+
+    pub fn with_seed(seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        SeedExplorer {
+            seed,
+            input: String::new(),
+            rows: generate_rows(&mut rng, PREVIEW_ROWS, &Difficulty::NORMAL),
+            scroll: 0,
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Hash of the previewed rows.
+    pub fn board_hash(&self) -> BoardHash {
+        hash_rows(&self.rows)
+    }
+
+    pub fn push_digit(&mut self, digit: char) {
+        if digit.is_ascii_digit() && format!("{}{digit}", self.input).parse::<u64>().is_ok() {
+            self.input.push(digit);
+        }
+    }
+
+    pub fn pop_digit(&mut self) {
+        self.input.pop();
+    }
+
+    /// Regenerates the preview from the typed seed, if there is one.
+    pub fn apply_input(&mut self) {
+        if let Ok(seed) = self.input.parse() {
+            *self = Self::with_seed(seed);
+        }
+    }
+
+    pub fn randomize(&mut self) {
+        *self = Self::new();
+    }
+
+    pub fn scroll_up(&mut self) {
+        if self.scroll + 1 < PREVIEW_ROWS {
+            self.scroll += 1;
+        }
+    }
+
+    pub fn scroll_down(&mut self) {
+        self.scroll = self.scroll.saturating_sub(1);
+    }
+
+    /// Renders the header and as many rows as fit in `height` lines.
+    pub fn render(&self, height: usize, theme: &Theme) -> Grid {
+        let mut grid = Grid::from_text(&format!(
+            "Seed: {}  Board: {}  New seed: {}_\n0-9 type, Enter apply, r random, c copy, ↑/↓ scroll, p play, q quit",
+            self.seed, self.board_hash(), self.input,
+        ));
+        let visible = height.saturating_sub(grid.lines().len()).min(PREVIEW_ROWS - self.scroll);
+        for idx in (self.scroll..self.scroll + visible).rev() {
+            let mut line = text(&format!("{idx:>3} "));
+            line.extend(self.rows[idx].visualize().render(WallOfDeathPhase::Normal, theme, idx as u64, 0..STRIPE_LENGTH));
+            grid.push_line(line);
+        }
+        grid
+    }
+}
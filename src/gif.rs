@@ -0,0 +1,217 @@
+//! Records the board of a run and saves it as an animated GIF.
+//!
+//! Every cell becomes a block of [CELL_PIXELS] in the color it shows most of: box glyphs their
+//! foreground, everything else its background. Frames that look like the previous one only make
+//! it last longer, and colors are kept in a palette of up to 256 entries, beyond which the
+//! closest one is used.
+use std::io::Write;
+use std::path::PathBuf;
+use crate::color::{darken, to_rgb, Color};
+use crate::grid::{Cell, Grid};
+use crate::map::TICKS_PER_SECOND;
+use crate::profile::data_dir;
+
+/// Width and height of the block of pixels drawn for a cell, cells are about twice as high as
+/// they are wide.
+pub const CELL_PIXELS: (usize, usize) = (8, 16);
+
+/// Directory inside the data directory GIFs are saved to.
+pub const GIF_DIR: &str = "gifs";
+
+const MAX_CODE_SIZE: u32 = 12;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct GifFrame {
+    /// Palette index of every cell, line by line.
+    cells: Vec<u8>,
+    /// Cells per line.
+    width: usize,
+    ticks: u32,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct GifRecording {
+    palette: Vec<(u8, u8, u8)>,
+    /// Size of the largest frame in cells.
+    width: usize,
+    height: usize,
+    frames: Vec<GifFrame>,
+}
+
+impl GifRecording {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `grid` as shown for one tick.
+    pub fn record(&mut self, grid: &Grid) {
+        self.width = self.width.max(grid.width());
+        self.height = self.height.max(grid.lines().len());
+        let cells = grid.lines().iter()
+            .map(|line| line.iter().map(|cell| self.index_of(cell_color(cell))).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let width = self.width;
+        let cells = cells.into_iter()
+            .flat_map(|mut line| {
+                line.resize(width, 0);
+                line
+            })
+            .collect::<Vec<_>>();
+        match self.frames.last_mut() {
+            Some(last) if last.width == width && last.cells == cells => last.ticks += 1,
+            _ => self.frames.push(GifFrame { cells, width, ticks: 1 }),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Writes the recording as a looping GIF.
+    pub fn encode(&self, out: &mut impl Write) -> std::io::Result<()> {
+        let (width, height) = ((self.width * CELL_PIXELS.0) as u16, (self.height * CELL_PIXELS.1) as u16);
+        out.write_all(b"GIF89a")?;
+        out.write_all(&width.to_le_bytes())?;
+        out.write_all(&height.to_le_bytes())?;
+        // A global palette of 256 colors, background color 0, square pixels.
+        out.write_all(&[0xf7, 0, 0])?;
+        for idx in 0..256 {
+            let (r, g, b) = self.palette.get(idx).copied().unwrap_or_default();
+            out.write_all(&[r, g, b])?;
+        }
+        // Loop forever.
+        out.write_all(&[0x21, 0xff, 11])?;
+        out.write_all(b"NETSCAPE2.0")?;
+        out.write_all(&[3, 1, 0, 0, 0])?;
+        for frame in &self.frames {
+            let delay = (frame.ticks as u64 * 100 / TICKS_PER_SECOND).min(u16::MAX as u64) as u16;
+            out.write_all(&[0x21, 0xf9, 4, 0])?;
+            out.write_all(&delay.to_le_bytes())?;
+            out.write_all(&[0, 0])?;
+            out.write_all(&[0x2c, 0, 0, 0, 0])?;
+            out.write_all(&width.to_le_bytes())?;
+            out.write_all(&height.to_le_bytes())?;
+            out.write_all(&[0, 8])?;
+            for block in lzw(&self.pixels(frame), 8).chunks(255) {
+                out.write_all(&[block.len() as u8])?;
+                out.write_all(block)?;
+            }
+            out.write_all(&[0])?;
+        }
+        out.write_all(&[0x3b])?;
+        out.flush()
+    }
+
+    /// Saves the recording into [GIF_DIR], named after the current time, and returns its path.
+    pub fn save(&self) -> std::io::Result<PathBuf> {
+        let millis = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let dir = data_dir().join(GIF_DIR);
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join(format!("run-{millis}.gif"));
+        let mut file = std::io::BufWriter::new(std::fs::File::create(&path)?);
+        self.encode(&mut file)?;
+        Ok(path)
+    }
+
+    /// The palette index of `rgb`, adding it while there is room.
+    fn index_of(&mut self, rgb: (u8, u8, u8)) -> u8 {
+        if let Some(idx) = self.palette.iter().position(|color| *color == rgb) {
+            return idx as u8;
+        }
+        if self.palette.len() < 256 {
+            self.palette.push(rgb);
+            return (self.palette.len() - 1) as u8;
+        }
+        let distance = |(r, g, b): (u8, u8, u8)| {
+            let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+            d(r, rgb.0) + d(g, rgb.1) + d(b, rgb.2)
+        };
+        (0..self.palette.len()).min_by_key(|idx| distance(self.palette[*idx])).unwrap_or_default() as u8
+    }
+
+    /// The palette indices of every pixel of `frame`, a block of [CELL_PIXELS] per cell.
+    fn pixels(&self, frame: &GifFrame) -> Vec<u8> {
+        let mut pixels = Vec::with_capacity(frame.cells.len() * CELL_PIXELS.0 * CELL_PIXELS.1);
+        let lines = frame.cells.chunks(frame.width.max(1)).chain(std::iter::repeat(&[][..]));
+        for line in lines.take(self.height) {
+            let row = (0..self.width)
+                .flat_map(|x| [line.get(x).copied().unwrap_or_default(); CELL_PIXELS.0])
+                .collect::<Vec<_>>();
+            for _ in 0..CELL_PIXELS.1 {
+                pixels.extend_from_slice(&row);
+            }
+        }
+        pixels
+    }
+}
+
+/// The color most of `cell` is drawn in, black for the terminals default colors.
+fn cell_color(cell: &Cell) -> (u8, u8, u8) {
+    let color = match cell.glyph {
+        '█' => cell.fg,
+        '▓' => darken(cell.fg, 25),
+        '▒' => darken(cell.fg, 50),
+        '░' => darken(cell.fg, 75),
+        _ if cell.bg != Color::Reset => cell.bg,
+        glyph if !glyph.is_whitespace() && !cell.is_continuation() => cell.fg,
+        _ => Color::Reset,
+    };
+    to_rgb(color).unwrap_or_default()
+}
+
+/// Compresses `pixels` with the variable-length LZW flavor of GIF, starting with codes of
+/// `min_code_size + 1` bits.
+fn lzw(pixels: &[u8], min_code_size: u32) -> Vec<u8> {
+    let clear = 1u16 << min_code_size;
+    let end = clear + 1;
+    let mut out = Vec::new();
+    let mut bits = 0u32;
+    let mut bit_count = 0;
+    let mut emit = |code: u16, size: u32, out: &mut Vec<u8>| {
+        bits |= (code as u32) << bit_count;
+        bit_count += size;
+        while bit_count >= 8 {
+            out.push(bits as u8);
+            bits >>= 8;
+            bit_count -= 8;
+        }
+    };
+    let mut table = std::collections::HashMap::new();
+    let mut next = end + 1;
+    let mut size = min_code_size + 1;
+    emit(clear, size, &mut out);
+    let mut prefix: Option<u16> = None;
+    for &pixel in pixels {
+        let Some(current) = prefix else {
+            prefix = Some(pixel as u16);
+            continue;
+        };
+        if let Some(&code) = table.get(&(current, pixel)) {
+            prefix = Some(code);
+            continue;
+        }
+        emit(current, size, &mut out);
+        if next < 1 << MAX_CODE_SIZE {
+            table.insert((current, pixel), next);
+            if next == 1 << size && size < MAX_CODE_SIZE {
+                size += 1;
+            }
+            next += 1;
+        } else {
+            emit(clear, size, &mut out);
+            table.clear();
+            next = end + 1;
+            size = min_code_size + 1;
+        }
+        prefix = Some(pixel as u16);
+    }
+    if let Some(current) = prefix {
+        emit(current, size, &mut out);
+    }
+    emit(end, size, &mut out);
+    emit(0, 7, &mut out);
+    out
+}
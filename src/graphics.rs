@@ -0,0 +1,232 @@
+//! Draws the board as pixels with the kitty graphics protocol or sixel.
+//!
+//! Every line of a frame is searched for a run of colored cells, which is drawn as an image of
+//! solid tiles in the cells color. Shaded boxes get darker tiles. Everything else, like the menus
+//! and the score, is still drawn as text by a [DiffRenderer]. Support is guessed from the
+//! environment; inside multiplexers the text renderer is used, as they don't place images
+//! reliably.
+use std::io::Write;
+use std::sync::OnceLock;
+use crossterm::{cursor, terminal, QueueableCommand};
+use crate::backend::Backend;
+use crate::color::{darken, to_rgb, Color, ColorDepth};
+use crate::diff::DiffRenderer;
+use crate::grid::{Cell, Grid};
+use crate::multiplexer::{base64, Multiplexer};
+
+/// Cell size in pixels to use when the terminal doesn't report it.
+const FALLBACK_CELL_SIZE: (usize, usize) = (10, 20);
+
+/// Kitty splits transmitted images into chunks of at most this many base64 bytes.
+const KITTY_CHUNK_SIZE: usize = 4096;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum GraphicsProtocol {
+    Kitty,
+    Sixel,
+}
+
+impl GraphicsProtocol {
+    /// The protocol detected on first use.
+    pub fn current() -> Option<Self> {
+        static CURRENT: OnceLock<Option<GraphicsProtocol>> = OnceLock::new();
+        *CURRENT.get_or_init(Self::detect)
+    }
+
+    /// Without colors the game is drawn as text, which images would defeat.
+    pub fn detect() -> Option<Self> {
+        if Multiplexer::current() != Multiplexer::None || ColorDepth::current() == ColorDepth::NoColor {
+            return None;
+        }
+        let term = std::env::var("TERM").unwrap_or_default();
+        let program = std::env::var("TERM_PROGRAM").unwrap_or_default();
+        if std::env::var_os("KITTY_WINDOW_ID").is_some() || term == "xterm-kitty"
+            || ["WezTerm", "ghostty"].contains(&program.as_str()) {
+            Some(GraphicsProtocol::Kitty)
+        } else if term.contains("sixel") || term.starts_with("foot") || term.starts_with("mlterm")
+            || program == "mlterm" {
+            Some(GraphicsProtocol::Sixel)
+        } else {
+            None
+        }
+    }
+}
+
+/// The colored cells of a line that are drawn as an image.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Strip {
+    /// Column of the first cell.
+    x: usize,
+    colors: Vec<(u8, u8, u8)>,
+}
+
+impl Strip {
+    /// The first run of colored cells in `line`.
+    fn find(line: &[Cell]) -> Option<Strip> {
+        let x = line.iter().position(|cell| tile_color(cell).is_some())?;
+        let colors = line[x..].iter().map_while(tile_color).collect();
+        Some(Strip { x, colors })
+    }
+
+    fn len(&self) -> usize {
+        self.colors.len()
+    }
+}
+
+/// The color of the tile `cell` is drawn as, [None] for cells that stay text.
+fn tile_color(cell: &Cell) -> Option<(u8, u8, u8)> {
+    if cell.fg == Color::Reset {
+        return None;
+    }
+    let percent = match cell.glyph {
+        ' ' => return Some((0, 0, 0)),
+        '▓' => 25,
+        '▒' => 50,
+        '░' => 75,
+        _ => 0,
+    };
+    to_rgb(darken(cell.fg, percent))
+}
+
+#[derive(Debug)]
+pub struct GraphicsRenderer {
+    protocol: GraphicsProtocol,
+    text: DiffRenderer,
+    /// The strip drawn on each line.
+    strips: Vec<Option<Strip>>,
+    /// Width and height of a cell in pixels.
+    cell_size: (usize, usize),
+    /// Whether the screen is cleared before the next frame.
+    invalid: bool,
+}
+
+impl GraphicsRenderer {
+    pub fn new(protocol: GraphicsProtocol) -> Self {
+        GraphicsRenderer {
+            protocol,
+            text: DiffRenderer::new(),
+            strips: Vec::new(),
+            cell_size: FALLBACK_CELL_SIZE,
+            invalid: true,
+        }
+    }
+
+    /// Removes the image of `strip` on line `y`.
+    fn erase(&mut self, out: &mut dyn Write, y: usize, strip: &Strip) -> std::io::Result<()> {
+        match self.protocol {
+            GraphicsProtocol::Kitty => write!(out, "\x1b_Ga=d,d=I,i={},q=2\x1b\\", y + 1)?,
+            GraphicsProtocol::Sixel => {
+                out.queue(cursor::MoveTo(strip.x as u16, y as u16))?;
+                out.queue(terminal::Clear(terminal::ClearType::UntilNewLine))?;
+            }
+        }
+        self.text.invalidate_line(y);
+        Ok(())
+    }
+
+    fn paint(&self, out: &mut dyn Write, y: usize, strip: &Strip) -> std::io::Result<()> {
+        out.queue(cursor::MoveTo(strip.x as u16, y as u16))?;
+        match self.protocol {
+            GraphicsProtocol::Kitty => self.paint_kitty(out, y, strip),
+            GraphicsProtocol::Sixel => self.paint_sixel(out, strip),
+        }
+    }
+
+    /// Transmits the strip as RGB image and places it at the cursor.
+    fn paint_kitty(&self, out: &mut dyn Write, y: usize, strip: &Strip) -> std::io::Result<()> {
+        let (cell_width, cell_height) = self.cell_size;
+        let row = strip.colors.iter()
+            .flat_map(|(r, g, b)| [*r, *g, *b].repeat(cell_width))
+            .collect::<Vec<_>>();
+        let data = base64(&row.repeat(cell_height));
+        let chunks = data.as_bytes().chunks(KITTY_CHUNK_SIZE).collect::<Vec<_>>();
+        for (idx, chunk) in chunks.iter().enumerate() {
+            let more = (idx + 1 < chunks.len()) as u8;
+            if idx == 0 {
+                write!(out, "\x1b_Ga=T,f=24,s={},v={cell_height},i={},C=1,q=2,m={more};",
+                    strip.len() * cell_width, y + 1)?;
+            } else {
+                write!(out, "\x1b_Gm={more};")?;
+            }
+            out.write_all(chunk)?;
+            write!(out, "\x1b\\")?;
+        }
+        Ok(())
+    }
+
+    /// Writes the strip as bands of six pixel rows, one solid run per tile.
+    fn paint_sixel(&self, out: &mut dyn Write, strip: &Strip) -> std::io::Result<()> {
+        let (cell_width, cell_height) = self.cell_size;
+        let mut palette: Vec<(u8, u8, u8)> = Vec::new();
+        for color in &strip.colors {
+            if !palette.contains(color) {
+                palette.push(*color);
+            }
+        }
+        write!(out, "\x1bPq\"1;1;{};{}", strip.len() * cell_width, cell_height)?;
+        for (idx, (r, g, b)) in palette.iter().enumerate() {
+            let percent = |c: u8| c as u32 * 100 / 255;
+            write!(out, "#{idx};2;{};{};{}", percent(*r), percent(*g), percent(*b))?;
+        }
+        for band in (0..cell_height).step_by(6) {
+            let rows = (cell_height - band).min(6);
+            let sixel = (63 + (1u8 << rows) - 1) as char;
+            if band > 0 {
+                write!(out, "-")?;
+            }
+            for color in &strip.colors {
+                let idx = palette.iter().position(|c| c == color).unwrap();
+                write!(out, "#{idx}!{cell_width}{sixel}")?;
+            }
+        }
+        write!(out, "\x1b\\")
+    }
+}
+
+impl Backend for GraphicsRenderer {
+    fn invalidate(&mut self) {
+        self.text.invalidate();
+        self.invalid = true;
+    }
+
+    fn draw(&mut self, mut out: &mut dyn Write, grid: &Grid) -> std::io::Result<()> {
+        if self.invalid {
+            if self.protocol == GraphicsProtocol::Kitty {
+                write!(out, "\x1b_Ga=d,d=A,q=2\x1b\\")?;
+            }
+            self.cell_size = match terminal::window_size() {
+                Ok(size) if size.columns > 0 && size.rows > 0 && size.width > 0 && size.height > 0 => {
+                    ((size.width / size.columns) as usize, (size.height / size.rows) as usize)
+                }
+                _ => FALLBACK_CELL_SIZE,
+            };
+            self.strips.clear();
+            self.invalid = false;
+        }
+
+        let strips = grid.lines().iter().map(|line| Strip::find(line)).collect::<Vec<_>>();
+        let mut text = Grid::new();
+        for (line, strip) in grid.lines().iter().zip(&strips) {
+            let mut line = line.clone();
+            if let Some(strip) = strip {
+                line[strip.x..strip.x + strip.len()].fill(Cell::plain(' '));
+            }
+            text.push_line(line);
+        }
+
+        let previous = std::mem::take(&mut self.strips);
+        for (y, old) in previous.iter().enumerate() {
+            if let Some(old) = old && strips.get(y).is_none_or(|new| new.as_ref() != Some(old)) {
+                self.erase(out, y, old)?;
+            }
+        }
+        self.text.draw(&mut out, &text)?;
+        for (y, strip) in strips.iter().enumerate() {
+            if let Some(strip) = strip && previous.get(y).and_then(Option::as_ref) != Some(strip) {
+                self.paint(out, y, strip)?;
+            }
+        }
+        self.strips = strips;
+        Ok(())
+    }
+}
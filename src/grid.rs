@@ -0,0 +1,221 @@
+//! Styled cells everything on screen is composed of.
+//!
+//! Screens build a [Grid] line by line and frontends turn it into terminal output in a single
+//! pass, either through [Grid::to_ansi] or cell by cell.
+//!
+//! Double-width glyphs like emoji are followed by a [Cell::continuation] covering their second
+//! column, so every cell still is one column wide.
+use crate::color::Color;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Cell {
+    pub glyph: char,
+    pub fg: Color,
+    pub bg: Color,
+}
+
+impl Cell {
+    /// A cell in the terminals default colors.
+    pub fn plain(glyph: char) -> Self {
+        Cell { glyph, fg: Color::Reset, bg: Color::Reset }
+    }
+
+    pub fn colored(glyph: char, fg: Color) -> Self {
+        Cell { glyph, fg, bg: Color::Reset }
+    }
+
+    /// The cell right of a double-width glyph, which isn't drawn itself.
+    pub fn continuation(bg: Color) -> Self {
+        Cell { glyph: CONTINUATION, fg: Color::Reset, bg }
+    }
+
+    pub fn is_continuation(&self) -> bool {
+        self.glyph == CONTINUATION
+    }
+}
+
+const CONTINUATION: char = '\0';
+
+/// Plain cells for each character of `text`.
+pub fn text(text: &str) -> Vec<Cell> {
+    text.chars().map(Cell::plain).collect()
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Grid {
+    lines: Vec<Vec<Cell>>,
+}
+
+impl Grid {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A grid of plain text, lines separated by `\n` with optional `\r`.
+    pub fn from_text(source: &str) -> Self {
+        let mut grid = Grid::new();
+        grid.push_text(source);
+        grid
+    }
+
+    /// Appends the lines of plain `source`.
+    pub fn push_text(&mut self, source: &str) {
+        self.lines.extend(source.split('\n').map(|line| text(line.trim_end_matches('\r'))));
+    }
+
+    pub fn push_line(&mut self, line: Vec<Cell>) {
+        self.lines.push(line);
+    }
+
+    /// Appends all lines of `other`.
+    pub fn append(&mut self, other: Grid) {
+        self.lines.extend(other.lines);
+    }
+
+    /// Places `other` right of all lines, `gap` columns apart. Shorter lines are padded to the
+    /// longest one and the shorter grid is extended with empty lines.
+    pub fn beside(self, other: Grid, gap: usize) -> Grid {
+        let width = self.lines.iter().map(Vec::len).max().unwrap_or_default() + gap;
+        let height = self.lines.len().max(other.lines.len());
+        let mut left = self.lines.into_iter();
+        let mut right = other.lines.into_iter();
+        let mut grid = Grid::new();
+        for _ in 0..height {
+            let mut line = left.next().unwrap_or_default();
+            line.resize(width, Cell::plain(' '));
+            line.extend(right.next().unwrap_or_default());
+            grid.push_line(line);
+        }
+        grid
+    }
+
+    /// Draws `other` over the cells starting at column `x` of line `y`, extending the grid where
+    /// it is too small. Double-width glyphs cut in half are replaced by spaces.
+    pub fn overlay(&mut self, other: &Grid, x: usize, y: usize) {
+        for (idx, source) in other.lines.iter().enumerate() {
+            if self.lines.len() <= y + idx {
+                self.lines.resize(y + idx + 1, Vec::new());
+            }
+            let line = &mut self.lines[y + idx];
+            if line.len() < x + source.len() {
+                line.resize(x + source.len(), Cell::plain(' '));
+            }
+            if x > 0 && line[x].is_continuation() {
+                line[x - 1] = Cell { glyph: ' ', ..line[x - 1] };
+            }
+            line[x..x + source.len()].copy_from_slice(source);
+            if let Some(cell) = line.get_mut(x + source.len()) && cell.is_continuation() {
+                *cell = Cell { glyph: ' ', ..*cell };
+            }
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.lines.iter().map(Vec::len).max().unwrap_or_default()
+    }
+
+    pub fn lines(&self) -> &[Vec<Cell>] {
+        &self.lines
+    }
+
+    /// Replaces every cell with the result of `f`, which gets the position, the cell and the
+    /// cell right of it, e.g. to compose an effects layer.
+    pub fn map_cells(&mut self, mut f: impl FnMut(usize, usize, Cell, Option<&Cell>) -> Cell) {
+        for (y, line) in self.lines.iter_mut().enumerate() {
+            for x in 0..line.len() {
+                line[x] = f(x, y, line[x], line.get(x + 1));
+            }
+        }
+    }
+
+    /// Draws the grid in the middle of `background`, with lines shorter than the longest one
+    /// padded with `fill` so nothing of the background shows through. Grids larger than the
+    /// background start at its edge instead.
+    pub fn centered(mut self, mut background: Grid, fill: Cell) -> Grid {
+        let width = self.width();
+        let left = background.width().saturating_sub(width) / 2;
+        let top = background.lines.len().saturating_sub(self.lines.len()) / 2;
+        for line in &mut self.lines {
+            line.resize(width, fill);
+        }
+        background.overlay(&self, left, top);
+        background
+    }
+
+    /// Cuts lines off after `width` cells, e.g. so they don't wrap in a narrow terminal. A wide
+    /// glyph whose second half is cut off becomes a space.
+    pub fn clip(&mut self, width: usize) {
+        for line in &mut self.lines {
+            if width > 0 && line.get(width).is_some_and(Cell::is_continuation) {
+                line[width - 1].glyph = ' ';
+            }
+            line.truncate(width);
+        }
+    }
+
+    /// Draws a box around all lines, with plain characters if `ascii` is set. Shorter lines are
+    /// padded to the longest one.
+    pub fn bordered(self, ascii: bool) -> Grid {
+        let [top_left, top_right, bottom_left, bottom_right, horizontal, vertical] = if ascii {
+            ['+', '+', '+', '+', '-', '|']
+        } else {
+            ['┌', '┐', '└', '┘', '─', '│']
+        };
+        let width = self.lines.iter().map(Vec::len).max().unwrap_or_default();
+        let edge = |left: char, right: char| {
+            let mut line = vec![Cell::plain(left)];
+            line.extend(vec![Cell::plain(horizontal); width]);
+            line.push(Cell::plain(right));
+            line
+        };
+        let mut grid = Grid::new();
+        grid.push_line(edge(top_left, top_right));
+        for mut line in self.lines {
+            line.resize(width, Cell::plain(' '));
+            line.insert(0, Cell::plain(vertical));
+            line.push(Cell::plain(vertical));
+            grid.push_line(line);
+        }
+        grid.push_line(edge(bottom_left, bottom_right));
+        grid
+    }
+
+    /// The glyphs of the grid without colors, lines separated by `\n`.
+    pub fn to_plain(&self) -> String {
+        self.lines.iter()
+            .map(|line| line.iter().filter(|cell| !cell.is_continuation()).map(|cell| cell.glyph).collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// The grid as text with ANSI colors, lines separated by `\r\n`, which starts the next line
+    /// at its beginning in raw mode and on Windows consoles alike.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn to_ansi(&self) -> String {
+        use std::fmt::Write;
+        use crossterm::style::{SetBackgroundColor, SetForegroundColor};
+        let mut out = String::new();
+        for (idx, line) in self.lines.iter().enumerate() {
+            if idx > 0 {
+                out.push_str("\r\n");
+            }
+            let mut colors = (Color::Reset, Color::Reset);
+            for cell in line {
+                if cell.fg != colors.0 {
+                    write!(out, "{}", SetForegroundColor(cell.fg.into())).unwrap();
+                }
+                if cell.bg != colors.1 {
+                    write!(out, "{}", SetBackgroundColor(cell.bg.into())).unwrap();
+                }
+                colors = (cell.fg, cell.bg);
+                if !cell.is_continuation() {
+                    out.push(cell.glyph);
+                }
+            }
+            if colors != (Color::Reset, Color::Reset) {
+                write!(out, "{}{}", SetForegroundColor(Color::Reset.into()), SetBackgroundColor(Color::Reset.into())).unwrap();
+            }
+        }
+        out
+    }
+}
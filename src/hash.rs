@@ -0,0 +1,47 @@
+//! Stable hashes of generated boards.
+//!
+//! Unlike [std::hash::DefaultHasher] the hash is guaranteed to stay the same across builds and
+//! platforms, so it can be used to reference boards in bug reports or to detect seeds that
+//! generate the same rows.
+use std::fmt;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use crate::difficulty::Difficulty;
+use crate::map::generate_rows;
+use crate::stripe::Stripe;
+
+/// Amount of rows considered when hashing a seed.
+pub const HASHED_ROWS: usize = 40;
+
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct BoardHash(pub u64);
+
+impl fmt::Display for BoardHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+/// Hashes `bytes` using 64 bit FNV-1a.
+pub fn hash_bytes(bytes: impl IntoIterator<Item = u8>) -> BoardHash {
+    let mut hash = FNV_OFFSET;
+    for byte in bytes {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+    BoardHash(hash)
+}
+
+/// Hashes the state of `rows`.
+pub fn hash_rows(rows: &[Stripe]) -> BoardHash {
+    hash_bytes(rows.iter().flat_map(Stripe::fingerprint))
+}
+
+/// Hashes the first [HASHED_ROWS] rows generated by `seed` at `difficulty`.
+pub fn hash_seed(seed: u64, difficulty: &Difficulty) -> BoardHash {
+    let mut rng = StdRng::seed_from_u64(seed);
+    hash_rows(&generate_rows(&mut rng, HASHED_ROWS, difficulty))
+}
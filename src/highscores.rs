@@ -0,0 +1,67 @@
+//! Local leaderboards, one table per mode.
+//!
+//! Stored as `table = score,score,...` lines in `highscores.conf` inside [profile_dir].
+use std::collections::BTreeMap;
+use crate::profile::{format_entries, parse_entries, profile_dir};
+
+const HIGHSCORES_FILE: &str = "highscores.conf";
+
+/// Amount of scores kept per table.
+pub const TABLE_SIZE: usize = 10;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HighScores {
+    /// Scores per table name, highest first.
+    tables: BTreeMap<String, Vec<u64>>,
+}
+
+impl HighScores {
+    pub fn load() -> HighScores {
+        let mut scores = HighScores::default();
+        let Ok(source) = std::fs::read_to_string(profile_dir().join(HIGHSCORES_FILE)) else {
+            return scores;
+        };
+        for (table, values) in parse_entries(&source) {
+            let mut values = values.split(',')
+                .filter_map(|value| value.trim().parse().ok())
+                .collect::<Vec<u64>>();
+            values.sort_unstable_by(|a, b| b.cmp(a));
+            values.truncate(TABLE_SIZE);
+            scores.tables.insert(table.to_string(), values);
+        }
+        scores
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        let entries = self.tables.iter()
+            .map(|(table, values)| {
+                let values = values.iter().map(u64::to_string).collect::<Vec<_>>().join(",");
+                (table.as_str(), values)
+            })
+            .collect::<Vec<_>>();
+        std::fs::create_dir_all(profile_dir())?;
+        std::fs::write(profile_dir().join(HIGHSCORES_FILE), format_entries(&entries))
+    }
+
+    /// The scores of `table`, highest first.
+    pub fn table(&self, table: &str) -> &[u64] {
+        self.tables.get(table).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    pub fn best(&self, table: &str) -> Option<u64> {
+        self.table(table).first().copied()
+    }
+
+    /// Adds `score` to `table` and returns its rank starting at 0, or [None] if it didn't make it
+    /// into the table.
+    pub fn submit(&mut self, table: &str, score: u64) -> Option<usize> {
+        let values = self.tables.entry(table.to_string()).or_default();
+        let rank = values.iter().position(|value| score > *value).unwrap_or(values.len());
+        if rank >= TABLE_SIZE || score == 0 {
+            return None;
+        }
+        values.insert(rank, score);
+        values.truncate(TABLE_SIZE);
+        Some(rank)
+    }
+}
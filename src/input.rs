@@ -0,0 +1,400 @@
+//! Where keys come from: the terminal or a script.
+//!
+//! Scripts list one key per line, each after waiting a number of ticks since the previous one:
+//!
+//! ```text
+//! # Wait a second, then hop up twice.
+//! 10 up
+//! 0 up
+//! 3 left
+//! ```
+//!
+//! Keys are `up`, `down`, `left`, `right`, `enter`, `esc` or a single character. Lines starting
+//! with `#` are ignored.
+//!
+//! A [ScriptRecorder] writes the keys of a run in this format, so it can be played again. Every
+//! [CHECK_TICKS] it also notes the [crate::map::MapState::state_hash] of the game as a line like
+//! `20 check 5bd1e9954c6f0a31`, which [ScriptedInput::verify] compares while playing the script,
+//! so a replay that went differently, e.g. because of a random draw that isn't seeded, is
+//! reported with the first tick it differed at.
+use std::collections::VecDeque;
+use std::fmt;
+use std::path::Path;
+use std::time::Duration;
+use crossterm::event::KeyCode;
+use crate::app::{App, Event};
+use crate::backend::TerminalBackend;
+use crate::hash::BoardHash;
+use crate::map::TICKS_PER_SECOND;
+
+/// Ticks between two state hashes in recorded scripts.
+pub const CHECK_TICKS: u64 = TICKS_PER_SECOND;
+
+pub trait InputSource {
+    /// The next key or resize, waiting at most `timeout` for one. `tick` is the amount of ticks
+    /// simulated so far.
+    fn next_event(&mut self, tick: u64, timeout: Duration) -> std::io::Result<Option<Event>>;
+}
+
+impl<T: TerminalBackend + ?Sized> InputSource for T {
+    fn next_event(&mut self, _tick: u64, timeout: Duration) -> std::io::Result<Option<Event>> {
+        self.poll_event(timeout)
+    }
+}
+
+#[derive(Debug)]
+pub enum ScriptError {
+    Io(std::io::Error),
+    /// The line number (starting at 1) and a description of what is wrong.
+    Parse(usize, String),
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScriptError::Io(err) => write!(f, "Can't read input script: {err}"),
+            ScriptError::Parse(line, err) => write!(f, "Invalid input script in line {line}: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for ScriptError {}
+
+/// A replay that doesn't match its recording anymore.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Desync {
+    pub tick: u64,
+    pub expected: BoardHash,
+    /// [None] if no game was running.
+    pub actual: Option<BoardHash>,
+}
+
+impl fmt::Display for Desync {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "The replay differs from its recording at tick {}: expected state {}, ", self.tick, self.expected)?;
+        match self.actual {
+            Some(actual) => write!(f, "got {actual}"),
+            None => write!(f, "but no game is running"),
+        }
+    }
+}
+
+impl std::error::Error for Desync {}
+
+/// A line of a script.
+enum ScriptLine {
+    Key(KeyCode),
+    /// The state hash of the game after the tick.
+    Check(BoardHash),
+}
+
+/// Keys pressed at fixed ticks, read from a script.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScriptedInput {
+    /// The tick each key is pressed at, in order.
+    keys: VecDeque<(u64, KeyCode)>,
+    /// The tick each state hash was noted after, in order.
+    checks: VecDeque<(u64, BoardHash)>,
+}
+
+impl ScriptedInput {
+    pub fn load(path: &Path) -> Result<ScriptedInput, ScriptError> {
+        let source = std::fs::read_to_string(path).map_err(ScriptError::Io)?;
+        Self::parse(&source)
+    }
+
+    pub fn parse(source: &str) -> Result<ScriptedInput, ScriptError> {
+        let mut keys = VecDeque::new();
+        let mut checks = VecDeque::new();
+        let mut tick = 0;
+        for (idx, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (wait, line) = parse_line(line).map_err(|err| ScriptError::Parse(idx + 1, err))?;
+            tick += wait;
+            match line {
+                ScriptLine::Key(key) => keys.push_back((tick, key)),
+                ScriptLine::Check(hash) => checks.push_back((tick, hash)),
+            }
+        }
+        Ok(ScriptedInput { keys, checks })
+    }
+
+    /// Whether all keys were pressed.
+    pub fn is_finished(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Compares the state hash of the game after `tick` ticks with the recorded one, if one was
+    /// noted for that tick.
+    pub fn verify(&mut self, tick: u64, actual: Option<BoardHash>) -> Result<(), Desync> {
+        while let Some((due, expected)) = self.checks.front().copied().filter(|(due, _)| *due <= tick) {
+            self.checks.pop_front();
+            if due == tick && actual != Some(expected) {
+                return Err(Desync { tick, expected, actual });
+            }
+        }
+        Ok(())
+    }
+}
+
+impl InputSource for ScriptedInput {
+    /// Returns the keys due at `tick` one by one, never waits.
+    fn next_event(&mut self, tick: u64, _timeout: Duration) -> std::io::Result<Option<Event>> {
+        if self.keys.front().is_some_and(|(due, _)| *due <= tick) {
+            return Ok(self.keys.pop_front().map(|(_, key)| Event::Key(key)));
+        }
+        Ok(None)
+    }
+}
+
+/// Collects the keys pressed during a run as a script.
+#[derive(Debug, Clone, Default)]
+pub struct ScriptRecorder {
+    script: String,
+    /// The tick of the last recorded key.
+    last_tick: u64,
+}
+
+impl ScriptRecorder {
+    /// Starts a script with `comment`, e.g. how to play it again.
+    pub fn new(comment: &str) -> ScriptRecorder {
+        let script = comment.lines().map(|line| format!("# {line}\n")).collect();
+        ScriptRecorder { script, last_tick: 0 }
+    }
+
+    /// Adds `event` if it is a key that scripts can press, pressed after `tick` ticks.
+    pub fn record(&mut self, tick: u64, event: &Event) {
+        let Event::Key(key) = event else { return };
+        let Some(name) = key_name(*key) else { return };
+        self.script.push_str(&format!("{} {name}\n", tick - self.last_tick));
+        self.last_tick = tick;
+    }
+
+    /// Notes `hash`, the state hash of the game after `tick` ticks, every [CHECK_TICKS].
+    pub fn record_state(&mut self, tick: u64, hash: BoardHash) {
+        if tick.is_multiple_of(CHECK_TICKS) {
+            self.script.push_str(&check_line(tick - self.last_tick, hash));
+            self.last_tick = tick;
+        }
+    }
+
+    pub fn script(&self) -> &str {
+        &self.script
+    }
+
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        std::fs::write(path, &self.script)
+    }
+}
+
+/// How `key` is written in scripts, [None] if they can't press it.
+pub(crate) fn key_name(key: KeyCode) -> Option<String> {
+    Some(match key {
+        KeyCode::Up => "up".to_string(),
+        KeyCode::Down => "down".to_string(),
+        KeyCode::Left => "left".to_string(),
+        KeyCode::Right => "right".to_string(),
+        KeyCode::Enter => "enter".to_string(),
+        KeyCode::Esc => "esc".to_string(),
+        KeyCode::Char(c) if !c.is_whitespace() => c.to_string(),
+        _ => return None,
+    })
+}
+
+/// The line noting the state `hash` after waiting `wait` ticks.
+pub(crate) fn check_line(wait: u64, hash: BoardHash) -> String {
+    format!("{wait} check {hash}\n")
+}
+
+fn parse_line(line: &str) -> Result<(u64, ScriptLine), String> {
+    let mut parts = line.split_whitespace();
+    let wait = parts.next().unwrap_or_default().parse().map_err(|_| "expected the ticks to wait".to_string())?;
+    let key = match parts.next().ok_or("missing key")? {
+        "check" => {
+            let hash = parts.next().and_then(|hash| u64::from_str_radix(hash, 16).ok()).ok_or("expected a state hash")?;
+            return Ok((wait, ScriptLine::Check(BoardHash(hash))));
+        }
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "enter" => KeyCode::Enter,
+        "esc" => KeyCode::Esc,
+        key if key.chars().count() == 1 => KeyCode::Char(key.chars().next().unwrap()),
+        key => return Err(format!("unknown key {key}")),
+    };
+    if parts.next().is_some() {
+        return Err("expected one key per line".to_string());
+    }
+    Ok((wait, ScriptLine::Key(key)))
+}
+
+/// Runs `app` on `terminal` for `ticks` ticks as fast as possible, like the game loop without the
+/// waiting: the events of `terminal` and the keys of `script` due at each tick are handled before
+/// it and a frame is drawn after it if anything changed. Stops early once the app quits.
+///
+/// Returns where the game first differed from the state hashes noted in `script`, if it did.
+pub fn drive(app: &mut App, terminal: &mut dyn TerminalBackend, mut script: Option<&mut ScriptedInput>, ticks: u64) -> std::io::Result<Option<Desync>> {
+    let (width, height) = terminal.size()?;
+    app.handle_event(Event::Resize { width, height });
+    let mut desync = None;
+    for tick in 0..ticks {
+        if app.should_quit() {
+            break;
+        }
+        while let Some(event) = terminal.next_event(tick, Duration::ZERO)? {
+            app.handle_event(event);
+        }
+        if let Some(script) = &mut script {
+            while let Some(event) = script.next_event(tick, Duration::ZERO)? {
+                app.handle_event(event);
+            }
+        }
+        app.handle_event(Event::Tick);
+        if let Some(script) = &mut script && desync.is_none() {
+            desync = script.verify(tick + 1, app.state_hash()).err();
+        }
+        if app.take_dirty() {
+            terminal.draw(&app.view())?;
+        }
+    }
+    Ok(desync)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::Frame;
+    use crate::backend::DummyBackend;
+    use crate::keymap::KeyboardLayout;
+    use crate::locale::Text;
+    use crate::map::{DeathCause, MapState, TICKS_PER_SECOND};
+    use crate::mode::GameMode;
+    use crate::profile::Profile;
+    use crate::stripe::{GreenStripe, Stripe};
+
+    /// A run on `rows` above the four empty ones the player starts on, which keeps nothing on disk.
+    fn app(rows: impl IntoIterator<Item = Stripe>) -> App {
+        let rows = [Stripe::Empty; 4].into_iter().chain(rows).collect();
+        App::play(profile(), GameMode::Endless, MapState::from_rows(rows, 7)).without_records()
+    }
+
+    fn profile() -> Profile {
+        Profile { layout: Some(KeyboardLayout::Qwerty), ..Profile::default() }
+    }
+
+    /// A zen run on `seed`, which can't end early.
+    fn zen(seed: u64) -> App {
+        let map = MapState::with_seed(seed).without_collisions().without_wall_of_death();
+        App::play(profile(), GameMode::Zen, map).without_records()
+    }
+
+    /// The script of a [zen] run on `seed` that hops up every few ticks.
+    fn record(seed: u64, ticks: u64) -> String {
+        let mut app = zen(seed);
+        let mut recorder = ScriptRecorder::new(&format!("Zen run on seed {seed}"));
+        for tick in 0..ticks {
+            if tick % 7 == 3 {
+                let event = Event::Key(KeyCode::Up);
+                recorder.record(tick, &event);
+                app.handle_event(event);
+            }
+            app.handle_event(Event::Tick);
+            recorder.record_state(tick + 1, app.state_hash().unwrap());
+        }
+        recorder.script().to_string()
+    }
+
+    fn text(frame: &Frame) -> String {
+        frame.grid.lines().iter().map(|line| line.iter().map(|cell| cell.glyph).collect::<String>() + "\n").collect()
+    }
+
+    #[test]
+    fn dummy_terminals_show_the_run_until_its_summary() {
+        let tree = Stripe::Green(GreenStripe::new(core::array::from_fn(|x| x == 7)));
+        let mut app = app([tree]);
+        let mut terminal = DummyBackend::new(80, 24);
+        terminal.events.push_back(Event::Key(KeyCode::Up));
+        drive(&mut app, &mut terminal, None, 100).unwrap();
+
+        assert!(terminal.frames.len() > 1);
+        assert!(terminal.frames.iter().all(|frame| frame.grid.lines().len() <= 24 && frame.grid.width() <= 80));
+        assert_eq!(app.last_score(), Some(1));
+        let language = app.profile().theme.language;
+        assert!(!text(&terminal.frames[0]).contains(language.text(Text::ContinuePrompt)));
+        assert!(text(terminal.frames.last().unwrap()).contains(language.text(Text::ContinuePrompt)));
+        assert!(text(terminal.frames.last().unwrap()).contains(language.text(Text::Cause(DeathCause::RanIntoTree))));
+
+        terminal.events.push_back(Event::Key(KeyCode::Enter));
+        drive(&mut app, &mut terminal, None, 10).unwrap();
+        assert!(app.should_quit());
+    }
+
+    #[test]
+    fn scripts_press_their_keys_at_their_ticks() {
+        let play = |ticks| {
+            let mut script = ScriptedInput::parse("# Wait, then hop onto the finish.\n5 up\n\n0 up\n").unwrap();
+            let mut app = app([Stripe::Empty, Stripe::Finish]);
+            let mut terminal = DummyBackend::new(80, 24);
+            drive(&mut app, &mut terminal, Some(&mut script), ticks).unwrap();
+            (script.is_finished(), app, terminal)
+        };
+        let (finished, app, _) = play(5);
+        assert!(!finished);
+        assert_eq!(app.last_score(), None);
+
+        let (finished, app, terminal) = play(6);
+        assert!(finished);
+        assert_eq!(app.last_score(), Some(2));
+        let language = app.profile().theme.language;
+        assert!(text(terminal.frames.last().unwrap()).contains(language.text(Text::LevelComplete)));
+    }
+
+    #[test]
+    fn scripts_name_the_line_they_fail_at() {
+        for (source, line) in [("1 up\nup\n", 2), ("# comment\n\n3 jump\n", 3), ("0 up left\n", 1), ("0 check zz\n", 1)] {
+            match ScriptedInput::parse(source) {
+                Err(ScriptError::Parse(actual, _)) => assert_eq!(actual, line, "{source:?}"),
+                other => panic!("{source:?} parsed as {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn recorded_keys_parse_back() {
+        let mut recorder = ScriptRecorder::new("Two lines\nof comments");
+        for (tick, key) in [(3, KeyCode::Up), (3, KeyCode::Char('p')), (10, KeyCode::Left), (12, KeyCode::Enter)] {
+            recorder.record(tick, &Event::Key(key));
+        }
+        recorder.record(14, &Event::Tick);
+        let mut script = ScriptedInput::parse(recorder.script()).unwrap();
+        let mut pressed = Vec::new();
+        for tick in 0..20 {
+            while let Some(event) = script.next_event(tick, Duration::ZERO).unwrap() {
+                pressed.push((tick, event));
+            }
+        }
+        assert_eq!(pressed, [
+            (3, Event::Key(KeyCode::Up)), (3, Event::Key(KeyCode::Char('p'))), (10, Event::Key(KeyCode::Left)), (12, Event::Key(KeyCode::Enter)),
+        ]);
+    }
+
+    #[test]
+    fn replays_match_the_state_hashes_of_their_recording() {
+        let ticks = 5 * TICKS_PER_SECOND;
+        let recording = record(42, ticks);
+        assert_eq!(recording.lines().filter(|line| line.contains("check")).count() as u64, ticks / CHECK_TICKS);
+
+        let mut script = ScriptedInput::parse(&recording).unwrap();
+        assert_eq!(drive(&mut zen(42), &mut DummyBackend::new(80, 24), Some(&mut script), ticks).unwrap(), None);
+        assert!(script.is_finished());
+
+        let mut script = ScriptedInput::parse(&recording).unwrap();
+        let desync = drive(&mut zen(43), &mut DummyBackend::new(80, 24), Some(&mut script), ticks).unwrap().unwrap();
+        assert_eq!(desync.tick, CHECK_TICKS);
+        assert!(desync.actual.is_some_and(|actual| actual != desync.expected));
+    }
+}
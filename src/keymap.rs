@@ -0,0 +1,145 @@
+use crossterm::event::KeyCode;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Action {
+    Up,
+    Down,
+    Left,
+    Right,
+    Pause,
+    /// Takes back the last move in casual runs.
+    Undo,
+    /// Shows the keys and what the colors mean.
+    Help,
+    Quit,
+}
+
+/// Physical keyboard layouts with different positions for the usual movement keys.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum KeyboardLayout {
+    Qwerty,
+    Azerty,
+    Qwertz,
+    Dvorak,
+    Colemak,
+}
+
+impl KeyboardLayout {
+    pub const ALL: [KeyboardLayout; 5] = [
+        KeyboardLayout::Qwerty,
+        KeyboardLayout::Azerty,
+        KeyboardLayout::Qwertz,
+        KeyboardLayout::Dvorak,
+        KeyboardLayout::Colemak,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            KeyboardLayout::Qwerty => "qwerty",
+            KeyboardLayout::Azerty => "azerty",
+            KeyboardLayout::Qwertz => "qwertz",
+            KeyboardLayout::Dvorak => "dvorak",
+            KeyboardLayout::Colemak => "colemak",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|layout| layout.name() == name)
+    }
+
+    /// The keys in the position of WASD on a QWERTY keyboard as up, left, down and right.
+    pub fn movement_keys(self) -> [char; 4] {
+        match self {
+            KeyboardLayout::Qwerty | KeyboardLayout::Qwertz => ['w', 'a', 's', 'd'],
+            KeyboardLayout::Azerty => ['z', 'q', 's', 'd'],
+            KeyboardLayout::Dvorak => [',', 'a', 'o', 'e'],
+            KeyboardLayout::Colemak => ['w', 'a', 'r', 's'],
+        }
+    }
+
+    /// Guesses the layout from the environment, without any guarantee of being right.
+    pub fn detect() -> Option<Self> {
+        if let Ok(layout) = std::env::var("XKB_DEFAULT_LAYOUT") {
+            let variant = std::env::var("XKB_DEFAULT_VARIANT").unwrap_or_default();
+            match (layout.as_str(), variant.as_str()) {
+                (_, "dvorak") => return Some(KeyboardLayout::Dvorak),
+                (_, "colemak") => return Some(KeyboardLayout::Colemak),
+                ("fr" | "be", _) => return Some(KeyboardLayout::Azerty),
+                ("de" | "at" | "ch" | "cz" | "hu" | "sk", _) => return Some(KeyboardLayout::Qwertz),
+                ("us" | "gb", _) => return Some(KeyboardLayout::Qwerty),
+                _ => {}
+            }
+        }
+        let locale = ["LC_ALL", "LC_CTYPE", "LANG"].into_iter()
+            .find_map(|var| std::env::var(var).ok().filter(|value| !value.is_empty()))?;
+        match locale.get(..5)? {
+            "fr_FR" | "fr_BE" | "nl_BE" => Some(KeyboardLayout::Azerty),
+            "de_DE" | "de_AT" | "de_CH" | "cs_CZ" | "hu_HU" | "sk_SK" => Some(KeyboardLayout::Qwertz),
+            _ => None,
+        }
+    }
+}
+
+/// The player a movement key belongs to in co-op runs: the arrow keys move the second player,
+/// all other keys the first one.
+pub fn coop_player(code: KeyCode) -> usize {
+    match code {
+        KeyCode::Up | KeyCode::Left | KeyCode::Down | KeyCode::Right => 1,
+        _ => 0,
+    }
+}
+
+/// Maps keys to actions. Arrow keys always work regardless of the layout.
+pub struct Keymap {
+    bindings: Vec<(KeyCode, Action)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self::for_layout(KeyboardLayout::Qwerty)
+    }
+}
+
+impl Keymap {
+    pub fn for_layout(layout: KeyboardLayout) -> Self {
+        let [up, left, down, right] = layout.movement_keys();
+        let mut bindings = vec![
+            (KeyCode::Up, Action::Up),
+            (KeyCode::Left, Action::Left),
+            (KeyCode::Down, Action::Down),
+            (KeyCode::Right, Action::Right),
+            (KeyCode::Char(up), Action::Up),
+            (KeyCode::Char(left), Action::Left),
+            (KeyCode::Char(down), Action::Down),
+            (KeyCode::Char(right), Action::Right),
+            (KeyCode::Char('p'), Action::Pause),
+            (KeyCode::Char('u'), Action::Undo),
+            (KeyCode::Char('?'), Action::Help),
+            (KeyCode::Esc, Action::Quit),
+        ];
+        if !layout.movement_keys().contains(&'q') {
+            bindings.push((KeyCode::Char('q'), Action::Quit));
+        }
+        Keymap { bindings }
+    }
+
+    pub fn action(&self, code: KeyCode) -> Option<Action> {
+        self.bindings.iter()
+            .find(|(key, _)| *key == code)
+            .map(|(_, action)| *action)
+    }
+
+    /// Name of the first key bound to `action`, e.g. for hints.
+    pub fn key_name(&self, action: Action) -> String {
+        let key = self.bindings.iter()
+            .filter(|(_, bound)| *bound == action)
+            .map(|(key, _)| *key)
+            .find(|key| matches!(key, KeyCode::Char(_)))
+            .or_else(|| self.bindings.iter().find(|(_, bound)| *bound == action).map(|(key, _)| *key));
+        match key {
+            Some(KeyCode::Char(c)) => c.to_string(),
+            Some(key) => key.to_string(),
+            None => "?".to_string(),
+        }
+    }
+}
@@ -0,0 +1,242 @@
+//! Hand-authored levels.
+//!
+//! A level file lists one row per line from top to bottom, so the file reads like the board
+//! looks on screen. Empty lines and lines starting with `#` are ignored. Rows are:
+//! - `green [trees]`: grass with trees at every `1` of a mask like `0100010`
+//! - `rail <cycle length> [start]`: railroad, optionally starting at a cycle position
+//! - `road <left|right> [cars]`: road driving in the given direction starting with cars at every
+//!   `1` of the mask
+//! - `traffic <left|right> <cycle length> [cars]`: road like above with a traffic light that
+//!   turns red every cycle
+//! - `empty`: nothing
+//!
+//! Masks of 7 digits set the tiles on the screen, the ones beside them are trees on grass and free
+//! on roads. Masks of 15 digits set every tile of the row.
+//!
+//! A finish line is added above the topmost row. A line `name <text>` sets the level name.
+use std::fmt;
+use std::path::Path;
+use crate::stripe::{self, GreenStripe, Railroad, Road, Stripe, TrafficRoad, STRIPE_LENGTH, VISIBLE_TILES};
+
+/// Levels compiled into the binary as `(name, source)`.
+pub const BUNDLED: [(&str, &str); 3] = [
+    ("first-steps", include_str!("../levels/first-steps.level")),
+    ("rush-hour", include_str!("../levels/rush-hour.level")),
+    ("tracks", include_str!("../levels/tracks.level")),
+];
+
+#[derive(Debug, Clone)]
+pub struct Level {
+    pub name: Option<String>,
+    /// Rows from bottom to top, ending with [Stripe::Finish].
+    pub rows: Vec<Stripe>,
+}
+
+#[derive(Debug)]
+pub enum LevelError {
+    Io(std::io::Error),
+    /// The line number (starting at 1) and a description of what is wrong.
+    Parse(usize, String),
+    Empty,
+}
+
+impl fmt::Display for LevelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LevelError::Io(err) => write!(f, "Can't read level: {err}"),
+            LevelError::Parse(line, err) => write!(f, "Invalid level in line {line}: {err}"),
+            LevelError::Empty => write!(f, "The level contains no rows"),
+        }
+    }
+}
+
+impl std::error::Error for LevelError {}
+
+impl Level {
+    /// Loads a bundled level by name or a level file by path.
+    pub fn load(name_or_path: &str) -> Result<Level, LevelError> {
+        if let Some((_, source)) = BUNDLED.iter().find(|(name, _)| *name == name_or_path) {
+            return Level::parse(source);
+        }
+        let source = std::fs::read_to_string(Path::new(name_or_path)).map_err(LevelError::Io)?;
+        Level::parse(&source)
+    }
+
+    /// Writes the level in the level file format.
+    ///
+    /// Rows that can't be expressed in the format, like scripted stripes, are written as `empty`.
+    pub fn to_source(&self) -> String {
+        let mut lines = Vec::new();
+        if let Some(name) = &self.name {
+            lines.push(format!("name {name}"));
+        }
+        lines.extend(self.rows.iter().rev().filter_map(format_row));
+        lines.push(String::new());
+        lines.join("\n")
+    }
+
+    pub fn parse(source: &str) -> Result<Level, LevelError> {
+        let mut name = None;
+        let mut rows = vec![Stripe::Finish];
+        for (idx, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(level_name) = line.strip_prefix("name ") {
+                name = Some(level_name.trim().to_string());
+                continue;
+            }
+            let row = parse_row(line).map_err(|err| LevelError::Parse(idx + 1, err))?;
+            rows.push(row);
+        }
+        if rows.len() == 1 {
+            return Err(LevelError::Empty);
+        }
+        rows.reverse();
+        Ok(Level { name, rows })
+    }
+}
+
+fn parse_row(line: &str) -> Result<Stripe, String> {
+    let mut parts = line.split_whitespace();
+    let kind = parts.next().unwrap_or_default();
+    let row = match kind {
+        "empty" => Stripe::Empty,
+        "green" => Stripe::Green(GreenStripe::new(parse_mask(parts.next(), true)?)),
+        "rail" => {
+            let cycle_length = parse_number(parts.next(), "cycle length")?;
+            let mut rail = Railroad::new(cycle_length);
+            if let Some(start) = parts.next() {
+                rail = rail.with_cycle_pos(parse_number(Some(start), "start")?);
+            }
+            Stripe::Rail(rail)
+        }
+        "road" | "traffic" => {
+            let left = match parts.next() {
+                Some("left") => true,
+                Some("right") => false,
+                other => return Err(format!("Expected road direction left or right, got {other:?}")),
+            };
+            if kind == "traffic" {
+                let cycle_length = parse_number(parts.next(), "cycle length")?;
+                Stripe::TrafficRoad(TrafficRoad::new(Road::new(left, parse_mask(parts.next(), false)?), cycle_length))
+            } else {
+                Stripe::Road(Road::new(left, parse_mask(parts.next(), false)?))
+            }
+        }
+        _ => return Err(format!("Unknown row type {kind:?}")),
+    };
+    if let Some(extra) = parts.next() {
+        return Err(format!("Unexpected {extra:?}"));
+    }
+    Ok(row)
+}
+
+fn format_row(row: &Stripe) -> Option<String> {
+    Some(match row {
+        Stripe::Finish | Stripe::Checkpoint => return None,
+        Stripe::Green(green) => format!("green {}", format_mask(green.trees())),
+        Stripe::Rail(rail) if rail.cycle_pos() == rail.cycle_length() => format!("rail {}", rail.cycle_length()),
+        Stripe::Rail(rail) => format!("rail {} {}", rail.cycle_length(), rail.cycle_pos()),
+        Stripe::Road(road) => format!("road {} {}", if road.left() { "left" } else { "right" }, format_mask(road.cars())),
+        Stripe::TrafficRoad(traffic) => format!("traffic {} {} {}", if traffic.road().left() { "left" } else { "right" },
+                                                traffic.cycle_length(), format_mask(traffic.road().cars())),
+        _ => "empty".to_string(),
+    })
+}
+
+fn format_mask(mask: [bool; STRIPE_LENGTH]) -> String {
+    mask.iter().map(|set| if *set { '1' } else { '0' }).collect()
+}
+
+/// Parses a mask like `0100010` for the tiles on the screen, with `outside` on the tiles around
+/// them, or one for all [STRIPE_LENGTH] tiles. Without a mask the tiles on the screen are all
+/// zeros.
+fn parse_mask(mask: Option<&str>, outside: bool) -> Result<[bool; STRIPE_LENGTH], String> {
+    let Some(mask) = mask else { return Ok(stripe::centered([false; VISIBLE_TILES], outside)) };
+    let mut res = vec![false; mask.len()];
+    if mask.len() != VISIBLE_TILES && mask.len() != STRIPE_LENGTH {
+        return Err(format!("Expected a mask of {VISIBLE_TILES} or {STRIPE_LENGTH} digits, got {mask:?}"));
+    }
+    for (cell, c) in res.iter_mut().zip(mask.chars()) {
+        *cell = match c {
+            '0' => false,
+            '1' => true,
+            _ => return Err(format!("Masks may only contain 0 and 1, got {mask:?}")),
+        };
+    }
+    Ok(match <[bool; VISIBLE_TILES]>::try_from(res.as_slice()) {
+        Ok(visible) => stripe::centered(visible, outside),
+        Err(_) => res.try_into().unwrap(),
+    })
+}
+
+fn parse_number(number: Option<&str>, what: &str) -> Result<usize, String> {
+    number.ok_or(format!("Missing {what}"))?
+        .parse()
+        .map_err(|_| format!("Invalid {what} {number:?}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn levels_parse_from_top_to_bottom() {
+        let level = Level::parse("name Test\n# The goal\nrail 20 5\n\ntraffic right 12 0000001\nroad left 100000000000001\ngreen 0100010\nempty\n").unwrap();
+        assert_eq!(level.name.as_deref(), Some("Test"));
+        assert_eq!(level.rows.len(), 6);
+        assert!(matches!(level.rows[0], Stripe::Empty));
+        let Stripe::Green(green) = level.rows[1] else { panic!("{:?}", level.rows[1]) };
+        assert_eq!(green.trees(), stripe::centered([false, true, false, false, false, true, false], true));
+        let Stripe::Road(road) = level.rows[2] else { panic!("{:?}", level.rows[2]) };
+        assert!(road.left());
+        assert_eq!(road.cars(), std::array::from_fn(|x| x == 0 || x == STRIPE_LENGTH - 1));
+        let Stripe::TrafficRoad(traffic) = level.rows[3] else { panic!("{:?}", level.rows[3]) };
+        assert!(!traffic.road().left());
+        assert_eq!(traffic.cycle_length(), 12);
+        let Stripe::Rail(rail) = level.rows[4] else { panic!("{:?}", level.rows[4]) };
+        assert_eq!((rail.cycle_length(), rail.cycle_pos()), (20, 5));
+        assert!(matches!(level.rows[5], Stripe::Finish));
+    }
+
+    #[test]
+    fn bundled_levels_parse() {
+        for (name, source) in BUNDLED {
+            let level = Level::parse(source).unwrap_or_else(|err| panic!("{name}: {err}"));
+            assert!(matches!(level.rows.last(), Some(Stripe::Finish)), "{name}");
+        }
+    }
+
+    #[test]
+    fn unknown_rows_are_rejected() {
+        assert!(matches!(Level::parse("empty\nriver left\n"), Err(LevelError::Parse(2, err)) if err.contains("\"river\"")));
+        assert!(matches!(parse_row("road up"), Err(err) if err.contains("left or right")));
+        assert!(matches!(parse_row("empty 0000000"), Err(err) if err.contains("Unexpected")));
+    }
+
+    #[test]
+    fn masks_need_a_known_length() {
+        assert!(matches!(Level::parse("green 010001\n"), Err(LevelError::Parse(1, err)) if err.contains("\"010001\"")));
+        assert!(parse_mask(Some("0100010"), false).is_ok());
+        assert!(parse_mask(Some(&"1".repeat(STRIPE_LENGTH)), false).is_ok());
+        assert!(parse_mask(Some(&"1".repeat(STRIPE_LENGTH + 1)), false).is_err());
+        assert!(matches!(parse_mask(Some("0120010"), false), Err(err) if err.contains("0 and 1")));
+        assert_eq!(parse_mask(None, true).unwrap(), stripe::centered([false; VISIBLE_TILES], true));
+    }
+
+    #[test]
+    fn numbers_must_be_given() {
+        assert_eq!(parse_number(Some("42"), "start"), Ok(42));
+        assert_eq!(parse_number(None, "cycle length"), Err("Missing cycle length".to_string()));
+        assert!(parse_number(Some("-1"), "start").is_err());
+        assert!(matches!(Level::parse("rail\n"), Err(LevelError::Parse(1, err)) if err == "Missing cycle length"));
+    }
+
+    #[test]
+    fn levels_without_rows_have_no_finish_line() {
+        assert!(matches!(Level::parse(""), Err(LevelError::Empty)));
+        assert!(matches!(Level::parse("name Nothing\n# only comments\n"), Err(LevelError::Empty)));
+    }
+}
@@ -1,4 +1,83 @@
 extern crate core;
 
+pub mod animation;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod app;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod asciicast;
+#[cfg(feature = "sound")]
+pub mod audio;
+#[cfg(all(feature = "serde", not(target_arch = "wasm32")))]
+pub mod autosave;
+pub mod backdrop;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod backend;
+pub mod biome;
+pub mod character;
+pub mod color;
+pub mod debug;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod diff;
+pub mod difficulty;
+pub mod editor;
+pub mod explorer;
+#[cfg(feature = "graphics")]
+pub mod graphics;
+#[cfg(feature = "gif")]
+pub mod gif;
+pub mod grid;
+pub mod hash;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod highscores;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod input;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod keymap;
+pub mod level;
+pub mod locale;
+#[cfg(feature = "logging")]
+pub mod logging;
 pub mod map;
-pub mod stripe;
\ No newline at end of file
+pub mod menu;
+pub mod mode;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod multiplexer;
+pub mod narration;
+pub mod net;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod profile;
+pub mod rng;
+pub mod sound;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod stats;
+#[cfg(feature = "scripting")]
+pub mod scripting;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod screenshot;
+pub mod seed_code;
+#[cfg(feature = "serde")]
+// Only autosaves use its binary format, which wasm32 builds leave out.
+#[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+mod serialization;
+#[cfg(feature = "ssh")]
+pub mod serve;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod shop;
+pub mod solver;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod spectate;
+pub mod stripe;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod submission;
+pub mod theme;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod title;
+pub mod tutorial;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod unlocks;
+pub mod versus;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+pub mod weather;
+#[cfg(feature = "ratatui")]
+pub mod widget;
\ No newline at end of file
@@ -0,0 +1,382 @@
+//! Translations of the texts shown in menus and games.
+//!
+//! Every [Text] has a translation in each [Language]. Texts containing values mark them with
+//! `{}`, which [Language::format] fills in order.
+use std::fmt::Display;
+use crate::map::DeathCause;
+use crate::stripe::Block;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum Language {
+    #[default]
+    English,
+    German,
+}
+
+impl Language {
+    pub const ALL: [Language; 2] = [Language::English, Language::German];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Language::English => "en",
+            Language::German => "de",
+        }
+    }
+
+    /// Parses a language code like `de` or a locale like `de_AT.UTF-8`.
+    pub fn from_name(name: &str) -> Option<Language> {
+        let code = name.split(['_', '-', '.']).next().unwrap_or_default().to_lowercase();
+        Self::ALL.into_iter().find(|language| language.name() == code)
+    }
+
+    /// The language of the locale set in `LC_ALL`, `LC_MESSAGES` or `LANG`, English if it has
+    /// no translation.
+    pub fn detect() -> Language {
+        ["LC_ALL", "LC_MESSAGES", "LANG"].iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find(|locale| !locale.is_empty())
+            .and_then(|locale| Self::from_name(&locale))
+            .unwrap_or_default()
+    }
+
+    pub fn text(self, text: Text) -> &'static str {
+        match self {
+            Language::English => english(text),
+            Language::German => german(text),
+        }
+    }
+
+    /// The translation of `text` with its `{}` replaced by `values`.
+    pub fn format(self, text: Text, values: &[&dyn Display]) -> String {
+        let mut parts = self.text(text).split("{}");
+        let mut result = parts.next().unwrap_or_default().to_string();
+        for (idx, part) in parts.enumerate() {
+            if let Some(value) = values.get(idx) {
+                result.push_str(&value.to_string());
+            }
+            result.push_str(part);
+        }
+        result
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Text {
+    MenuHint,
+    Hardcore,
+    Adaptive,
+    Coop,
+    Versus,
+    Level,
+    SeedExplorer,
+    LevelEditor,
+    KeyboardLayout,
+    Character,
+    Quit,
+    ChooseLayout,
+    ChooseCharacter,
+    Profile,
+    ChooseProfile,
+    DefaultProfile,
+    NewProfile,
+    PlayingAs,
+    ProfileName,
+    ProfileNameHint,
+    EditorUnavailable,
+    ProfilesUnavailable,
+    ShopUnavailable,
+    Locked,
+    RequireRows,
+    RequireGames,
+    RequireScore,
+    RequireNearMisses,
+    UnlockableCharacter,
+    UnlockableTheme,
+    UnlockableMode,
+    UnlockableDifficulty,
+    UnlockableWeather,
+    Unlocked,
+    UnlockFirst,
+    Shop,
+    ShopTitle,
+    SkinItem,
+    TrailItem,
+    ItemPrice,
+    ItemOwned,
+    ItemWorn,
+    RequireCoins,
+    Bought,
+    Earned,
+    TooNarrow,
+    InterruptedRun,
+    Resume,
+    Discard,
+    Endless,
+    TimeAttack,
+    Daily,
+    Zen,
+    Practice,
+    Casual,
+    Night,
+    Tutorial,
+    AutoScroll,
+    Lives,
+    Score,
+    Best,
+    TimeLeft,
+    Combo,
+    Opponent,
+    OpponentOut,
+    Paused,
+    Move,
+    Player,
+    ArrowKeys,
+    Pause,
+    Continue,
+    Undo,
+    Help,
+    HelpKeys,
+    HelpColors,
+    HelpMode,
+    HelpClose,
+    Legend(Block),
+    RewindPrompt,
+    ContinuePrompt,
+    HighScores,
+    TimesUp,
+    LevelComplete,
+    YouDied,
+    Board,
+    SeedCode,
+    NoSeedCode,
+    GifPrompt,
+    PlayerProgress,
+    PlayerWins,
+    Draw,
+    Alive,
+    Cause(DeathCause),
+}
+
+fn english(text: Text) -> &'static str {
+    match text {
+        Text::MenuHint => "↑/↓ select, Enter confirm, q quit",
+        Text::Hardcore => "Hardcore",
+        Text::Adaptive => "Adaptive",
+        Text::Coop => "Co-op",
+        Text::Versus => "Versus",
+        Text::Level => "Level: {}",
+        Text::SeedExplorer => "Seed explorer",
+        Text::LevelEditor => "Level editor ({})",
+        Text::KeyboardLayout => "Keyboard layout",
+        Text::Character => "Character",
+        Text::Quit => "Quit",
+        Text::ChooseLayout => "Choose your keyboard layout",
+        Text::ChooseCharacter => "Choose your character",
+        Text::Profile => "Profile: {}",
+        Text::ChooseProfile => "Choose a profile",
+        Text::DefaultProfile => "Default",
+        Text::NewProfile => "New profile",
+        Text::PlayingAs => "Playing as {}",
+        Text::ProfileName => "Name of the new profile (letters, digits, - and _):",
+        Text::ProfileNameHint => "Enter create, Esc cancel",
+        Text::EditorUnavailable => "The level editor is not available on servers",
+        Text::ProfilesUnavailable => "Profiles are not available on servers",
+        Text::ShopUnavailable => "The shop is not available on servers",
+        Text::Locked => "locked: {}",
+        Text::RequireRows => "cross {} more rows",
+        Text::RequireGames => "play {} more runs",
+        Text::RequireScore => "score {} in one run",
+        Text::RequireNearMisses => "dodge {} more cars or trains closely",
+        Text::UnlockableCharacter => "the {}",
+        Text::UnlockableTheme => "the {} theme",
+        Text::UnlockableMode => "the {} mode",
+        Text::UnlockableDifficulty => "the {} difficulty",
+        Text::UnlockableWeather => "the {} weather",
+        Text::Unlocked => "Unlocked {}",
+        Text::UnlockFirst => "Unlock {} first: {}",
+        Text::Shop => "Shop ({} coins)",
+        Text::ShopTitle => "Shop: {} coins to spend, Enter buys, puts on or takes off",
+        Text::SkinItem => "{} skin",
+        Text::TrailItem => "{} trail",
+        Text::ItemPrice => "{} for {} coins",
+        Text::ItemOwned => "{} (owned)",
+        Text::ItemWorn => "{} (worn)",
+        Text::RequireCoins => "collect {} more coins",
+        Text::Bought => "Bought: {}",
+        Text::Earned => "Earned {} coins",
+        Text::TooNarrow => "Make the terminal at least {} columns wide",
+        Text::InterruptedRun => "Your last run was interrupted",
+        Text::Resume => "Resume {} at {} points",
+        Text::Discard => "Discard it",
+        Text::Endless => "Endless",
+        Text::TimeAttack => "Time attack ({}s)",
+        Text::Daily => "Daily {}",
+        Text::Zen => "Zen",
+        Text::Practice => "Practice",
+        Text::Casual => "Casual",
+        Text::Night => "Night",
+        Text::Tutorial => "Tutorial",
+        Text::AutoScroll => "auto-scroll",
+        Text::Lives => "lives",
+        Text::Score => "Score: {}",
+        Text::Best => "Best: {}",
+        Text::TimeLeft => "Time left: {}s",
+        Text::Combo => "Combo x{}",
+        Text::Opponent => "Opponent: {}",
+        Text::OpponentOut => "Opponent: {} (out)",
+        Text::Paused => "Paused",
+        Text::Move => "move",
+        Text::Player => "player {}",
+        Text::ArrowKeys => "arrow keys",
+        Text::Pause => "pause",
+        Text::Continue => "continue",
+        Text::Undo => "undo",
+        Text::Help => "help",
+        Text::HelpKeys => "Keys",
+        Text::HelpColors => "Colors",
+        Text::HelpMode => "Mode: {}",
+        Text::HelpClose => "Press any key to close",
+        Text::Legend(Block::BrightGreen) => "grass, safe",
+        Text::Legend(Block::Green) => "trees and bushes, block the way",
+        Text::Legend(Block::White) => "zebra crossings, safe",
+        Text::Legend(Block::Gray) => "road, rocks",
+        Text::Legend(Block::DarkYellow) => "flashing railroad: a train is coming",
+        Text::Legend(Block::Red) => "cars, trains and crocodile heads",
+        Text::Legend(Block::Black) => "gaps between train wagons",
+        Text::Legend(Block::Magenta) => "trucks",
+        Text::Legend(Block::Blue) => "water",
+        Text::Legend(Block::Brown) => "turtles, safe until they dive",
+        Text::RewindPrompt => "Press z to rewind, any other key to continue",
+        Text::ContinuePrompt => "Press any key to continue",
+        Text::HighScores => "High scores ({}):",
+        Text::TimesUp => "Time's up!",
+        Text::LevelComplete => "Level complete!",
+        Text::YouDied => "You died!",
+        Text::Board => "Board: {}",
+        Text::SeedCode => "Seed code: {}",
+        Text::NoSeedCode => "No seed code, the board adapted to how you play",
+        Text::GifPrompt => "Press g to save a GIF of the run",
+        Text::PlayerProgress => "Player {}: {}/{}",
+        Text::PlayerWins => "Player {} wins!",
+        Text::Draw => "Draw!",
+        Text::Alive => "Alive",
+        Text::Cause(DeathCause::HitByCar) => "Hit by a car",
+        Text::Cause(DeathCause::HitByTrain) => "Hit by a train",
+        Text::Cause(DeathCause::RanIntoTree) => "Ran into a tree",
+        Text::Cause(DeathCause::Drowned) => "Drowned",
+        Text::Cause(DeathCause::FellBehind) => "Fell behind",
+        Text::Cause(DeathCause::Scripted) => "Hit a scripted obstacle",
+    }
+}
+
+fn german(text: Text) -> &'static str {
+    match text {
+        Text::MenuHint => "↑/↓ auswählen, Enter bestätigen, q beenden",
+        Text::Hardcore => "Hardcore",
+        Text::Adaptive => "Adaptiv",
+        Text::Coop => "Koop",
+        Text::Versus => "Duell",
+        Text::Level => "Level: {}",
+        Text::SeedExplorer => "Seed-Explorer",
+        Text::LevelEditor => "Level-Editor ({})",
+        Text::KeyboardLayout => "Tastaturbelegung",
+        Text::Character => "Figur",
+        Text::Quit => "Beenden",
+        Text::ChooseLayout => "Wähle deine Tastaturbelegung",
+        Text::ChooseCharacter => "Wähle deine Figur",
+        Text::Profile => "Profil: {}",
+        Text::ChooseProfile => "Wähle ein Profil",
+        Text::DefaultProfile => "Standard",
+        Text::NewProfile => "Neues Profil",
+        Text::PlayingAs => "Du spielst als {}",
+        Text::ProfileName => "Name des neuen Profils (Buchstaben, Ziffern, - und _):",
+        Text::ProfileNameHint => "Enter erstellen, Esc abbrechen",
+        Text::EditorUnavailable => "Der Level-Editor ist auf Servern nicht verfügbar",
+        Text::ProfilesUnavailable => "Profile sind auf Servern nicht verfügbar",
+        Text::ShopUnavailable => "Der Laden ist auf Servern nicht verfügbar",
+        Text::Locked => "gesperrt: {}",
+        Text::RequireRows => "überquere {} weitere Reihen",
+        Text::RequireGames => "spiele {} weitere Runden",
+        Text::RequireScore => "erreiche {} Punkte in einer Runde",
+        Text::RequireNearMisses => "weiche {} weiteren Autos oder Zügen knapp aus",
+        Text::UnlockableCharacter => "Figur {}",
+        Text::UnlockableTheme => "Farbschema {}",
+        Text::UnlockableMode => "Modus {}",
+        Text::UnlockableDifficulty => "Schwierigkeit {}",
+        Text::UnlockableWeather => "Wetter {}",
+        Text::Unlocked => "Freigeschaltet: {}",
+        Text::UnlockFirst => "Schalte zuerst {} frei: {}",
+        Text::Shop => "Laden ({} Münzen)",
+        Text::ShopTitle => "Laden: {} Münzen übrig, Enter kauft, zieht an oder aus",
+        Text::SkinItem => "Aussehen {}",
+        Text::TrailItem => "Spur {}",
+        Text::ItemPrice => "{} für {} Münzen",
+        Text::ItemOwned => "{} (gekauft)",
+        Text::ItemWorn => "{} (angezogen)",
+        Text::RequireCoins => "sammle {} weitere Münzen",
+        Text::Bought => "Gekauft: {}",
+        Text::Earned => "{} Münzen verdient",
+        Text::TooNarrow => "Mach das Terminal mindestens {} Spalten breit",
+        Text::InterruptedRun => "Dein letzter Lauf wurde unterbrochen",
+        Text::Resume => "{} bei {} Punkten fortsetzen",
+        Text::Discard => "Verwerfen",
+        Text::Endless => "Endlos",
+        Text::TimeAttack => "Zeitrennen ({}s)",
+        Text::Daily => "Tagesrunde {}",
+        Text::Zen => "Zen",
+        Text::Practice => "Training",
+        Text::Casual => "Entspannt",
+        Text::Night => "Nacht",
+        Text::Tutorial => "Tutorial",
+        Text::AutoScroll => "Auto-Scroll",
+        Text::Lives => "Leben",
+        Text::Score => "Punkte: {}",
+        Text::Best => "Rekord: {}",
+        Text::TimeLeft => "Verbleibend: {}s",
+        Text::Combo => "Kombo x{}",
+        Text::Opponent => "Gegner: {}",
+        Text::OpponentOut => "Gegner: {} (raus)",
+        Text::Paused => "Pause",
+        Text::Move => "bewegen",
+        Text::Player => "Spieler {}",
+        Text::ArrowKeys => "Pfeiltasten",
+        Text::Pause => "Pause",
+        Text::Continue => "weiter",
+        Text::Undo => "rückgängig",
+        Text::Help => "Hilfe",
+        Text::HelpKeys => "Tasten",
+        Text::HelpColors => "Farben",
+        Text::HelpMode => "Modus: {}",
+        Text::HelpClose => "Drücke eine beliebige Taste zum Schließen",
+        Text::Legend(Block::BrightGreen) => "Gras, sicher",
+        Text::Legend(Block::Green) => "Bäume und Büsche, versperren den Weg",
+        Text::Legend(Block::White) => "Zebrastreifen, sicher",
+        Text::Legend(Block::Gray) => "Straße, Felsen",
+        Text::Legend(Block::DarkYellow) => "blinkende Gleise: ein Zug kommt",
+        Text::Legend(Block::Red) => "Autos, Züge und Krokodilköpfe",
+        Text::Legend(Block::Black) => "Lücken zwischen Waggons",
+        Text::Legend(Block::Magenta) => "Lastwagen",
+        Text::Legend(Block::Blue) => "Wasser",
+        Text::Legend(Block::Brown) => "Schildkröten, sicher bis sie tauchen",
+        Text::RewindPrompt => "Drücke z zum Zurückspulen, eine andere Taste zum Fortfahren",
+        Text::ContinuePrompt => "Drücke eine beliebige Taste",
+        Text::HighScores => "Bestenliste ({}):",
+        Text::TimesUp => "Zeit abgelaufen!",
+        Text::LevelComplete => "Level geschafft!",
+        Text::YouDied => "Du bist gestorben!",
+        Text::Board => "Karte: {}",
+        Text::SeedCode => "Seed-Code: {}",
+        Text::NoSeedCode => "Kein Seed-Code, die Karte hat sich an deine Spielweise angepasst",
+        Text::GifPrompt => "Drücke g, um ein GIF der Runde zu speichern",
+        Text::PlayerProgress => "Spieler {}: {}/{}",
+        Text::PlayerWins => "Spieler {} gewinnt!",
+        Text::Draw => "Unentschieden!",
+        Text::Alive => "Lebt",
+        Text::Cause(DeathCause::HitByCar) => "Von einem Auto erfasst",
+        Text::Cause(DeathCause::HitByTrain) => "Von einem Zug erfasst",
+        Text::Cause(DeathCause::RanIntoTree) => "Gegen einen Baum gelaufen",
+        Text::Cause(DeathCause::Drowned) => "Ertrunken",
+        Text::Cause(DeathCause::FellBehind) => "Zurückgefallen",
+        Text::Cause(DeathCause::Scripted) => "An einem Skript-Hindernis gescheitert",
+    }
+}
@@ -0,0 +1,154 @@
+//! A log of what the app does and dumps of the last moments of runs that ended badly.
+//!
+//! [init] writes the `tracing` events of this crate to [LOG_FILE] inside [data_dir], at the level
+//! named by [LEVEL_ENV] (`info` by default). Screen changes and game events are logged at `info`,
+//! keys at `debug` and the state of every tick at `trace`.
+//!
+//! Each thread records the run it plays: the state of the rows around the players for the last
+//! [DUMP_TICKS] ticks and every key since the run started. When a run ends in death or the game
+//! panics, that is written to [DUMP_DIR] as an input script with the states as comments, so the
+//! run can be played again with `--seed-code <code> --input-script <dump>`.
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use crossterm::event::KeyCode;
+use tracing::level_filters::LevelFilter;
+use tracing_subscriber::filter::Targets;
+use tracing_subscriber::fmt::time::uptime;
+use tracing_subscriber::layer::SubscriberExt;
+use crate::input::{check_line, key_name, CHECK_TICKS};
+use crate::map::MapState;
+use crate::profile::data_dir;
+
+pub const LOG_FILE: &str = "crossy_terminal.log";
+
+pub const DUMP_DIR: &str = "dumps";
+
+/// Environment variable picking the most detailed level that is logged, e.g. `debug`.
+pub const LEVEL_ENV: &str = "CROSSY_LOG";
+
+/// Ticks of state a dump holds, five seconds at the default speed.
+pub const DUMP_TICKS: usize = 100;
+
+/// Rows around each player whose state is recorded, from below to above them.
+const RECORDED_ROWS: std::ops::RangeInclusive<isize> = -1..=2;
+
+/// Starts logging to [LOG_FILE], replacing the log of the last start, and dumps the current run
+/// of a thread that panics.
+pub fn init() -> std::io::Result<()> {
+    std::fs::create_dir_all(data_dir())?;
+    let file = File::create(data_dir().join(LOG_FILE))?;
+    let level = std::env::var(LEVEL_ENV).ok().and_then(|level| level.parse().ok()).unwrap_or(LevelFilter::INFO);
+    let subscriber = tracing_subscriber::fmt()
+        .with_writer(Mutex::new(file))
+        .with_timer(uptime())
+        .finish()
+        // Only this crate logs, the dependencies would drown it out.
+        .with(Targets::new().with_target(env!("CARGO_CRATE_NAME"), level));
+    tracing::subscriber::set_global_default(subscriber).map_err(std::io::Error::other)?;
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        tracing::error!("{info}");
+        if let Err(err) = dump("panic") {
+            tracing::error!("Writing the dump failed: {err}");
+        }
+        previous(info);
+    }));
+    Ok(())
+}
+
+/// What a thread recorded of the run it plays.
+#[derive(Default)]
+struct Recording {
+    /// Comments describing the run, e.g. how to play it again.
+    header: Vec<String>,
+    /// Ticks since the run started.
+    ticks: u64,
+    /// The tick and state of the last [DUMP_TICKS] ticks.
+    states: VecDeque<(u64, String)>,
+    /// The keys and state hashes since the run started, as an input script.
+    script: String,
+    /// The tick of the last line in [Recording::script].
+    last_line: u64,
+}
+
+thread_local! {
+    static RECORDING: RefCell<Recording> = RefCell::default();
+}
+
+/// Starts recording a new run, described by the `header` lines.
+pub(crate) fn start_run(header: Vec<String>) {
+    tracing::info!("Run started: {}", header.join(", "));
+    RECORDING.set(Recording { header, ..Recording::default() });
+}
+
+/// Records a tick of the run, with the state of `map` after it unless the run is paused.
+pub(crate) fn record_tick(map: Option<&MapState>) {
+    RECORDING.with_borrow_mut(|recording| {
+        recording.ticks += 1;
+        let Some(map) = map else { return };
+        if recording.ticks.is_multiple_of(CHECK_TICKS) {
+            recording.script.push_str(&check_line(recording.ticks - recording.last_line, map.state_hash()));
+            recording.last_line = recording.ticks;
+        }
+        let state = describe(map);
+        tracing::trace!("tick {}: {state}", recording.ticks);
+        recording.states.push_back((recording.ticks, state));
+        if recording.states.len() > DUMP_TICKS {
+            recording.states.pop_front();
+        }
+    });
+}
+
+pub(crate) fn record_key(key: KeyCode) {
+    tracing::debug!("key {key:?}");
+    let Some(name) = key_name(key) else { return };
+    RECORDING.with_borrow_mut(|recording| {
+        recording.script.push_str(&format!("{} {name}\n", recording.ticks - recording.last_line));
+        recording.last_line = recording.ticks;
+    });
+}
+
+/// Writes what was recorded of the current run to [DUMP_DIR] and returns the path of the dump.
+pub fn dump(reason: &str) -> std::io::Result<PathBuf> {
+    let millis = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    let dir = data_dir().join(DUMP_DIR);
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("dump-{millis}.txt"));
+    let contents = RECORDING.with_borrow(|recording| {
+        let mut contents = format!("# Dump after {reason} at tick {}\n", recording.ticks);
+        for line in &recording.header {
+            contents.push_str(&format!("# {line}\n"));
+        }
+        for (tick, state) in &recording.states {
+            contents.push_str(&format!("# {tick:>6} {state}\n"));
+        }
+        contents.push_str(&recording.script);
+        contents
+    });
+    std::fs::write(&path, contents)?;
+    tracing::info!("Dumped the run to {}", path.display());
+    Ok(path)
+}
+
+/// The score, the players and the rows around them on a single line.
+fn describe(map: &MapState) -> String {
+    let mut parts = vec![format!("score {}", map.score())];
+    let visible = map.visible_rows();
+    for player in 0..map.player_count() {
+        let (x, y) = (map.player_x_of(player), map.player_y_of(player));
+        parts.push(format!("player {} at {x},{y}", player + 1));
+        for offset in RECORDED_ROWS {
+            let Some(row_y) = y.checked_add_signed(offset as i64).filter(|row_y| visible.contains(row_y)) else { continue };
+            if let Some(row) = map.rows().nth((row_y - visible.start) as usize) {
+                parts.push(format!("{row_y}: {}", row.debug_state()));
+            }
+        }
+    }
+    parts.join(" | ")
+}
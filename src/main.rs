@@ -1,45 +1,480 @@
-use crossterm::event::Event;
-use crossterm::{cursor, event, terminal, ExecutableCommand, QueueableCommand};
-use crossy_terminal::map::MapState;
-use std::io::{stdout, Write};
-use std::time::Duration;
+// The game needs a terminal, wasm32 builds only provide the engine in the library.
+#![cfg_attr(target_arch = "wasm32", no_main)]
+#![cfg(not(target_arch = "wasm32"))]
+use crossy_terminal::app::{self, App};
+use crossy_terminal::asciicast;
+#[cfg(feature = "sound")]
+use crossy_terminal::audio::Audio;
+use crossy_terminal::backend::{CrosstermBackend, TerminalBackend};
+use crossy_terminal::debug::Timings;
+use crossy_terminal::difficulty::Difficulty;
+use crossy_terminal::editor::LevelEditor;
+use crossy_terminal::input::{Desync, InputSource, ScriptRecorder, ScriptedInput};
+use crossy_terminal::keymap::KeyboardLayout;
+use crossy_terminal::locale::{Language, Text};
+use crossy_terminal::map::TICKS_PER_SECOND;
+use crossy_terminal::mode::GameMode;
+use crossy_terminal::net::{Connection, DEFAULT_PORT};
+use crossy_terminal::profile::{self, Profile, DEFAULT_FPS, FPS_RANGE, MAX_NAME_LEN, SPEED_RANGE};
+use crossy_terminal::seed_code::SeedCode;
+#[cfg(feature = "ssh")]
+use crossy_terminal::serve::{serve, SERVE_PORT};
+use crossy_terminal::sound::Sound;
+use crossy_terminal::spectate::{self, watch, Broadcast, SPECTATE_PORT};
+use crossy_terminal::stats::Stats;
+use crossy_terminal::submission::{self, Submission};
+use crossy_terminal::theme::{Glyphs, Theme};
+use crossy_terminal::unlocks::{Unlockable, Unlocks};
+use crossy_terminal::weather::Weather;
+use std::fs::File;
+use std::io::{stdout, BufWriter};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 fn main() {
-    let mut stdout = stdout();
-    let mut map = MapState::new();
-    stdout.execute(cursor::Hide).unwrap();
-    terminal::enable_raw_mode().unwrap();
-
-    while map.alive {
-        if event::poll(Duration::from_millis(100)).unwrap() {
-            match event::read() {
-                Ok(Event::Key(key)) => {
-                    if key.code == event::KeyCode::Char('q') {
-                        break;
-                    } else if key.code.is_up() {
-                        map.up();
-                    } else if key.code.is_right() {
-                        map.right();
-                    } else if key.code.is_down() {
-                        map.down();
-                    } else if key.code.is_left() {
-                        map.left();
-                    }
+    if let Some(name) = arg_value("--profile") {
+        if !profile::is_valid_name(&name) {
+            exit_with(format!("Profile names are up to {MAX_NAME_LEN} letters, digits, - and _"));
+        }
+        profile::select(Some(&name));
+    }
+
+    if std::env::args().any(|arg| arg == "--stats") {
+        println!("{}", Stats::load().to_json());
+        return;
+    }
+
+    if std::env::args().nth(1).as_deref() == Some("serve") {
+        #[cfg(feature = "ssh")]
+        {
+            let port = std::env::args().nth(2).map_or(Ok(SERVE_PORT), |port| port.parse()).unwrap_or_else(|err| exit_with(format!("Invalid port: {err}")));
+            #[cfg(feature = "logging")]
+            if let Err(err) = crossy_terminal::logging::init() {
+                eprintln!("Logging is disabled: {err}");
+            }
+            println!("Serving games over SSH on port {port}");
+            serve(port).unwrap_or_else(|err| exit_with(err));
+            return;
+        }
+        #[cfg(not(feature = "ssh"))]
+        exit_with("Serving games needs a build with the ssh feature");
+    }
+
+    if std::env::args().nth(1).as_deref() == Some("verify") {
+        let path = std::env::args().nth(2).unwrap_or_else(|| exit_with("Usage: crossy_terminal verify <submission>"));
+        let submission = Submission::load(Path::new(&path)).and_then(|submission| submission.verify().map(|()| submission));
+        match submission {
+            Ok(submission) => println!("Verified a score of {} on seed code {}", submission.score, submission.seed_code),
+            Err(err) => exit_with(err),
+        }
+        return;
+    }
+
+    #[cfg(feature = "logging")]
+    if let Err(err) = crossy_terminal::logging::init() {
+        eprintln!("Logging is disabled: {err}");
+    }
+
+    // Connect before entering raw mode, so the waiting is printed normally.
+    let race = if std::env::args().any(|arg| arg == "--host") {
+        let seed = rand::random();
+        println!("Waiting for a player to join on port {DEFAULT_PORT}...");
+        Some((Connection::host(DEFAULT_PORT, seed).unwrap_or_else(|err| exit_with(format!("Hosting the race failed: {err}"))), seed))
+    } else {
+        arg_value("--join").map(|address| Connection::join(&address).unwrap_or_else(|err| exit_with(format!("Joining the race at {address} failed: {err}"))))
+    };
+
+    if let Some(address) = arg_value("--watch") {
+        let stream = spectate::connect(&address).unwrap_or_else(|err| exit_with(format!("Connecting to {address} failed: {err}")));
+        CrosstermBackend::leave_on_panic();
+        let mut terminal = CrosstermBackend::new();
+        terminal.enter().unwrap_or_else(|err| exit_with(err));
+        let watched = watch(stream, &mut stdout());
+        terminal.leave().unwrap_or_else(|err| exit_with(err));
+        if let Err(err) = watched {
+            exit_with(format!("Watching {address} failed: {err}"));
+        }
+        return;
+    }
+
+    // Everything else the command line asks for is checked before entering raw mode too, so
+    // mistakes are printed normally.
+    #[cfg(feature = "scripting")]
+    if let Some(dir) = arg_value("--scripts")
+        && let Err(err) = crossy_terminal::scripting::load(Path::new(&dir)) {
+        exit_with(err);
+    }
+    let profile = configured_profile().unwrap_or_else(|err| exit_with(err));
+    let (mut app, connection) = match race {
+        Some((connection, seed)) => (App::race(profile, seed), Some(connection)),
+        None => (app(profile).unwrap_or_else(|err| exit_with(err)), None),
+    };
+    let script = arg_value("--input-script")
+        .map(|path| ScriptedInput::load(Path::new(&path)).unwrap_or_else(|err| exit_with(err)));
+    let mut terminal = CrosstermBackend::new();
+    if let Some(path) = arg_value("--export-asciicast") {
+        let mut script = script.unwrap_or_else(|| exit_with("--export-asciicast needs an --input-script to play"));
+        let (width, height) = terminal.size().unwrap_or_else(|err| exit_with(format!("Can't get the terminal size: {err}")));
+        let file = File::create(&path).unwrap_or_else(|err| exit_with(format!("Can't create {path}: {err}")));
+        asciicast::export(&mut app.without_records(), &mut script, width, height, &mut BufWriter::new(file))
+            .unwrap_or_else(|err| exit_with(format!("Exporting the asciicast failed: {err}")));
+        return;
+    }
+    let submission = arg_value("--submission").map(|path| {
+        if !submission::has_key() {
+            exit_with(submission::SubmissionError::NoKey);
+        }
+        let mode = selected_mode().unwrap_or_else(|err| exit_with(err))
+            .unwrap_or_else(|| exit_with("--submission needs a run started with --mode or --seed-code"));
+        let code = app.seed_code().unwrap_or_else(|| exit_with("--submission needs a run on a seed, not a level"));
+        (path, mode, code)
+    });
+    let broadcast = std::env::args().any(|arg| arg == "--broadcast")
+        .then(|| Broadcast::open(SPECTATE_PORT).unwrap_or_else(|err| exit_with(format!("Opening the broadcast failed: {err}"))));
+
+    CrosstermBackend::leave_on_panic();
+    terminal.enter().unwrap();
+    let mut recorder = (arg_value("--record").is_some() || submission.is_some()).then(|| {
+        let comment = match app.seed_code() {
+            Some(code) => format!("Play it again with --seed-code {code} --input-script <this file>"),
+            None => "Recorded from the main menu, which picks random seeds".to_string(),
+        };
+        ScriptRecorder::new(&comment)
+    });
+    let tick_interval = TICK_INTERVAL.div_f32(app.profile().speed.unwrap_or(1.0));
+    let frame_interval = Duration::from_secs(1) / app.profile().fps.unwrap_or(DEFAULT_FPS);
+    let signals = Signals::register();
+    let desync = run(&mut terminal, &mut app, script, recorder.as_mut(), connection, broadcast, tick_interval, frame_interval, &signals);
+
+    terminal.leave().unwrap();
+    if let Some(recorder) = &recorder {
+        if let Some(path) = arg_value("--record") {
+            recorder.save(Path::new(&path)).unwrap();
+        }
+        if let Some((path, mode, code)) = submission && let Some(score) = app.last_score() {
+            match Submission::new(mode, app.profile(), code, score, recorder.script().to_string()) {
+                Ok(submission) => submission.save(Path::new(&path)).unwrap(),
+                Err(err) => exit_with(err),
+            }
+        }
+    }
+    if let Some(desync) = desync {
+        eprintln!("{desync}");
+        std::process::exit(1);
+    }
+}
+
+/// The profile with the settings given on the command line applied, or what is wrong with them.
+fn configured_profile() -> Result<Profile, String> {
+    let mut profile = Profile::load();
+    if let Some(layout) = arg_value("--layout") {
+        profile.layout = KeyboardLayout::from_name(&layout);
+    }
+    if let Some(theme) = arg_value("--theme") {
+        let glyphs = profile.theme.glyphs;
+        let language = profile.theme.language;
+        profile.theme = Theme::from_name(&theme).ok_or_else(|| format!("Unknown theme {theme}"))?;
+        require_unlocked(Unlockable::Theme(profile.theme.name), language)?;
+        profile.theme.glyphs = glyphs;
+        if let Some(shapes) = profile.hazard_shapes {
+            profile.theme.hazard_shapes = shapes;
+        }
+        profile.theme.accessibility = profile.accessibility;
+        profile.theme.language = profile.language.unwrap_or_else(Language::detect);
+    }
+    if std::env::args().any(|arg| arg == "--ascii") {
+        profile.theme.glyphs = Glyphs::Ascii;
+    }
+    if std::env::args().any(|arg| arg == "--screen-reader") {
+        profile.screen_reader = true;
+    }
+    if let Some(speed) = arg_value("--speed") {
+        let speed = speed.parse().ok().filter(|speed| SPEED_RANGE.contains(speed)).ok_or("The speed must be between 0.5 and 2.0")?;
+        profile.speed = Some(speed).filter(|speed| *speed != 1.0);
+    }
+    if let Some(fps) = arg_value("--fps") {
+        let fps = fps.parse().ok().filter(|fps| FPS_RANGE.contains(fps)).ok_or("The fps must be between 1 and 240")?;
+        profile.fps = Some(fps).filter(|fps| *fps != DEFAULT_FPS);
+    }
+    if let Some(style) = arg_value("--style") {
+        profile.theme.glyphs = Glyphs::from_name(&style).ok_or_else(|| format!("Unknown style {style}"))?;
+    }
+    Ok(profile)
+}
+
+/// The app for the screen picked on the command line, or why the run it asks for can't start.
+fn app(profile: Profile) -> Result<App, String> {
+    Ok(if std::env::args().any(|arg| arg == "--explore") {
+        App::explore(profile)
+    } else if std::env::args().any(|arg| arg == "--versus") {
+        App::versus(profile)
+    } else if let Some(mode) = selected_mode()?
+        && let Some(code) = arg_value("--seed-code") {
+        let code = SeedCode::parse(&code).map_err(|err| err.to_string())?;
+        // Daily codes replay the day they were made on, not today.
+        let mode = match mode {
+            GameMode::Daily { .. } => GameMode::daily_from_seed(code.seed).ok_or("The seed code isn't one of a daily run")?,
+            mode => mode,
+        };
+        let map = mode.create_seeded_map(code.seed, code.difficulty).map_err(|err| err.to_string())?;
+        let map = code.apply(map, profile.character.partner());
+        App::play(profile, mode, map)
+    } else if let Some(mode) = selected_mode()? {
+        let difficulty = match arg_value("--difficulty") {
+            Some(name) => Difficulty::from_name(&name).ok_or_else(|| format!("Unknown difficulty {name}"))?,
+            None => Difficulty::NORMAL,
+        };
+        require_unlocked(Unlockable::Difficulty(difficulty.name), profile.theme.language)?;
+        let difficulty = Stats::load().adapt(difficulty);
+        let mut map = mode.create_map(difficulty).map_err(|err| err.to_string())?;
+        if let Some(seconds) = arg_value("--auto-scroll") {
+            let seconds: u64 = seconds.parse().map_err(|_| format!("Invalid auto-scroll interval {seconds}"))?;
+            map = map.with_auto_scroll(seconds * TICKS_PER_SECOND * difficulty.speed as u64);
+        }
+        if let Some(weather) = arg_value("--weather") {
+            let weather = Weather::from_name(&weather).ok_or_else(|| format!("Unknown weather {weather}"))?;
+            require_unlocked(Unlockable::Weather(weather), profile.theme.language)?;
+            map = map.with_weather(weather);
+        }
+        if let Some(lives) = arg_value("--lives") {
+            map = map.with_lives(lives.parse().map_err(|_| format!("Invalid number of lives {lives}"))?);
+        }
+        if std::env::args().any(|arg| arg == "--coop") {
+            map = map.with_second_player(profile.character.partner());
+        }
+        App::play(profile, mode, map)
+    } else if let Some(path) = arg_value("--edit") {
+        App::edit(profile, LevelEditor::open(Path::new(&path)).map_err(|err| err.to_string())?)
+    } else {
+        App::new(profile)
+    })
+}
+
+/// How long to wait for input before checking whether a tick is due.
+const INPUT_POLL_INTERVAL: Duration = Duration::from_millis(15);
+
+/// How often the timings in the debug overlay are measured.
+const TIMINGS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Time between two ticks of the simulation.
+const TICK_INTERVAL: Duration = Duration::from_millis(1000 / TICKS_PER_SECOND);
+
+/// Signals the game loop reacts to, each setting its flag until the loop handled it.
+#[derive(Default)]
+struct Signals {
+    /// SIGINT or SIGTERM, handled like Ctrl+C.
+    terminated: Arc<AtomicBool>,
+    /// SIGTSTP, handled like Ctrl+Z.
+    suspended: Arc<AtomicBool>,
+    /// SIGCONT, after which the terminal may need to be set up again.
+    continued: Arc<AtomicBool>,
+}
+
+impl Signals {
+    fn register() -> Signals {
+        let signals = Signals::default();
+        #[cfg(unix)]
+        {
+            use signal_hook::consts::{SIGCONT, SIGINT, SIGTERM, SIGTSTP};
+            for (signal, flag) in [(SIGINT, &signals.terminated), (SIGTERM, &signals.terminated), (SIGTSTP, &signals.suspended), (SIGCONT, &signals.continued)] {
+                signal_hook::flag::register(signal, flag.clone()).unwrap();
+            }
+        }
+        signals
+    }
+}
+
+/// Runs `app` until it quits or one of the `signals` ends it, and returns where the game first
+/// differed from the recording `script` was played from, if it did.
+///
+/// Frames are drawn at most every `frame_interval`, independent of the ticks. A frame that took
+/// longer to draw than that delays the next one by as long again, so a slow terminal never keeps
+/// the loop from reading keys and ticking.
+#[allow(clippy::too_many_arguments)]
+fn run(terminal: &mut dyn TerminalBackend, app: &mut App, mut script: Option<ScriptedInput>, mut recorder: Option<&mut ScriptRecorder>, mut connection: Option<Connection>, mut broadcast: Option<Broadcast>, tick_interval: Duration, frame_interval: Duration, signals: &Signals) -> Option<Desync> {
+    let (width, height) = terminal.size().unwrap();
+    app.handle_event(app::Event::Resize { width, height });
+    let sound_settings = app.profile().sound;
+    let mut audio = Audio::open(sound_settings);
+    let mut last_update = Instant::now();
+    // Time that passed but wasn't simulated yet.
+    let mut lag = Duration::ZERO;
+    let mut tick = 0;
+    let mut desync = None;
+    let mut next_frame = Instant::now();
+    // Ticks, frames and the time spent drawing them since the timings were last measured.
+    let mut measuring_since = Instant::now();
+    let (mut measured_ticks, mut measured_frames, mut measured_drawing) = (0, 0, Duration::ZERO);
+    while !app.should_quit() {
+        if signals.terminated.swap(false, Ordering::Relaxed) {
+            app.handle_event(app::Event::Interrupt);
+            continue;
+        }
+        // The time spent suspended isn't simulated.
+        if signals.suspended.swap(false, Ordering::Relaxed) {
+            suspend(terminal, app);
+            last_update = Instant::now();
+        }
+        if signals.continued.swap(false, Ordering::Relaxed) {
+            terminal.resume().unwrap();
+            let (width, height) = terminal.size().unwrap();
+            app.handle_event(app::Event::Resize { width, height });
+            last_update = Instant::now();
+        }
+        match terminal.next_event(tick, INPUT_POLL_INTERVAL).unwrap() {
+            Some(app::Event::Suspend) => {
+                suspend(terminal, app);
+                last_update = Instant::now();
+            }
+            Some(event) => {
+                if let Some(recorder) = &mut recorder {
+                    recorder.record(tick, &event);
                 }
-                _ => {}
+                app.handle_event(event);
+            }
+            None => {}
+        }
+        let now = Instant::now();
+        lag += now - last_update;
+        last_update = now;
+        while lag >= tick_interval {
+            // Scripted keys come in addition to the keyboard, so the player can always quit. They
+            // are pressed right before their tick, even when several ticks are caught up at once.
+            if let Some(script) = &mut script {
+                while let Some(event) = script.next_event(tick, Duration::ZERO).unwrap() {
+                    app.handle_event(event);
+                }
+            }
+            app.handle_event(app::Event::Tick);
+            lag -= tick_interval;
+            tick += 1;
+            measured_ticks += 1;
+            if let Some(recorder) = &mut recorder && let Some(hash) = app.state_hash() {
+                recorder.record_state(tick, hash);
+            }
+            if let Some(script) = &mut script && desync.is_none() {
+                desync = script.verify(tick, app.state_hash()).err();
+            }
+        }
+        if measuring_since.elapsed() >= TIMINGS_INTERVAL {
+            app.set_timings(Timings {
+                tick_rate: measured_ticks as f32 / measuring_since.elapsed().as_secs_f32(),
+                frame_time: measured_drawing.checked_div(measured_frames).unwrap_or_default(),
+            });
+            measuring_since = Instant::now();
+            (measured_ticks, measured_frames, measured_drawing) = (0, 0, Duration::ZERO);
+        }
+
+        if let Some(race) = &mut connection {
+            let exchanged = app.race_progress().map_or(Ok(()), |progress| race.send(progress))
+                .and_then(|_| race.receive());
+            match exchanged {
+                Ok(Some(progress)) => app.set_remote_progress(progress),
+                Ok(None) => {}
+                // Keep playing alone, the marker stays where the other player was last.
+                Err(_) => connection = None,
+            }
+        }
+
+        if let Some(text) = app.take_clipboard() {
+            terminal.set_clipboard(&text).unwrap();
+        }
+        for sound in app.take_sounds().into_iter().filter(|_| !sound_settings.muted) {
+            play(terminal, audio.as_ref(), sound);
+        }
+        if let Some(audio) = &mut audio {
+            audio.set_music(app.music());
+        }
+        if let Some(broadcast) = &mut broadcast {
+            broadcast.accept();
+        }
+        if Instant::now() >= next_frame && app.take_dirty() {
+            let started = Instant::now();
+            let frame = app.view();
+            terminal.draw(&frame).unwrap();
+            if let Some(broadcast) = &mut broadcast {
+                broadcast.send(&frame.grid);
             }
+            let drawing = started.elapsed();
+            measured_frames += 1;
+            measured_drawing += drawing;
+            next_frame = Instant::now() + frame_interval.saturating_sub(drawing).max(drawing);
         }
+    }
+    desync
+}
+
+/// Pauses the game and stops the process until it is continued, then draws everything again.
+fn suspend(terminal: &mut dyn TerminalBackend, app: &mut App) {
+    app.handle_event(app::Event::Suspend);
+    terminal.suspend().unwrap();
+    let (width, height) = terminal.size().unwrap();
+    app.handle_event(app::Event::Resize { width, height });
+}
+
+/// Stands in for the audio device in builds without the `sound` feature.
+#[cfg(not(feature = "sound"))]
+struct Audio;
+
+#[cfg(not(feature = "sound"))]
+impl Audio {
+    fn open(_settings: crossy_terminal::sound::SoundSettings) -> Option<Audio> {
+        None
+    }
+
+    fn play(&self, _sound: Sound) {}
 
-        // TODO: make this a stable cycle
-        map.update();
+    fn set_music(&mut self, _music: Option<(crossy_terminal::sound::Track, bool)>) {}
+}
+
+/// Plays `sound`, or rings the bell for trains if there is no audio.
+fn play(terminal: &mut dyn TerminalBackend, audio: Option<&Audio>, sound: Sound) {
+    match audio {
+        Some(audio) => audio.play(sound),
+        None if sound == Sound::TrainHorn => terminal.bell().unwrap(),
+        None => {}
+    }
+}
+
+/// The mode picked with `--mode` or `--level`, if any. Picking only other options plays endless.
+fn selected_mode() -> Result<Option<GameMode>, String> {
+    if let Some(level) = arg_value("--level") {
+        return Ok(Some(GameMode::Level(level)));
+    }
+    let other_options = ["--difficulty", "--auto-scroll", "--weather", "--lives", "--seed-code"].into_iter().any(|name| arg_value(name).is_some())
+        || std::env::args().any(|arg| arg == "--coop");
+    let Some(name) = arg_value("--mode").or_else(|| other_options.then(|| "endless".to_string())) else {
+        return Ok(None);
+    };
+    let mut mode = GameMode::from_name(&name).ok_or_else(|| format!("Unknown mode {name}"))?;
+    if let Some(name) = mode.name() {
+        require_unlocked(Unlockable::Mode(name), Profile::load().theme.language)?;
+    }
+    if let GameMode::TimeAttack { seconds } = &mut mode && let Some(time) = arg_value("--time") {
+        *seconds = time.parse().map_err(|_| format!("Invalid time {time}"))?;
+    }
+    Ok(Some(mode))
+}
 
-        stdout.queue(terminal::BeginSynchronizedUpdate).unwrap();
-        stdout.queue(cursor::MoveTo(0,0)).unwrap();
-        stdout.queue(terminal::Clear(terminal::ClearType::FromCursorDown)).unwrap();
-        stdout.write_all(format!("Use q to quit\n\r{}", map.render()).as_bytes()).unwrap();
-        stdout.queue(terminal::EndSynchronizedUpdate).unwrap();
-        stdout.flush().unwrap();
+/// Refuses `item` if it is still locked, see [crossy_terminal::unlocks], saying in `language`
+/// what is left to do.
+fn require_unlocked(item: Unlockable, language: Language) -> Result<(), String> {
+    let stats = Stats::load();
+    match Unlocks::load_with(&stats).requirement(item) {
+        Some(requirement) => Err(language.format(Text::UnlockFirst, &[&item.title(language), &requirement.describe(&stats, language)])),
+        None => Ok(()),
     }
+}
+
+/// Prints `err` and exits with an error, for mistakes on the command line.
+fn exit_with(err: impl std::fmt::Display) -> ! {
+    eprintln!("{err}");
+    std::process::exit(1);
+}
 
-    terminal::disable_raw_mode().unwrap();
+/// The argument following `name`, if present.
+fn arg_value(name: &str) -> Option<String> {
+    std::env::args().skip_while(|arg| arg != name).nth(1)
 }
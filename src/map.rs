@@ -1,105 +1,906 @@
 use std::collections::VecDeque;
 use std::ops::Div;
-use rayon::prelude::*;
-use crate::stripe::{Block, GreenStripe, Stripe, WallOfDeathPhase, STRIPE_LENGTH};
+use std::sync::Arc;
+use rand::Rng;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use crate::animation::Hop;
+use crate::biome::Biome;
+use crate::character::Character;
+use crate::color::Color;
+use crate::difficulty::Difficulty;
+use crate::grid::{Cell, Grid};
+use crate::hash::{hash_bytes, hash_seed, BoardHash};
+use crate::level::Level;
+use crate::locale::Text;
+use crate::rng::{write_audit_log, GameRng};
+use crate::solver::passable;
+use crate::stripe::{Block, GrassTile, GreenStripe, Stripe, WallOfDeathPhase, BUSH_STEPS, STRIPE_LENGTH, VISIBLE_TILES};
+use crate::theme::{Glyphs, PlayerMarker, Theme};
+use crate::weather::Weather;
 
 const ROW_COUNT: usize = 20;
 
+/// Rows generated ahead of the visible ones, so they can be checked to be passable.
+const LOOKAHEAD_ROWS: usize = 6;
+
+/// Times a row that can't be crossed is generated again.
+const GENERATION_ATTEMPTS: usize = 5;
+
+/// How many times [MapState::update] is called per second of real time.
+pub const TICKS_PER_SECOND: u64 = 10;
+
 const MAX_PLAYER_Y_INDEX: usize = 3;
 
+/// Steps the player is shown and hidden for with [PlayerMarker::Blinking].
+const BLINK_STEPS: u64 = 4;
+
+/// Rows around the player that are fully visible at night.
+const NIGHT_RADIUS: u64 = 3;
+
+/// Rows around the player that are fully visible at night while a lantern burns.
+const LANTERN_RADIUS: u64 = 7;
+
+/// Rows past the visible ones that are dimmed instead of hidden at night.
+const NIGHT_DIM_ROWS: u64 = 2;
+
+/// How long a lantern burns.
+const LANTERN_SECONDS: u64 = 10;
+
+/// Rows between two checkpoints on generated boards.
+pub const CHECKPOINT_ROWS: u64 = 50;
+
+/// Score lost for every life lost.
+const RESPAWN_PENALTY: u64 = 10;
+
+/// How long the player can't die after respawning.
+const INVULNERABLE_SECONDS: u64 = 2;
+
+/// How long the player blinks and passes through everything after a collision in harmless runs.
+const BUMP_TICKS: u64 = TICKS_PER_SECOND;
+
+/// Most ticks between two hops up that keep a combo going.
+const COMBO_TICKS: u64 = 4;
+
+/// Hops up in a row before every new row is worth [COMBO_BONUS] more.
+const COMBO_HOPS: u32 = 5;
+
+/// Points added to every new row reached during a combo.
+const COMBO_BONUS: u64 = 1;
+
+/// Ticks the board is shown after a crash before the game is over.
+pub const CRASH_TICKS: u32 = 15;
+
+/// Ticks the crashed player is shown in red and in their own color in turn.
+const CRASH_FLASH_TICKS: u32 = 2;
+
+/// The last ticks of [CRASH_TICKS] the board fades out in.
+const CRASH_FADE_TICKS: u32 = 4;
+
+/// The direction of the players last hop.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Facing {
+    #[default]
+    Up,
+    Right,
+    Down,
+    Left,
+}
+
+/// Effects on the player that run out after a while.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Effect {
+    /// A picked up lantern lights up more rows at night.
+    Lantern,
+    /// Hopped into a bush and can't move.
+    Stuck,
+    /// Just respawned and can't die.
+    Invulnerable,
+}
+
+impl Effect {
+    pub fn name(self) -> &'static str {
+        match self {
+            Effect::Lantern => "lantern",
+            Effect::Stuck => "stuck",
+            Effect::Invulnerable => "invulnerable",
+        }
+    }
+
+    /// Shown next to the remaining time in the status bar.
+    pub fn icon(self, glyphs: Glyphs) -> char {
+        match (self, glyphs) {
+            (Effect::Lantern, Glyphs::Ascii) => '!',
+            (Effect::Lantern, _) => '☼',
+            (Effect::Stuck, Glyphs::Ascii) => '&',
+            (Effect::Stuck, _) => '♣',
+            (Effect::Invulnerable, Glyphs::Ascii) => '+',
+            (Effect::Invulnerable, _) => '✚',
+        }
+    }
+}
+
+/// Everything that differs between the players sharing a board.
+#[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+struct Player {
+    /// The players position as an x coordinate in [0...14], 7 is the center.
+    x: u8,
+    /// The amount of rows the player is below `state[MAX_PLAYER_Y_INDEX]`, the highest row they
+    /// can stand on.
+    down: u8,
+    character: Character,
+    facing: Facing,
+    /// Steps until the player can leave the bush they hopped into.
+    stuck: u64,
+    /// Ticks until the respawned player can die again.
+    invulnerable_ticks: u64,
+    /// Whether a car or train is right next to the player, see [GameEvent::NearMiss].
+    near_miss: bool,
+    /// The tick of the last hop up, see [MapState::combo].
+    last_hop_up: Option<u64>,
+    /// Hops up in a row, each at most [COMBO_TICKS] after the one before.
+    combo: u32,
+    alive: bool,
+}
+
+impl Player {
+    fn new(character: Character) -> Player {
+        Player {
+            x: STRIPE_LENGTH.div(2) as u8,
+            down: 0,
+            character,
+            facing: Facing::default(),
+            stuck: 0,
+            invulnerable_ticks: 0,
+            near_miss: false,
+            last_hop_up: None,
+            combo: 0,
+            alive: true,
+        }
+    }
+}
+
+/// Callbacks of library users, see [MapState::on_score_change], [MapState::on_death] and
+/// [MapState::on_row_generated]. Clones of a [MapState] share them.
+#[derive(Clone, Default)]
+struct Observers {
+    score_change: Vec<Arc<dyn Fn(u64) + Send + Sync>>,
+    death: Vec<Arc<dyn Fn(DeathCause) + Send + Sync>>,
+    row_generated: Vec<Arc<dyn Fn(u64, Stripe) + Send + Sync>>,
+}
+
+/// Something that happened in a run, collected until [MapState::take_events].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum GameEvent {
+    /// A player reached a row no player stood on before, which was worth `points`.
+    RowCrossed { player: usize, y: u64, score: u64, points: u64 },
+    /// A car or train passed right next to a player.
+    NearMiss { player: usize },
+    /// A railroad right around a living player started warning about a train.
+    TrainWarning,
+    /// A player died, which only ends the run if they had no lives left.
+    Death { player: usize, cause: DeathCause },
+    /// A player ran into something in a harmless run, which only makes them blink.
+    Bump { player: usize, cause: DeathCause },
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum DeathCause {
+    HitByCar,
+    HitByTrain,
+    RanIntoTree,
+    /// Fell into a river, onto a diving turtle or a crocodiles head, or was carried off the board.
+    Drowned,
+    /// Standing still until the wall of death caught up or walking off the bottom.
+    FellBehind,
+    /// Collided with a stripe defined by a script.
+    Scripted,
+}
+
+impl DeathCause {
+    pub const ALL: [DeathCause; 6] = [
+        DeathCause::HitByCar,
+        DeathCause::HitByTrain,
+        DeathCause::RanIntoTree,
+        DeathCause::Drowned,
+        DeathCause::FellBehind,
+        DeathCause::Scripted,
+    ];
+
+    fn of(stripe: &Stripe) -> Self {
+        match stripe {
+            Stripe::Road(_) | Stripe::TrafficRoad(_) => DeathCause::HitByCar,
+            Stripe::Rail(_) => DeathCause::HitByTrain,
+            Stripe::Green(_) => DeathCause::RanIntoTree,
+            Stripe::River(_) => DeathCause::Drowned,
+            _ => DeathCause::Scripted,
+        }
+    }
+
+    /// Stable identifier, e.g. for the stats file.
+    pub fn id(self) -> &'static str {
+        match self {
+            DeathCause::HitByCar => "hit_by_car",
+            DeathCause::HitByTrain => "hit_by_train",
+            DeathCause::RanIntoTree => "ran_into_tree",
+            DeathCause::Drowned => "drowned",
+            DeathCause::FellBehind => "fell_behind",
+            DeathCause::Scripted => "scripted",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|cause| cause.id() == id)
+    }
+}
+
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct MapState {
     /// Queue of [ROW_COUNT] rows.
     state: VecDeque<Stripe>,
-    /// The players position as an x coordinate in [0...6].
-    ///
-    /// 3 is the center. The players y coordinate is always at `state[MAX_PLAYER_Y_INDEX]` or below.
-    player_x: u8,
+    /// The players sharing the board, only the first one in single player runs.
+    players: Vec<Player>,
     /// The y (score) value of the lowest visible row.
     bottom_y: u64,
-    /// The amount of steps downward (y-direction) the player currently maintains.
-    player_down: u8,
+    /// The y value of the highest row a player reached.
+    highest_y: u64,
     score: u64,
     /// A wall of death moves upwards to discourage standing still.
     wall_of_death: u64,
+    /// Whether the wall of death advances on its own.
+    wall_of_death_moves: bool,
+    /// Whether collisions only make the player blink instead of killing them.
+    harmless: bool,
+    /// Steps between the bottom row scrolling away on its own, [None] to only scroll when the
+    /// player hops forward.
+    auto_scroll: Option<u64>,
+    /// Steps until the next automatic scroll.
+    scroll_timer: u64,
+    difficulty: Difficulty,
+    /// The biome new rows are generated in.
+    biome: Biome,
+    weather: Weather,
+    /// Whether only the rows around the player are visible.
+    night: bool,
+    /// Positions of the lanterns that weren't picked up yet as `(y, x)`.
+    lanterns: Vec<(u64, u8)>,
+    /// Ticks until the picked up lantern burns out.
+    lantern_ticks: u64,
+    /// Lives left including the current one, shared by all players, [None] if players are out
+    /// with their first death.
+    lives: Option<u32>,
+    /// The y value of the last checkpoint the player reached.
+    checkpoint: Option<u64>,
     wall_of_death_phase: WallOfDeathPhase,
     tick: u64,
     /// False until the first key is pressed
     game_started: bool,
+    /// False once all players died.
     pub alive: bool,
+    /// Why the player died, [None] while alive.
+    death_cause: Option<DeathCause>,
+    /// True once the run ended successfully by reaching the finish line of a level or running out
+    /// of time in a timed run.
+    pub finished: bool,
+    /// Ticks until a timed run ends, counting from the first key press.
+    ticks_left: Option<u64>,
+    /// The rows of a level that are not visible yet, or [None] to generate random rows.
+    level_rows: Option<VecDeque<Stripe>>,
+    /// The next [LOOKAHEAD_ROWS] generated rows above the visible ones, as they were generated.
+    lookahead: VecDeque<Stripe>,
+    /// The seed both rngs are derived from.
+    seed: u64,
+    /// Rng used exclusively for generating new rows.
+    ///
+    /// Keeping it separate from [tick_rng] makes the sequence of rows depend on the seed only.
+    row_rng: GameRng,
+    /// Rng used for everything that happens while the stripes update.
+    tick_rng: GameRng,
+    /// Whether anything visible changed since the last [MapState::take_dirty]. Loaded maps still
+    /// have to be drawn.
+    #[cfg_attr(feature = "serde", serde(skip, default = "loaded_dirty"))]
+    dirty: bool,
+    /// What happened since the last [MapState::take_events]. Events are meant to be taken every
+    /// tick, so they aren't saved.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    events: Vec<GameEvent>,
+    /// Callbacks stay with the app that registered them.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    observers: Observers,
+}
+
+impl Default for MapState {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl MapState {
     pub fn new() -> MapState {
-        let mut state = [Stripe::Empty; ROW_COUNT];
-        state.fill_with(Stripe::generate);
-        for i in 0..MAX_PLAYER_Y_INDEX + 1 {
-            state[i] = Stripe::Green(GreenStripe::generate());
-        }
+        Self::with_seed(rand::random())
+    }
+
+    pub fn with_seed(seed: u64) -> MapState {
+        Self::with_difficulty(seed, Difficulty::NORMAL)
+    }
+
+    pub fn with_difficulty(seed: u64, difficulty: Difficulty) -> MapState {
+        let mut row_rng = GameRng::new(seed, "row");
+        let mut state = generate_rows(&mut row_rng, ROW_COUNT + LOOKAHEAD_ROWS, &difficulty);
+        let lookahead = state.split_off(ROW_COUNT);
+        let mut map = Self::from_parts(seed, row_rng, state, None);
+        map.lookahead = VecDeque::from(lookahead);
+        map.difficulty = difficulty;
+        map
+    }
+
+    /// Plays the rows of `level` instead of random ones.
+    pub fn from_level(level: &Level) -> MapState {
+        Self::from_seeded_rows(rand::random(), level.rows.clone())
+    }
+
+    /// A board of `rows`, from the bottom one, with the player starting in column `player_x` of
+    /// the fourth. Rows beyond the board come into view as the player moves up, followed by
+    /// empty ones.
+    ///
+    /// Vehicles entering roads later on are picked from seed 0, so the same rows and moves always
+    /// play out the same way.
+    pub fn from_rows(rows: Vec<Stripe>, player_x: u8) -> MapState {
+        let mut map = Self::from_seeded_rows(0, rows);
+        map.players[0].x = player_x.min(STRIPE_LENGTH as u8 - 1);
+        map
+    }
+
+    fn from_seeded_rows(seed: u64, rows: Vec<Stripe>) -> MapState {
+        let mut rows = VecDeque::from(rows);
+        let state = (0..ROW_COUNT)
+            .map(|_| rows.pop_front().unwrap_or(Stripe::Empty))
+            .collect();
+        Self::from_parts(seed, GameRng::new(seed, "row"), state, Some(rows))
+    }
+
+    fn from_parts(seed: u64, row_rng: GameRng, state: Vec<Stripe>, level_rows: Option<VecDeque<Stripe>>) -> MapState {
         MapState {
             state: VecDeque::from(state),
-            player_x: STRIPE_LENGTH.div(2) as u8,
+            players: vec![Player::new(Character::default())],
             bottom_y: 0,
-            player_down: 0,
+            highest_y: MAX_PLAYER_Y_INDEX as u64,
             score: 0,
             alive: true,
+            finished: false,
+            death_cause: None,
+            ticks_left: None,
+            level_rows,
+            lookahead: VecDeque::new(),
             wall_of_death: 0,
+            wall_of_death_moves: true,
+            harmless: false,
+            auto_scroll: None,
+            scroll_timer: 0,
+            difficulty: Difficulty::NORMAL,
+            biome: Biome::Meadow,
+            weather: Weather::Clear,
+            night: false,
+            lanterns: Vec::new(),
+            lantern_ticks: 0,
+            lives: None,
+            checkpoint: None,
             wall_of_death_phase: WallOfDeathPhase::Normal,
             tick: 0,
             game_started: false,
+            seed,
+            row_rng,
+            tick_rng: GameRng::new(seed.rotate_left(32), "tick"),
+            dirty: true,
+            events: Vec::new(),
+            observers: Observers::default(),
         }
     }
 
+    /// Scrolls the bottom row away every `steps` steps even if the player doesn't move, killing
+    /// a player that is left on it.
+    pub fn with_auto_scroll(mut self, steps: u64) -> MapState {
+        self.auto_scroll = Some(steps.max(1));
+        self.scroll_timer = steps.max(1);
+        self
+    }
+
+    pub fn auto_scrolls(&self) -> bool {
+        self.auto_scroll.is_some()
+    }
+
+    /// Steps between automatic scrolls, [None] if the board only scrolls when the player hops.
+    pub fn auto_scroll(&self) -> Option<u64> {
+        self.auto_scroll
+    }
+
+    /// Ends the run successfully `seconds` after the first key press.
+    pub fn with_time_limit(mut self, seconds: u64) -> MapState {
+        self.ticks_left = Some(seconds * TICKS_PER_SECOND);
+        self
+    }
+
+    /// Keeps the wall of death from advancing, so standing still is safe.
+    pub fn without_wall_of_death(mut self) -> MapState {
+        self.wall_of_death_moves = false;
+        self
+    }
+
+    /// Lets the players pass through cars, trains, trees and water after blinking for a moment
+    /// instead of dying. Falling off the bottom still ends the run.
+    pub fn without_collisions(mut self) -> MapState {
+        self.harmless = true;
+        self
+    }
+
+    pub fn is_harmless(&self) -> bool {
+        self.harmless
+    }
+
+    /// Respawns the player at the bottom of the board until `lives` lives are lost.
+    pub fn with_lives(mut self, lives: u32) -> MapState {
+        self.lives = Some(lives.max(1));
+        self
+    }
+
+    /// Lives left including the current one, [None] if the run ends with the first death.
+    pub fn lives(&self) -> Option<u32> {
+        self.lives
+    }
+
+    pub fn with_weather(mut self, weather: Weather) -> MapState {
+        self.weather = weather;
+        self
+    }
+
+    pub fn weather(&self) -> Weather {
+        self.weather
+    }
+
+    /// Hides the rows far from the player and places lanterns that light up more of them.
+    pub fn with_night(mut self) -> MapState {
+        self.night = true;
+        self
+    }
+
+    /// Lets the first player cross as `character`.
+    pub fn with_character(mut self, character: Character) -> MapState {
+        self.players[0].character = character;
+        self
+    }
+
+    /// Adds a second player crossing as `character` right of the first one. The run goes on
+    /// until both died.
+    pub fn with_second_player(mut self, character: Character) -> MapState {
+        let mut player = Player::new(character);
+        player.x += 1;
+        self.players.truncate(1);
+        self.players.push(player);
+        self
+    }
+
+    pub fn player_count(&self) -> usize {
+        self.players.len()
+    }
+
+    pub fn character(&self) -> Character {
+        self.players[0].character
+    }
+
+    pub fn facing(&self) -> Facing {
+        self.players[0].facing
+    }
+
+    /// The active effects with the seconds until they run out, rounded up. Effects on single
+    /// players are listed once with the longest time left.
+    pub fn effects(&self) -> Vec<(Effect, u64)> {
+        let alive = self.players.iter().filter(|player| player.alive);
+        let stuck_ticks = alive.clone().map(|player| player.stuck.div_ceil(self.difficulty.speed as u64)).max().unwrap_or_default();
+        let invulnerable_ticks = alive.map(|player| player.invulnerable_ticks).max().unwrap_or_default();
+        [(Effect::Lantern, self.lantern_ticks), (Effect::Stuck, stuck_ticks), (Effect::Invulnerable, invulnerable_ticks)]
+            .into_iter()
+            .filter(|(_, ticks)| *ticks > 0)
+            .map(|(effect, ticks)| (effect, ticks.div_ceil(TICKS_PER_SECOND)))
+            .collect()
+    }
+
+    /// The longest combo of a living player, once it is long enough to earn bonus points and
+    /// until they take longer than [COMBO_TICKS] for the next hop up.
+    pub fn combo(&self) -> Option<u32> {
+        self.players.iter()
+            .filter(|player| player.alive && player.last_hop_up.is_some_and(|last| self.tick - last <= COMBO_TICKS))
+            .map(|player| player.combo)
+            .max()
+            .filter(|combo| *combo >= COMBO_HOPS)
+    }
+
+    /// Seconds left in a timed run, rounded up.
+    pub fn seconds_left(&self) -> Option<u64> {
+        self.ticks_left.map(|ticks| ticks.div_ceil(TICKS_PER_SECOND))
+    }
+
+    /// Whether the rows come from a [Level] instead of being generated.
+    pub fn is_level(&self) -> bool {
+        self.level_rows.is_some()
+    }
+
+    pub fn score(&self) -> u64 {
+        self.score
+    }
+
+    /// The first players column, 0 is the leftmost one.
+    pub fn player_x(&self) -> u8 {
+        self.players[0].x
+    }
+
+    /// The row the first player stands on.
+    pub fn player_y(&self) -> u64 {
+        self.player_y_of(0)
+    }
+
+    /// Column and row of the first player.
+    pub fn player_pos(&self) -> (u8, u64) {
+        (self.player_x(), self.player_y())
+    }
+
+    /// The row player `player` stands on.
+    pub fn player_y_of(&self, player: usize) -> u64 {
+        self.y_pos(MAX_PLAYER_Y_INDEX.saturating_sub(self.players[player].down as usize))
+    }
+
+    /// The column of player `player`.
+    pub fn player_x_of(&self, player: usize) -> u8 {
+        self.players[player].x
+    }
+
+    /// The row `offset` rows above the first player, below for negative offsets, [None] if it
+    /// isn't visible.
+    pub fn row_near_player(&self, offset: isize) -> Option<&Stripe> {
+        self.row_near(0, offset)
+    }
+
+    fn row_near(&self, player: usize, offset: isize) -> Option<&Stripe> {
+        let idx = (MAX_PLAYER_Y_INDEX as isize - self.players[player].down as isize).checked_add(offset)?;
+        self.state.get(usize::try_from(idx).ok()?)
+    }
+
+    /// The rows on the board from the bottom one, which is row [MapState::visible_rows]`.start`.
+    pub fn rows(&self) -> impl DoubleEndedIterator<Item = &Stripe> + ExactSizeIterator {
+        self.state.iter()
+    }
+
+    /// The rows generated above the board but not visible yet, from the lowest one. Levels have
+    /// none.
+    pub fn lookahead(&self) -> impl DoubleEndedIterator<Item = &Stripe> + ExactSizeIterator {
+        self.lookahead.iter()
+    }
+
+    /// The rows on the board, from the bottom one.
+    pub fn visible_rows(&self) -> std::ops::Range<u64> {
+        self.bottom_y..self.y_pos(ROW_COUNT)
+    }
+
+    /// The tiles of every row on the screen, [VISIBLE_TILES] centered between the players still
+    /// alive as far as the edges of the board allow.
+    pub fn visible_columns(&self) -> std::ops::Range<usize> {
+        let alive = self.players.iter().filter(|player| player.alive).map(|player| player.x as usize).collect::<Vec<_>>();
+        let xs = if alive.is_empty() { self.players.iter().map(|player| player.x as usize).collect() } else { alive };
+        let center = (xs.iter().min().unwrap_or(&0) + xs.iter().max().unwrap_or(&0)) / 2;
+        let start = center.saturating_sub(VISIBLE_TILES / 2).min(STRIPE_LENGTH - VISIBLE_TILES);
+        start..start + VISIBLE_TILES
+    }
+
+    /// The amount of new rows the player reached.
+    pub fn rows_crossed(&self) -> u64 {
+        self.highest_y - MAX_PLAYER_Y_INDEX as u64
+    }
+
+    /// Whether the player pressed a key yet.
+    pub fn started(&self) -> bool {
+        self.game_started
+    }
+
+    pub fn death_cause(&self) -> Option<DeathCause> {
+        self.death_cause
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Whether a railroad right around a living player warns about a train.
+    pub fn train_warning(&self) -> bool {
+        (0..self.players.len()).filter(|player| self.players[*player].alive)
+            .flat_map(|player| (-1..=2).filter_map(move |offset| self.row_near(player, offset)))
+            .any(|row| matches!(row, Stripe::Rail(rail) if rail.warns()))
+    }
+
+    /// Whether the map looks different since the last call, so frontends can skip drawing it.
+    pub fn take_dirty(&mut self) -> bool {
+        std::mem::take(&mut self.dirty)
+    }
+
+    /// Calls `callback` with the new score whenever it changes.
+    pub fn on_score_change(&mut self, callback: impl Fn(u64) + Send + Sync + 'static) {
+        self.observers.score_change.push(Arc::new(callback));
+    }
+
+    /// Calls `callback` with the cause whenever a player dies, also when they have lives left.
+    pub fn on_death(&mut self, callback: impl Fn(DeathCause) + Send + Sync + 'static) {
+        self.observers.death.push(Arc::new(callback));
+    }
+
+    /// Calls `callback` with the y value and the row whenever a new row scrolls onto the board.
+    pub fn on_row_generated(&mut self, callback: impl Fn(u64, Stripe) + Send + Sync + 'static) {
+        self.observers.row_generated.push(Arc::new(callback));
+    }
+
+    /// What happened since the last call, oldest first.
+    pub fn take_events(&mut self) -> Vec<GameEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    /// Writes the rng audit log of this run, see [crate::rng].
+    pub fn write_rng_audit(&self) -> std::io::Result<()> {
+        write_audit_log(self.seed, &[&self.row_rng, &self.tick_rng])
+    }
+
+    pub fn difficulty(&self) -> Difficulty {
+        self.difficulty
+    }
+
+    /// The biome of the row the first player stands on.
+    pub fn biome(&self) -> Biome {
+        Biome::at(self.player_y())
+    }
+
+    /// Hash of the board generated by this maps seed, see [hash_seed].
+    pub fn board_hash(&self) -> BoardHash {
+        hash_seed(self.seed, &self.difficulty)
+    }
+
+    /// Hash of everything that changes while playing: the rows, the players, the score and the
+    /// tick. Runs that hash the same after the same ticks played out the same, which replays
+    /// check, see [crate::input].
+    pub fn state_hash(&self) -> BoardHash {
+        let mut bytes = Vec::new();
+        for row in &self.state {
+            bytes.extend(row.fingerprint());
+        }
+        for player in &self.players {
+            bytes.extend([player.x, player.down, player.facing as u8, player.alive.into()]);
+            bytes.extend(player.stuck.to_le_bytes());
+            bytes.extend(player.invulnerable_ticks.to_le_bytes());
+        }
+        for value in [self.bottom_y, self.score, self.wall_of_death, self.tick] {
+            bytes.extend(value.to_le_bytes());
+        }
+        bytes.extend([self.alive.into(), self.wall_of_death_phase as u8]);
+        hash_bytes(bytes)
+    }
+
     pub fn up(&mut self) {
+        self.hop(0, Facing::Up);
+    }
+
+    pub fn down(&mut self) {
+        self.hop(0, Facing::Down);
+    }
+
+    pub fn left(&mut self) {
+        self.hop(0, Facing::Left);
+    }
+
+    pub fn right(&mut self) {
+        self.hop(0, Facing::Right);
+    }
+
+    /// Lets player `player` hop toward `direction`. Dead players don't move.
+    pub fn hop(&mut self, player: usize, direction: Facing) {
         if !self.game_started { self.game_started = true; }
+        self.dirty = true;
+        if !self.players.get(player).is_some_and(|player| player.alive) {
+            return;
+        }
+        self.players[player].facing = direction;
+        match direction {
+            Facing::Up => for _ in 0..self.players[player].character.hop_rows() {
+                if !self.players[player].alive {
+                    return;
+                }
+                self.hop_up(player);
+            },
+            Facing::Down => self.hop_down(player),
+            Facing::Left => self.hop_sideways(player, -1),
+            Facing::Right => self.hop_sideways(player, 1),
+        }
+    }
 
-        if self.player_down > 0 {
-            self.player_down -= 1;
+    fn hop_up(&mut self, player: usize) {
+        let Player { x, down, stuck, .. } = self.players[player];
+        if stuck > 0 || self.rock_at((MAX_PLAYER_Y_INDEX + 1).checked_sub(down as usize), x) {
+            return;
+        }
+
+        if down > 0 {
+            self.players[player].down -= 1;
         } else {
-            self.score += 1;
-            self.state.push_back(Stripe::generate());
-            self.state.pop_front();
-            self.bottom_y += 1;
-            if self.wall_of_death < self.bottom_y {
-                self.wall_of_death_phase = WallOfDeathPhase::Normal;
-                self.wall_of_death = self.bottom_y;
+            self.scroll();
+            for (idx, other) in self.players.iter_mut().enumerate() {
+                if idx != player {
+                    other.down = other.down.saturating_add(1);
+                }
             }
         }
+        let tick = self.tick;
+        let hopper = &mut self.players[player];
+        let chained = hopper.last_hop_up.is_some_and(|last| tick - last <= COMBO_TICKS);
+        hopper.combo = if chained { hopper.combo + 1 } else { 1 };
+        hopper.last_hop_up = Some(tick);
+        let bonus = if hopper.combo >= COMBO_HOPS { COMBO_BONUS } else { 0 };
+        let player_y = self.player_y_of(player);
+        if player_y > self.highest_y {
+            self.highest_y = player_y;
+            let score = self.score_after_row() + bonus;
+            let points = score.saturating_sub(self.score);
+            self.set_score(score);
+            self.events.push(GameEvent::RowCrossed { player, y: player_y, score, points });
+        }
         self.detect_death();
+        self.land(player);
     }
 
-    pub fn down(&mut self) {
-        if !self.game_started { self.game_started = true; }
+    fn hop_down(&mut self, player: usize) {
+        let Player { x, down, stuck, .. } = self.players[player];
+        if stuck > 0 || self.rock_at(MAX_PLAYER_Y_INDEX.checked_sub(down as usize + 1), x) {
+            return;
+        }
 
-        self.player_down += 1;
+        self.players[player].down += 1;
+        self.players[player].combo = 0;
         self.detect_death();
+        self.land(player);
     }
 
-    pub fn left(&mut self) {
-        if !self.game_started { self.game_started = true; }
-
-        if self.player_x > 0 {
-            self.player_x -= 1;
+    /// Hops one column to the left for a negative `shift`, to the right otherwise.
+    fn hop_sideways(&mut self, player: usize, shift: i8) {
+        if self.players[player].stuck > 0 {
+            return;
+        }
+        let target = self.column_next_to(player, shift);
+        let moved = target.is_some_and(|x| !self.rock_at(self.player_idx(player), x));
+        if let Some(x) = target.filter(|_| moved) {
+            self.players[player].x = x;
         }
         self.detect_death();
+        if moved {
+            self.land(player);
+        }
+        if let Some(x) = self.column_next_to(player, shift) {
+            self.slide(player, x);
+        }
     }
 
-    pub fn right(&mut self) {
-        if !self.game_started { self.game_started = true; }
+    /// The column `shift` columns next to player `player`, [None] past the edges.
+    fn column_next_to(&self, player: usize, shift: i8) -> Option<u8> {
+        self.players[player].x.checked_add_signed(shift).filter(|x| (*x as usize) < STRIPE_LENGTH)
+    }
 
-        if self.player_x < (STRIPE_LENGTH - 1) as u8 {
-            self.player_x += 1;
+    /// Slides on to column `x` after a sideways hop in slippery weather, unless a tree or rock is
+    /// in the way or the player landed in a bush.
+    fn slide(&mut self, player: usize, x: u8) {
+        if !self.players[player].alive || !self.weather.slippery() || self.players[player].stuck > 0 {
+            return;
         }
-        self.detect_death();
+        let blocked = self.rock_at(self.player_idx(player), x) || self.row_near(player, 0)
+            .is_some_and(|row| matches!(row, Stripe::Green(_)) && row.collides(x));
+        if !blocked {
+            self.players[player].x = x;
+            self.detect_death();
+            self.land(player);
+        }
+    }
+
+    /// Index of the row player `player` stands on in [MapState::state], [None] once they fell
+    /// off the bottom.
+    fn player_idx(&self, player: usize) -> Option<usize> {
+        MAX_PLAYER_Y_INDEX.checked_sub(self.players[player].down as usize)
+    }
+
+    /// Whether a rock stands at column `x` of the row at `idx`.
+    fn rock_at(&self, idx: Option<usize>, x: u8) -> bool {
+        idx.and_then(|idx| self.state.get(idx)).is_some_and(|row| row.blocks(x))
+    }
+
+    /// Applies what is on the tile the player landed on: lanterns are picked up and bushes hold
+    /// the player in place.
+    fn land(&mut self, player: usize) {
+        let Some(player_idx) = self.player_idx(player) else { return };
+        if !self.players[player].alive {
+            return;
+        }
+        let x = self.players[player].x;
+        let position = (self.y_pos(player_idx), x);
+        if let Some(idx) = self.lanterns.iter().position(|lantern| *lantern == position) {
+            self.lanterns.remove(idx);
+            self.lantern_ticks = LANTERN_SECONDS * TICKS_PER_SECOND;
+        }
+        if let Stripe::Green(green) = self.state[player_idx] && green.tiles()[x as usize] == GrassTile::Bush {
+            self.players[player].stuck = BUSH_STEPS;
+        }
+    }
+
+    /// Lets the first player hop toward `input`, if any, and advances the game by exactly one
+    /// tick, like a key press followed by a tick in the app.
+    ///
+    /// Nothing depends on the wall clock: the same seed and inputs always lead to the same state,
+    /// so fuzzers and tests can drive as many ticks as they like.
+    pub fn step(&mut self, input: Option<Facing>) {
+        if let Some(direction) = input {
+            self.hop(0, direction);
+        }
+        self.update();
     }
 
     pub fn update(&mut self) {
+        self.lantern_ticks = self.lantern_ticks.saturating_sub(1);
+        for player in &mut self.players {
+            player.invulnerable_ticks = player.invulnerable_ticks.saturating_sub(1);
+        }
+        if self.game_started && self.alive && let Some(ticks_left) = &mut self.ticks_left {
+            *ticks_left = ticks_left.saturating_sub(1);
+            if *ticks_left == 0 {
+                self.finished = true;
+            }
+        }
+
+        let warned = self.train_warning();
+        for _ in 0..self.difficulty.speed {
+            self.advance();
+        }
+        if !warned && self.train_warning() {
+            self.events.push(GameEvent::TrainWarning);
+        }
+    }
+
+    /// Advances the world by one step, [Difficulty::speed] times per tick.
+    fn advance(&mut self) {
         self.tick += 1;
+        self.dirty = true;
+        for player in &mut self.players {
+            player.stuck = player.stuck.saturating_sub(1);
+        }
+        self.tick_rng.set_context(self.tick);
 
         for stripe in &mut self.state {
-            stripe.update();
+            stripe.update(&mut self.tick_rng);
+        }
+        for player in 0..self.players.len() {
+            if self.players[player].alive && let Some(player_idx) = self.player_idx(player)
+                && let Some(shift) = self.state[player_idx].carries() {
+                match self.column_next_to(player, shift) {
+                    Some(x) => self.players[player].x = x,
+                    None => self.die(player, DeathCause::Drowned),
+                }
+            }
         }
 
-        if self.game_started && self.tick % 5 == 0 {
+        if self.game_started && let Some(steps) = self.auto_scroll {
+            self.scroll_timer -= 1;
+            if self.scroll_timer == 0 {
+                self.scroll_timer = steps;
+                self.scroll();
+                for player in &mut self.players {
+                    player.down = player.down.saturating_add(1);
+                }
+            }
+        }
+
+        if self.game_started && self.wall_of_death_moves && self.tick.is_multiple_of(self.difficulty.wall_of_death_steps) {
             match self.wall_of_death_phase {
                 WallOfDeathPhase::Normal => { self.wall_of_death_phase = WallOfDeathPhase::Muddy }
                 WallOfDeathPhase::Muddy => { self.wall_of_death_phase = WallOfDeathPhase::Shaky }
@@ -115,43 +916,442 @@ impl MapState {
         self.detect_death();
     }
 
+    /// Moves the view up by one row.
+    fn scroll(&mut self) {
+        let row = self.next_row();
+        let y = self.y_pos(ROW_COUNT);
+        if self.night && let Stripe::Green(green) = row
+            && let Some(x) = lantern_at(self.seed, y)
+                .filter(|x| !green.trees()[*x as usize] && green.tiles()[*x as usize] != GrassTile::Rock) {
+            self.lanterns.push((y, x));
+        }
+        self.lanterns.retain(|(lantern_y, _)| *lantern_y > self.bottom_y);
+        for callback in &self.observers.row_generated {
+            callback(y, row);
+        }
+        self.state.push_back(row);
+        self.state.pop_front();
+        self.bottom_y += 1;
+        if self.wall_of_death < self.bottom_y {
+            self.wall_of_death_phase = WallOfDeathPhase::Normal;
+            self.wall_of_death = self.bottom_y;
+        }
+    }
+
+    /// Generates the board again from the seed with row `y` at the bottom.
+    fn restart_at(&mut self, y: u64) {
+        let mut row_rng = GameRng::new(self.seed, "row");
+        let mut rows = generate_rows(&mut row_rng, y as usize + ROW_COUNT + LOOKAHEAD_ROWS, &self.difficulty);
+        self.lookahead = VecDeque::from(rows.split_off(y as usize + ROW_COUNT));
+        self.state = VecDeque::from(rows.split_off(y as usize));
+        self.row_rng = row_rng;
+        self.bottom_y = y;
+    }
+
+    fn next_row(&mut self) -> Stripe {
+        match &mut self.level_rows {
+            Some(rows) => rows.pop_front().unwrap_or(Stripe::Empty),
+            None => {
+                let y = self.y_pos(ROW_COUNT + self.lookahead.len());
+                self.row_rng.set_context(y);
+                self.biome = Biome::at(y);
+                let row = if is_checkpoint(y) {
+                    Stripe::Checkpoint
+                } else {
+                    generate_row(&mut self.row_rng, &self.difficulty, self.biome, self.lookahead.make_contiguous())
+                };
+                self.lookahead.push_back(row);
+                self.lookahead.pop_front().unwrap_or(row)
+            }
+        }
+    }
+
     fn detect_death(&mut self) {
-        let player_pos = self.y_pos((MAX_PLAYER_Y_INDEX as i32 - self.player_down as i32) as usize);
-        if player_pos < self.wall_of_death
-            || (MAX_PLAYER_Y_INDEX as i32 - self.player_down as i32) < 0
-            || self.state.get(MAX_PLAYER_Y_INDEX - self.player_down as usize)
-                    .unwrap().collides(self.player_x) {
+        for player in 0..self.players.len() {
+            if !self.alive {
+                return;
+            }
+            if !self.players[player].alive {
+                continue;
+            }
+            let Some(player_idx) = self.player_idx(player) else {
+                self.die(player, DeathCause::FellBehind);
+                continue;
+            };
+            let stripe = self.state[player_idx];
+            let x = self.players[player].x;
+            let near_miss = matches!(DeathCause::of(&stripe), DeathCause::HitByCar | DeathCause::HitByTrain)
+                && [x.checked_sub(1), x.checked_add(1)].into_iter().flatten()
+                    .any(|x| (x as usize) < STRIPE_LENGTH && stripe.collides(x));
+            if near_miss && !self.players[player].near_miss {
+                self.events.push(GameEvent::NearMiss { player });
+            }
+            self.players[player].near_miss = near_miss;
+            if self.y_pos(player_idx) < self.wall_of_death {
+                self.die(player, DeathCause::FellBehind);
+            } else if stripe.collides(self.players[player].x) {
+                self.die(player, DeathCause::of(&stripe));
+            } else if matches!(stripe, Stripe::Checkpoint) {
+                self.checkpoint = Some(self.y_pos(player_idx));
+            } else if matches!(stripe, Stripe::Finish) {
+                self.finished = true;
+            }
+        }
+    }
+
+    /// Ends the run once no player is left.
+    fn die(&mut self, player: usize, cause: DeathCause) {
+        if self.players[player].invulnerable_ticks > 0 {
+            return;
+        }
+        if self.harmless && cause != DeathCause::FellBehind {
+            self.players[player].invulnerable_ticks = BUMP_TICKS;
+            self.events.push(GameEvent::Bump { player, cause });
+            return;
+        }
+        self.events.push(GameEvent::Death { player, cause });
+        for callback in &self.observers.death {
+            callback(cause);
+        }
+        if let Some(lives) = &mut self.lives && *lives > 1 {
+            *lives -= 1;
+            return self.respawn(player);
+        }
+        self.players[player].alive = false;
+        if self.players.iter().all(|player| !player.alive) {
             self.alive = false;
+            self.death_cause = Some(cause);
         }
     }
 
-    pub fn render(&self) -> String {
+    /// Puts the player back next to the other player if they are alive. Otherwise they start on
+    /// the bottom row, as close to its center as possible, and the wall of death is pushed back
+    /// to it. The board is moved back to the last checkpoint first.
+    fn respawn(&mut self, player: usize) {
+        self.set_score(self.score.saturating_sub(RESPAWN_PENALTY));
+        let partner = (0..self.players.len()).find(|other| *other != player && self.players[*other].alive);
+        let (down, target) = match partner {
+            Some(partner) => (self.players[partner].down, self.players[partner].x),
+            None => {
+                if let Some(checkpoint) = self.checkpoint {
+                    self.restart_at(checkpoint);
+                    for other in &mut self.players {
+                        other.down = MAX_PLAYER_Y_INDEX as u8;
+                    }
+                }
+                self.wall_of_death = self.bottom_y;
+                self.wall_of_death_phase = WallOfDeathPhase::Normal;
+                (MAX_PLAYER_Y_INDEX as u8, STRIPE_LENGTH.div(2) as u8)
+            }
+        };
+        let row = self.state[MAX_PLAYER_Y_INDEX - down as usize];
+        let respawned = &mut self.players[player];
+        respawned.down = down;
+        respawned.x = (0..STRIPE_LENGTH as u8)
+            .filter(|x| !row.collides(*x) && !row.blocks(*x) && (partner.is_none() || *x != target))
+            .min_by_key(|x| x.abs_diff(target))
+            .unwrap_or(target);
+        respawned.facing = Facing::Up;
+        respawned.stuck = 0;
+        respawned.combo = 0;
+        respawned.invulnerable_ticks = INVULNERABLE_SECONDS * TICKS_PER_SECOND;
+    }
+
+    pub fn render(&self, theme: &Theme) -> Grid {
+        self.render_animated(theme, &[])
+    }
+
+    /// Renders the board with the players in the middle of `hops`, indexed by player.
+    pub fn render_animated(&self, theme: &Theme, hops: &[Option<Hop>]) -> Grid {
+        let language = theme.language;
+        if self.finished && self.ticks_left.is_some() {
+            return Grid::from_text(&format!("{} {}", language.text(Text::TimesUp), language.format(Text::Score, &[&self.score])));
+        }
+        if self.finished {
+            return Grid::from_text(&format!("{} {}", language.text(Text::LevelComplete), language.format(Text::Score, &[&self.score])));
+        }
         if !self.alive {
-            return format!("You died! Score: {}", self.score);
+            let cause = self.death_cause.map_or(String::new(), |cause| format!(" {}.", language.text(Text::Cause(cause))));
+            return Grid::from_text(&format!("{}{cause} {}\n{}", language.text(Text::YouDied),
+                language.format(Text::Score, &[&self.score]), language.format(Text::Board, &[&self.board_hash()])));
+        }
+
+        self.render_board(theme, hops, None)
+    }
+
+    /// Renders the board as it was when the player crashed `ticks` ticks ago, flashing the player
+    /// red before fading out.
+    pub fn render_crash(&self, theme: &Theme, ticks: u32) -> Grid {
+        let mut grid = self.render_board(theme, &[], Some(ticks));
+        if ticks + CRASH_FADE_TICKS >= CRASH_TICKS {
+            grid.map_cells(|_, _, cell, next| dim(cell, next, theme));
         }
+        grid
+    }
 
-        self.state.par_iter()
+    /// Renders the rows, `crash` is the amount of ticks since the last player crashed.
+    fn render_board(&self, theme: &Theme, hops: &[Option<Hop>], crash: Option<u32>) -> Grid {
+        let mut grid = Grid::new();
+        let columns = self.visible_columns();
+        let lines = self.state.iter()
             .enumerate()
             .map(|(idx, stripe)| {
                 let mut stripe = stripe.visualize();
-                if idx == MAX_PLAYER_Y_INDEX - self.player_down as usize {
-                    stripe.add_overlay(self.player_x as usize, Block::White);
+                for (_, x) in self.lanterns.iter().filter(|(y, _)| *y == self.y_pos(idx)) {
+                    stripe.add_overlay(*x as usize, Block::DarkYellow);
+                }
+                for (player_number, player) in self.players.iter().enumerate() {
+                    let blinking = theme.accessibility.player_marker == PlayerMarker::Blinking || player.invulnerable_ticks > 0;
+                    let hidden = crash.is_none() && (!player.alive || blinking && (self.tick / BLINK_STEPS) % 2 == 1);
+                    if self.player_idx(player_number) != Some(idx) || hidden {
+                        continue;
+                    }
+                    stripe.add_player(player.x as usize, player.character, player.facing);
+                    if let Some(hop) = hops.get(player_number).copied().flatten() {
+                        stripe.animate_player(hop);
+                    }
+                    if crash.is_some_and(|ticks| (ticks / CRASH_FLASH_TICKS).is_multiple_of(2)) {
+                        stripe.add_overlay(player.x as usize, Block::Red);
+                    }
                 }
                 let phase = if self.y_pos(idx) == self.wall_of_death {
-                    self.wall_of_death_phase.clone()
+                    self.wall_of_death_phase
                 } else if self.y_pos(idx) < self.wall_of_death {
                     WallOfDeathPhase::Gone
                 } else {
                     WallOfDeathPhase::Normal
                 };
-                stripe.render(phase)
+                stripe.render(phase, theme, self.y_pos(idx), columns.clone())
             })
-            .rev()
-            .collect::<Vec<String>>()
-            .join("\n\r")
+            .collect::<Vec<_>>();
+        for line in lines.into_iter().rev() {
+            grid.push_line(line);
+        }
+        self.weather.apply(&mut grid, self.tick, self.y_pos(ROW_COUNT - 1));
+        if self.night {
+            self.apply_fog(&mut grid, theme);
+        }
+        grid
+    }
+
+    /// Dims the rows just outside the visible ones around the players and hides all others.
+    fn apply_fog(&self, grid: &mut Grid, theme: &Theme) {
+        let player_ys = (0..self.players.len())
+            .filter(|player| self.players[*player].alive)
+            .map(|player| self.player_y_of(player))
+            .collect::<Vec<_>>();
+        let top_y = self.y_pos(ROW_COUNT - 1);
+        let radius = if self.lantern_ticks > 0 { LANTERN_RADIUS } else { NIGHT_RADIUS };
+        grid.map_cells(|_, line, cell, next| {
+            let distance = player_ys.iter().map(|y| (top_y - line as u64).abs_diff(*y)).min().unwrap_or(u64::MAX);
+            if distance <= radius {
+                cell
+            } else if distance > radius + NIGHT_DIM_ROWS {
+                Cell::plain(' ')
+            } else {
+                dim(cell, next, theme)
+            }
+        });
+    }
+
+    fn set_score(&mut self, score: u64) {
+        if score != self.score {
+            self.score = score;
+            for callback in &self.observers.score_change {
+                callback(score);
+            }
+        }
+    }
+
+    /// The score after the player crossed into a new row.
+    fn score_after_row(&self) -> u64 {
+        #[cfg(feature = "scripting")]
+        if let Some(score) = crate::scripting::score_row(self.score, self.highest_y) {
+            return score;
+        }
+        self.score + 1
     }
 
     fn y_pos(&self, idx: usize) -> u64 {
         self.bottom_y + idx as u64
     }
 }
+
+/// Shades `cell` in its color, followed by `next` in the same line. Halves of wide glyphs are
+/// cleared instead.
+fn dim(cell: Cell, next: Option<&Cell>, theme: &Theme) -> Cell {
+    if cell.is_continuation() || next.is_some_and(Cell::is_continuation) {
+        Cell { glyph: ' ', fg: Color::Reset, bg: cell.bg }
+    } else {
+        let glyph = if theme.glyphs == Glyphs::Ascii { '.' } else { '░' };
+        Cell::colored(glyph, if cell.fg == Color::Reset { cell.bg } else { cell.fg })
+    }
+}
+
+/// The column of the lantern on grass row `y` of the board generated by `seed`, if it has one.
+///
+/// Lanterns are derived from the seed without touching the rngs, so night runs get the same rows
+/// as other runs with the same seed.
+fn lantern_at(seed: u64, y: u64) -> Option<u8> {
+    let n = (seed ^ y).wrapping_mul(0x9e3779b97f4a7c15);
+    (n >> 61 == 0).then(|| ((n >> 32) % STRIPE_LENGTH as u64) as u8)
+}
+
+/// Generates the first `count` rows of a board the same way [MapState] does.
+///
+/// The [Difficulty::start_rows] rows up to the one the player starts on are always safe.
+pub fn generate_rows(rng: &mut impl Rng, count: usize, difficulty: &Difficulty) -> Vec<Stripe> {
+    let safe_rows = (MAX_PLAYER_Y_INDEX + 1).saturating_sub(difficulty.start_rows)..=MAX_PLAYER_Y_INDEX;
+    let mut rows: Vec<Stripe> = Vec::with_capacity(count);
+    for i in 0..count {
+        let row = if safe_rows.contains(&i) {
+            Stripe::Green(GreenStripe::generate(rng, difficulty, Biome::at(i as u64), rows.last()))
+        } else if is_checkpoint(i as u64) {
+            Stripe::Checkpoint
+        } else {
+            generate_row(rng, difficulty, Biome::at(i as u64), &rows)
+        };
+        rows.push(row);
+    }
+    rows
+}
+
+/// Whether generated row `y` is a checkpoint.
+fn is_checkpoint(y: u64) -> bool {
+    y > 0 && y.is_multiple_of(CHECKPOINT_ROWS)
+}
+
+/// Generates the row above `previous`.
+///
+/// Rows that make the last [LOOKAHEAD_ROWS] rows impossible to cross are generated again, up
+/// to [GENERATION_ATTEMPTS] times before falling back to empty grass.
+fn generate_row(rng: &mut impl Rng, difficulty: &Difficulty, biome: Biome, previous: &[Stripe]) -> Stripe {
+    let mut window = previous[previous.len().saturating_sub(LOOKAHEAD_ROWS)..].to_vec();
+    for _ in 0..GENERATION_ATTEMPTS {
+        window.push(Stripe::generate(rng, difficulty, biome, previous.last()));
+        if passable(&window, difficulty) {
+            return window[window.len() - 1];
+        }
+        window.pop();
+    }
+    Stripe::Green(GreenStripe::new([false; STRIPE_LENGTH]))
+}
+
+
+#[cfg(feature = "serde")]
+fn loaded_dirty() -> bool {
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stripe::{Railroad, Road};
+
+    /// Inputs of the step tests, repeated for as long as they run.
+    const MOVES: [Option<Facing>; 7] = [
+        Some(Facing::Up), None, Some(Facing::Left), Some(Facing::Up), None, Some(Facing::Right), Some(Facing::Down),
+    ];
+
+    fn play(mut map: MapState, ticks: usize) -> MapState {
+        for tick in 0..ticks {
+            map.step(MOVES[tick % MOVES.len()]);
+        }
+        map
+    }
+
+    #[test]
+    fn steps_keep_the_board_consistent() {
+        for seed in 0..20 {
+            let mut map = MapState::with_seed(seed).without_collisions().without_wall_of_death();
+            let mut score = 0;
+            for tick in 0..1_000 {
+                map.step(MOVES[tick % MOVES.len()]);
+                assert!((map.player_x() as usize) < STRIPE_LENGTH, "seed {seed}, tick {tick}");
+                assert_eq!(map.rows().len(), ROW_COUNT, "seed {seed}, tick {tick}");
+                assert_eq!(map.lookahead().len(), LOOKAHEAD_ROWS, "seed {seed}, tick {tick}");
+                assert!(map.score() >= score, "seed {seed}, tick {tick}");
+                score = map.score();
+            }
+        }
+    }
+
+    #[test]
+    fn steps_only_depend_on_seed_and_inputs() {
+        for seed in 0..20 {
+            let hash = play(MapState::with_seed(seed), 500).state_hash();
+            assert_eq!(play(MapState::with_seed(seed), 500).state_hash(), hash, "seed {seed}");
+        }
+        assert_ne!(play(MapState::with_seed(1), 500).state_hash(), play(MapState::with_seed(2), 500).state_hash());
+    }
+
+    /// Four empty rows for the player to start on, followed by `rows`.
+    fn above_start(rows: impl IntoIterator<Item = Stripe>) -> Vec<Stripe> {
+        [Stripe::Empty; MAX_PLAYER_Y_INDEX + 1].into_iter().chain(rows).collect()
+    }
+
+    fn with_tile(x: usize) -> [bool; STRIPE_LENGTH] {
+        core::array::from_fn(|column| column == x)
+    }
+
+    #[test]
+    fn from_rows_fills_the_board_from_the_bottom() {
+        let rows = vec![Stripe::Green(GreenStripe::new([false; STRIPE_LENGTH])), Stripe::Rail(Railroad::new(40)), Stripe::Finish];
+        let map = MapState::from_rows(rows, 2);
+        let kinds = map.rows().map(Stripe::kind).collect::<Vec<_>>();
+        assert_eq!(kinds[..3], ["green", "rail", "finish"]);
+        assert!(kinds[3..].iter().all(|kind| *kind == "empty"));
+        assert_eq!(kinds.len(), ROW_COUNT);
+        assert_eq!(map.player_pos(), (2, MAX_PLAYER_Y_INDEX as u64));
+        assert_eq!(MapState::from_rows(Vec::new(), 200).player_x(), STRIPE_LENGTH as u8 - 1);
+    }
+
+    #[test]
+    fn from_rows_scrolls_the_remaining_rows_in() {
+        let mut rows = vec![Stripe::Empty; ROW_COUNT];
+        rows.push(Stripe::Checkpoint);
+        let mut map = MapState::from_rows(rows, 7);
+        assert!(map.is_level());
+        map.up();
+        assert_eq!(map.rows().last().map(Stripe::kind), Some("checkpoint"));
+        map.up();
+        assert_eq!(map.rows().last().map(Stripe::kind), Some("empty"));
+    }
+
+    #[test]
+    fn rocks_block_and_trees_kill() {
+        let tiles = core::array::from_fn(|x| if x == 7 { GrassTile::Rock } else { GrassTile::Grass });
+        let green = GreenStripe::new(with_tile(8)).with_tiles(tiles);
+        let mut map = MapState::from_rows(above_start([Stripe::Green(green)]), 7);
+        map.up();
+        assert_eq!(map.player_pos(), (7, MAX_PLAYER_Y_INDEX as u64));
+        assert!(map.alive);
+        map.right();
+        map.up();
+        assert!(!map.alive);
+        assert_eq!(map.death_cause(), Some(DeathCause::RanIntoTree));
+    }
+
+    #[test]
+    fn cars_on_explicit_roads_hit_the_player() {
+        let mut map = MapState::from_rows(above_start([Stripe::Road(Road::new(false, with_tile(7)))]), 7);
+        map.up();
+        assert_eq!(map.death_cause(), Some(DeathCause::HitByCar));
+
+        let mut map = MapState::from_rows(above_start([Stripe::Road(Road::new(false, with_tile(3)))]), 7);
+        map.up();
+        assert!(map.alive);
+    }
+
+    #[test]
+    fn rail_cycle_position_decides_about_trains() {
+        let rail = Railroad::new(40);
+        let mut map = MapState::from_rows(above_start([Stripe::Rail(rail.with_cycle_pos(1))]), 7);
+        map.up();
+        assert_eq!(map.death_cause(), Some(DeathCause::HitByTrain));
+
+        let mut map = MapState::from_rows(above_start([Stripe::Rail(rail.with_cycle_pos(20))]), 7);
+        map.up();
+        assert!(map.alive);
+    }
+}
@@ -0,0 +1,72 @@
+use crate::color::Color;
+use crate::grid::{text, Cell, Grid};
+use crate::locale::{Language, Text};
+
+/// A vertical list of labeled entries with one of them selected.
+pub struct Menu<T> {
+    title: String,
+    entries: Vec<(String, T)>,
+    /// What is left to do to unlock each entry, [None] for available ones.
+    locks: Vec<Option<String>>,
+    selected: usize,
+}
+
+impl<T: Clone> Menu<T> {
+    pub fn new(title: &str, entries: Vec<(String, T)>) -> Self {
+        Menu {
+            title: title.to_string(),
+            locks: vec![None; entries.len()],
+            entries,
+            selected: 0,
+        }
+    }
+
+    /// Greys out the entries `lock` returns what is left to do for, see [crate::unlocks].
+    pub fn with_locks(mut self, lock: impl Fn(&T) -> Option<String>) -> Self {
+        self.locks = self.entries.iter().map(|(_, value)| lock(value)).collect();
+        self
+    }
+
+    /// Selects the entry at `idx` instead of the first one.
+    pub fn with_selected(mut self, idx: usize) -> Self {
+        self.selected = idx.min(self.entries.len() - 1);
+        self
+    }
+
+    pub fn up(&mut self) {
+        self.selected = self.selected.checked_sub(1).unwrap_or(self.entries.len() - 1);
+    }
+
+    pub fn down(&mut self) {
+        self.selected = (self.selected + 1) % self.entries.len();
+    }
+
+    pub fn selected(&self) -> T {
+        self.entries[self.selected].1.clone()
+    }
+
+    pub fn selected_index(&self) -> usize {
+        self.selected
+    }
+
+    /// Whether the selected entry is still locked and can't be confirmed.
+    pub fn is_selected_locked(&self) -> bool {
+        self.locks[self.selected].is_some()
+    }
+
+    pub fn render(&self, language: Language) -> Grid {
+        let mut grid = Grid::from_text(&format!("{}\n", self.title));
+        for (idx, ((label, _), lock)) in self.entries.iter().zip(&self.locks).enumerate() {
+            let marker = if idx == self.selected { '>' } else { ' ' };
+            grid.push_line(match lock {
+                Some(lock) => {
+                    let line = format!("{marker} {label} ({})", language.format(Text::Locked, &[lock]));
+                    line.chars().map(|glyph| Cell::colored(glyph, Color::DarkGrey)).collect()
+                }
+                None => text(&format!("{marker} {label}")),
+            });
+        }
+        grid.push_text(&format!("\n{}", language.text(Text::MenuHint)));
+        grid
+    }
+}
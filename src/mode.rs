@@ -0,0 +1,213 @@
+//! The ways a run can be played.
+//!
+//! A [GameMode] decides how the [MapState] of a run is built, when the run ends and which high
+//! score table it counts toward.
+use std::time::{SystemTime, UNIX_EPOCH};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use crate::difficulty::Difficulty;
+use crate::level::{Level, LevelError};
+use crate::locale::{Language, Text};
+use crate::map::MapState;
+use crate::tutorial;
+
+/// Lengths of the time-attack runs offered in the main menu, in seconds.
+pub const TIME_ATTACK_SECONDS: [u64; 2] = [60, 120];
+
+/// Rows between the snapshots practice runs can be rewound to.
+pub const PRACTICE_SNAPSHOT_ROWS: u64 = 5;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum GameMode {
+    /// Random rows until the player dies.
+    Endless,
+    /// Cross as many rows as possible before the time runs out.
+    TimeAttack { seconds: u64 },
+    /// Random rows from a seed that is the same for everyone on a given `day` (UTC), counted in
+    /// days since the unix epoch.
+    Daily { day: u64 },
+    /// A bundled level by name or a level file by path.
+    Level(String),
+    /// The wall of death stands still, collisions only make the player blink and the score isn't
+    /// recorded.
+    Zen,
+    /// Dying allows rewinding to a snapshot taken every [PRACTICE_SNAPSHOT_ROWS] rows, the score
+    /// isn't recorded.
+    Practice,
+    /// The last move can be taken back until the next tick, the score isn't recorded.
+    Casual,
+    /// Random rows of which only the ones around the player are visible, lanterns light up more
+    /// of them for a while.
+    Night,
+    /// The guided first run, see [crate::tutorial].
+    Tutorial,
+}
+
+impl GameMode {
+    /// The daily run of today.
+    pub fn daily() -> GameMode {
+        GameMode::Daily { day: today() }
+    }
+
+    /// The daily run whose board is generated from `seed`, [None] if no day up to today has it.
+    pub fn daily_from_seed(seed: u64) -> Option<GameMode> {
+        let day = seed.wrapping_mul(DAILY_SEED_INVERSE);
+        (day <= today()).then_some(GameMode::Daily { day })
+    }
+
+    /// Parses the modes that don't need further arguments, as accepted by `--mode`.
+    pub fn from_name(name: &str) -> Option<GameMode> {
+        match name {
+            "endless" => Some(GameMode::Endless),
+            "time-attack" => Some(GameMode::TimeAttack { seconds: TIME_ATTACK_SECONDS[0] }),
+            "daily" => Some(GameMode::daily()),
+            "zen" => Some(GameMode::Zen),
+            "practice" => Some(GameMode::Practice),
+            "casual" => Some(GameMode::Casual),
+            "night" => Some(GameMode::Night),
+            "tutorial" => Some(GameMode::Tutorial),
+            _ => None,
+        }
+    }
+
+    /// The name [GameMode::from_name] parses, [None] for levels.
+    pub fn name(&self) -> Option<&'static str> {
+        Some(match self {
+            GameMode::Endless => "endless",
+            GameMode::TimeAttack { .. } => "time-attack",
+            GameMode::Daily { .. } => "daily",
+            GameMode::Level(_) => return None,
+            GameMode::Zen => "zen",
+            GameMode::Practice => "practice",
+            GameMode::Casual => "casual",
+            GameMode::Night => "night",
+            GameMode::Tutorial => "tutorial",
+        })
+    }
+
+    /// Shown in menus and the title.
+    pub fn title(&self, language: Language) -> String {
+        match self {
+            GameMode::Endless => language.text(Text::Endless).to_string(),
+            GameMode::TimeAttack { seconds } => language.format(Text::TimeAttack, &[seconds]),
+            GameMode::Daily { day } => language.format(Text::Daily, &[&format_date(*day)]),
+            GameMode::Level(name) => name.clone(),
+            GameMode::Zen => language.text(Text::Zen).to_string(),
+            GameMode::Practice => language.text(Text::Practice).to_string(),
+            GameMode::Casual => language.text(Text::Casual).to_string(),
+            GameMode::Night => language.text(Text::Night).to_string(),
+            GameMode::Tutorial => language.text(Text::Tutorial).to_string(),
+        }
+    }
+
+    /// Builds the map a new run of this mode starts with. Levels are always played at normal
+    /// difficulty.
+    pub fn create_map(&self, difficulty: Difficulty) -> Result<MapState, LevelError> {
+        self.create_seeded_map(rand::random(), difficulty)
+    }
+
+    /// Like [GameMode::create_map], but generates the board from `seed`. Daily runs, which use the
+    /// seed of their day, levels and the tutorial ignore it.
+    pub fn create_seeded_map(&self, seed: u64, difficulty: Difficulty) -> Result<MapState, LevelError> {
+        Ok(match self {
+            GameMode::Endless => MapState::with_difficulty(seed, difficulty),
+            GameMode::TimeAttack { seconds } => {
+                MapState::with_difficulty(seed, difficulty).with_time_limit(*seconds)
+            }
+            GameMode::Daily { day } => MapState::with_difficulty(daily_seed(*day), difficulty),
+            GameMode::Level(name) => MapState::from_level(&Level::load(name)?),
+            GameMode::Zen => MapState::with_difficulty(seed, difficulty).without_wall_of_death().without_collisions(),
+            GameMode::Night => MapState::with_difficulty(seed, difficulty).with_night(),
+            GameMode::Practice | GameMode::Casual => MapState::with_difficulty(seed, difficulty),
+            GameMode::Tutorial => tutorial::map(),
+        })
+    }
+
+    /// Name of the high score table runs of this mode at `difficulty` are submitted to, [None] if
+    /// they don't count.
+    pub fn high_score_table(&self, difficulty: &Difficulty) -> Option<String> {
+        let table = self.base_table()?;
+        if *difficulty == Difficulty::NORMAL || matches!(self, GameMode::Level(_)) {
+            Some(table)
+        } else {
+            Some(format!("{table}-{}", difficulty.name))
+        }
+    }
+
+    fn base_table(&self) -> Option<String> {
+        match self {
+            GameMode::Endless => Some("endless".to_string()),
+            GameMode::TimeAttack { seconds } => Some(format!("time-attack-{seconds}")),
+            GameMode::Daily { day } => Some(format!("daily-{}", format_date(*day))),
+            GameMode::Level(name) => Some(format!("level-{name}")),
+            GameMode::Night => Some("night".to_string()),
+            GameMode::Zen | GameMode::Practice | GameMode::Casual | GameMode::Tutorial => None,
+        }
+    }
+
+    /// Whether the progress toward the best score is meaningful, which isn't the case when the
+    /// amount of rows is fixed.
+    pub fn shows_progress(&self) -> bool {
+        !matches!(self, GameMode::Level(_) | GameMode::Zen | GameMode::Practice | GameMode::Casual | GameMode::Tutorial)
+    }
+}
+
+/// Days since the unix epoch in UTC.
+fn today() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() / 86_400
+}
+
+/// Multiplier spreading consecutive days over the whole seed range, odd so it can be undone.
+const DAILY_SEED_FACTOR: u64 = 0x9e37_79b9_7f4a_7c15;
+
+/// Multiplicative inverse of [DAILY_SEED_FACTOR] modulo 2^64, turning daily seeds back into days.
+const DAILY_SEED_INVERSE: u64 = {
+    // Each Newton step doubles the correct low bits, starting from the 3 an odd number gets right.
+    let mut inverse = DAILY_SEED_FACTOR;
+    let mut step = 0;
+    while step < 5 {
+        inverse = inverse.wrapping_mul(2u64.wrapping_sub(DAILY_SEED_FACTOR.wrapping_mul(inverse)));
+        step += 1;
+    }
+    inverse
+};
+
+/// Spreads consecutive days over the whole seed range.
+fn daily_seed(day: u64) -> u64 {
+    day.wrapping_mul(DAILY_SEED_FACTOR)
+}
+
+/// Formats days since the unix epoch as `YYYY-MM-DD`.
+fn format_date(day: u64) -> String {
+    // Converts to the proleptic gregorian calendar with eras of 400 years starting in March.
+    let days = day + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day_of_month = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day_of_month:02}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn daily_seeds_tell_their_day() {
+        for day in [0, 1, 20_377, today()] {
+            assert_eq!(GameMode::daily_from_seed(daily_seed(day)), Some(GameMode::Daily { day }));
+        }
+        assert_eq!(GameMode::daily_from_seed(daily_seed(today() + 1)), None);
+    }
+
+    #[test]
+    fn daily_maps_come_from_their_day() {
+        let map = GameMode::Daily { day: 20_000 }.create_seeded_map(1, Difficulty::NORMAL).unwrap();
+        assert_eq!(map.seed(), daily_seed(20_000));
+    }
+}
@@ -0,0 +1,113 @@
+//! Workarounds for running inside terminal multiplexers like tmux and GNU screen.
+//!
+//! Multiplexers interpret escape sequences themselves and drop the ones they don't know, so
+//! sequences meant for the outer terminal (taskbar progress, clipboard) have to be wrapped in a
+//! passthrough sequence. tmux only forwards them with `set -g allow-passthrough on`.
+use std::fmt;
+use std::sync::OnceLock;
+use crossterm::Command;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Multiplexer {
+    None,
+    Tmux,
+    Screen,
+}
+
+impl Multiplexer {
+    /// The multiplexer this process runs in, detected once.
+    pub fn current() -> Self {
+        static CURRENT: OnceLock<Multiplexer> = OnceLock::new();
+        *CURRENT.get_or_init(Self::detect)
+    }
+
+    pub fn detect() -> Self {
+        let term = std::env::var("TERM").unwrap_or_default();
+        if std::env::var_os("TMUX").is_some() || term.starts_with("tmux") {
+            Multiplexer::Tmux
+        } else if std::env::var_os("STY").is_some() || term.starts_with("screen") {
+            Multiplexer::Screen
+        } else {
+            Multiplexer::None
+        }
+    }
+
+    /// Wraps `sequence` so the multiplexer forwards it to the outer terminal.
+    pub fn passthrough(self, sequence: &str) -> String {
+        match self {
+            Multiplexer::None => sequence.to_string(),
+            Multiplexer::Tmux => format!("\x1bPtmux;{}\x1b\\", sequence.replace('\x1b', "\x1b\x1b")),
+            Multiplexer::Screen => format!("\x1bP{sequence}\x1b\\"),
+        }
+    }
+
+    /// tmux handles synchronized updates itself since 3.2, screen ignores them and may print
+    /// garbage for them.
+    pub fn supports_synchronized_updates(self) -> bool {
+        self != Multiplexer::Screen
+    }
+
+    /// Whether 24-bit colors reach the outer terminal.
+    ///
+    /// Trusts `COLORTERM`, `TERM` values like `xterm-direct` and Windows Terminal, which sets
+    /// `WT_SESSION` but no `COLORTERM`. Multiplexers often don't forward
+    /// `COLORTERM`, so tmux is trusted when its `TERM` or the outer terminal advertises it, screen
+    /// is assumed not to support it.
+    pub fn supports_truecolor(self) -> bool {
+        let colorterm = std::env::var("COLORTERM").unwrap_or_default();
+        let advertised = colorterm == "truecolor" || colorterm == "24bit"
+            || std::env::var("TERM").is_ok_and(|term| term.contains("direct"))
+            || std::env::var_os("WT_SESSION").is_some();
+        match self {
+            Multiplexer::None | Multiplexer::Tmux => advertised,
+            Multiplexer::Screen => false,
+        }
+    }
+}
+
+/// Runs the wrapped command through [Multiplexer::passthrough] of the current multiplexer.
+pub struct Passthrough<C: Command>(pub C);
+
+impl<C: Command> Command for Passthrough<C> {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        let mut sequence = String::new();
+        self.0.write_ansi(&mut sequence)?;
+        f.write_str(&Multiplexer::current().passthrough(&sequence))
+    }
+
+    #[cfg(windows)]
+    fn execute_winapi(&self) -> std::io::Result<()> {
+        self.0.execute_winapi()
+    }
+}
+
+/// Copies text to the system clipboard using `OSC 52`, through multiplexers as well.
+pub struct SetClipboard<'a>(pub &'a str);
+
+impl Command for SetClipboard<'_> {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        let sequence = format!("\x1b]52;c;{}\x07", base64(self.0.as_bytes()));
+        f.write_str(&Multiplexer::current().passthrough(&sequence))
+    }
+
+    #[cfg(windows)]
+    fn execute_winapi(&self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+pub(crate) fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut res = String::new();
+    for chunk in bytes.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, b)| n | (*b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                res.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                res.push('=');
+            }
+        }
+    }
+    res
+}
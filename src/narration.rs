@@ -0,0 +1,110 @@
+//! Describes the surroundings of the player in words for screen readers and braille displays.
+//!
+//! With the `screen_reader` profile setting the board is replaced by a few lines like
+//! `Row ahead: road, traffic from the left, car 2 columns away, free at columns 1-2, 5-7`.
+//! Columns are counted from 1 on the left.
+use crate::map::MapState;
+use crate::stripe::{GrassTile, Stripe, STRIPE_LENGTH};
+
+/// The rows ahead, below and behind the player.
+pub fn describe(map: &MapState) -> String {
+    let x = map.player_x();
+    let mut lines = vec![format!("Score {}, column {} of {STRIPE_LENGTH}.", map.score(), x + 1)];
+    for (name, offset) in [("Row ahead", 1), ("Your row", 0), ("Row behind", -1)] {
+        if let Some(row) = map.row_near_player(offset) {
+            lines.push(format!("{name}: {}", describe_row(row, x)));
+        }
+    }
+    lines.join("\n")
+}
+
+fn describe_row(row: &Stripe, x: u8) -> String {
+    let mut parts = Vec::new();
+    match row {
+        Stripe::Empty => parts.push("empty".to_string()),
+        Stripe::Finish => parts.push("finish line".to_string()),
+        Stripe::Checkpoint => parts.push("checkpoint".to_string()),
+        Stripe::Green(green) => {
+            parts.push("grass".to_string());
+            for (tile, name) in [(GrassTile::Rock, "rocks"), (GrassTile::Bush, "bushes")] {
+                let columns = (0..STRIPE_LENGTH as u8).filter(|col| green.tiles()[*col as usize] == tile).collect::<Vec<_>>();
+                if !columns.is_empty() {
+                    parts.push(format!("{name} at columns {}", ranges(&columns)));
+                }
+            }
+        }
+        Stripe::Road(road) => {
+            parts.push("road".to_string());
+            let side = if road.left() { "right" } else { "left" };
+            parts.push(format!("traffic from the {side}"));
+            parts.push(approaching_car(road.cars(), road.left(), x as usize));
+            if let Some(crossing) = road.crossing() {
+                parts.push(format!("zebra crossing at column {}", crossing + 1));
+            }
+        }
+        Stripe::TrafficRoad(traffic) => {
+            let road = traffic.road();
+            parts.push(format!("road with {} light", if traffic.red() { "red" } else { "green" }));
+            let side = if road.left() { "right" } else { "left" };
+            parts.push(format!("traffic from the {side}"));
+            parts.push(approaching_car(road.cars(), road.left(), x as usize));
+        }
+        Stripe::River(river) => {
+            parts.push("river".to_string());
+            parts.push(format!("flowing to the {}", if river.left() { "left" } else { "right" }));
+            if river.diving() {
+                parts.push("turtles under water".to_string());
+            }
+        }
+        Stripe::Rail(rail) => parts.push(if row.collides(x) {
+            "railroad, train passing".to_string()
+        } else if rail.warns() {
+            "railroad, train coming".to_string()
+        } else {
+            "railroad, clear".to_string()
+        }),
+        #[cfg(feature = "scripting")]
+        Stripe::Scripted(_) => parts.push("scripted".to_string()),
+    }
+    if !matches!(row, Stripe::Empty | Stripe::Finish | Stripe::Checkpoint | Stripe::Rail(_)) {
+        let free = (0..STRIPE_LENGTH as u8).filter(|col| !row.collides(*col)).collect::<Vec<_>>();
+        parts.push(match free.as_slice() {
+            [] => "blocked".to_string(),
+            free if free.len() == STRIPE_LENGTH => "free".to_string(),
+            free => format!("free at columns {}", ranges(free)),
+        });
+    }
+    parts.join(", ")
+}
+
+/// How far the nearest car that will reach column `x` is away.
+fn approaching_car(cars: [bool; STRIPE_LENGTH], moving_left: bool, x: usize) -> String {
+    let distance = (0..STRIPE_LENGTH)
+        .take_while(|distance| if moving_left { x + distance < STRIPE_LENGTH } else { *distance <= x })
+        .find(|distance| cars[if moving_left { x + distance } else { x - distance }]);
+    match distance {
+        Some(0) => "car in your column".to_string(),
+        Some(1) => "car 1 column away".to_string(),
+        Some(distance) => format!("car {distance} columns away"),
+        None => "no car approaching".to_string(),
+    }
+}
+
+/// Zero based columns as one based ranges like `1-2, 5`.
+fn ranges(columns: &[u8]) -> String {
+    let mut ranges: Vec<(u8, u8)> = Vec::new();
+    for col in columns {
+        match ranges.last_mut() {
+            Some((_, end)) if *end + 1 == *col => *end = *col,
+            _ => ranges.push((*col, *col)),
+        }
+    }
+    ranges.iter()
+        .map(|(start, end)| if start == end {
+            format!("{}", start + 1)
+        } else {
+            format!("{}-{}", start + 1, end + 1)
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
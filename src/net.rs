@@ -0,0 +1,137 @@
+//! Races against a player on another machine over TCP.
+//!
+//! The host sends `crossy_terminal <version> <seed>` after accepting the connection, so both
+//! players cross the same rows. Afterwards both sides send a `<y> <score> <alive>` line whenever
+//! their [Progress] changes.
+use std::io::{BufRead, BufReader, ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::ops::Range;
+use crate::color::Color;
+use crate::grid::{Cell, Grid};
+use crate::theme::Glyphs;
+
+/// Port used by `--host` and by `--join` for addresses without one.
+pub const DEFAULT_PORT: u16 = 7531;
+
+/// Changes whenever the lines exchanged change meaning.
+const PROTOCOL_VERSION: u32 = 1;
+
+/// How far a player got, as sent to the other one.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct Progress {
+    /// The row the player stands on.
+    pub y: u64,
+    pub score: u64,
+    pub alive: bool,
+}
+
+impl Progress {
+    fn to_line(self) -> String {
+        format!("{} {} {}\n", self.y, self.score, self.alive as u8)
+    }
+
+    fn from_line(line: &str) -> Option<Progress> {
+        let mut values = line.split_whitespace().map(|value| value.parse::<u64>().ok());
+        let (Some(Some(y)), Some(Some(score)), Some(Some(alive)), None) = (values.next(), values.next(), values.next(), values.next()) else {
+            return None;
+        };
+        Some(Progress { y, score, alive: alive != 0 })
+    }
+
+    /// A column as high as the board showing where the other player is on the `visible` rows,
+    /// with an arrow at the edge if they are out of sight.
+    pub fn marker_column(&self, visible: Range<u64>, glyphs: Glyphs) -> Grid {
+        let ascii = glyphs == Glyphs::Ascii;
+        let marker = match (self.alive, ascii) {
+            (true, true) => '@',
+            (true, false) => '●',
+            (false, true) => 'x',
+            (false, false) => '✗',
+        };
+        let mut grid = Grid::new();
+        for y in visible.clone().rev() {
+            let glyph = if y == self.y {
+                marker
+            } else if y + 1 == visible.end && self.y >= visible.end {
+                if ascii { '^' } else { '↑' }
+            } else if y == visible.start && self.y < visible.start {
+                if ascii { 'v' } else { '↓' }
+            } else {
+                ' '
+            };
+            grid.push_line(vec![Cell::colored(glyph, Color::Magenta)]);
+        }
+        grid
+    }
+}
+
+/// A connection to the other player of a race.
+pub struct Connection {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+    /// Read but not yet complete line.
+    buffer: String,
+    /// The progress sent last, to only send changes.
+    sent: Option<Progress>,
+}
+
+impl Connection {
+    /// Waits for a player to join on `port` and sends them `seed`.
+    pub fn host(port: u16, seed: u64) -> std::io::Result<Connection> {
+        let (mut stream, _) = TcpListener::bind(("0.0.0.0", port))?.accept()?;
+        stream.write_all(format!("crossy_terminal {PROTOCOL_VERSION} {seed}\n").as_bytes())?;
+        Self::from_stream(stream.try_clone()?, BufReader::new(stream))
+    }
+
+    /// Connects to the host at `address` and returns the seed to race on.
+    pub fn join(address: &str) -> std::io::Result<(Connection, u64)> {
+        let stream = if address.contains(':') {
+            TcpStream::connect(address)?
+        } else {
+            TcpStream::connect((address, DEFAULT_PORT))?
+        };
+        // Progress lines buffered with the greeting are kept for [Connection::receive].
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut greeting = String::new();
+        (&mut reader).take(64).read_line(&mut greeting)?;
+        let seed = match greeting.split_whitespace().collect::<Vec<_>>()[..] {
+            ["crossy_terminal", version, seed] if version == PROTOCOL_VERSION.to_string() => seed.parse().ok(),
+            _ => None,
+        };
+        let seed = seed.ok_or_else(|| std::io::Error::new(ErrorKind::InvalidData, "not a compatible crossy_terminal host"))?;
+        Self::from_stream(stream, reader).map(|connection| (connection, seed))
+    }
+
+    fn from_stream(stream: TcpStream, reader: BufReader<TcpStream>) -> std::io::Result<Connection> {
+        stream.set_nonblocking(true)?;
+        stream.set_nodelay(true)?;
+        Ok(Connection { reader, writer: stream, buffer: String::new(), sent: None })
+    }
+
+    /// Sends `progress` if it changed since the last call.
+    pub fn send(&mut self, progress: Progress) -> std::io::Result<()> {
+        if self.sent == Some(progress) {
+            return Ok(());
+        }
+        self.writer.write_all(progress.to_line().as_bytes())?;
+        self.sent = Some(progress);
+        Ok(())
+    }
+
+    /// The latest progress the other player sent since the last call, without blocking.
+    pub fn receive(&mut self) -> std::io::Result<Option<Progress>> {
+        let mut latest = None;
+        loop {
+            match self.reader.read_line(&mut self.buffer) {
+                Ok(0) => return Err(ErrorKind::UnexpectedEof.into()),
+                Ok(_) if self.buffer.ends_with('\n') => {
+                    latest = Progress::from_line(&self.buffer).or(latest);
+                    self.buffer.clear();
+                }
+                Ok(_) => {}
+                Err(err) if err.kind() == ErrorKind::WouldBlock => return Ok(latest),
+                Err(err) => return Err(err),
+            }
+        }
+    }
+}
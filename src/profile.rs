@@ -0,0 +1,233 @@
+//! Settings that persist between runs.
+//!
+//! The profile is stored as `key = value` lines in `profile.conf` inside [profile_dir].
+//!
+//! Players sharing a computer can keep apart in named profiles, picked with `--profile <name>`
+//! or from the main menu. Each has its own settings, stats, high scores and saved game in
+//! [PROFILES_DIR], while the default profile keeps them in [data_dir] directly.
+use std::path::PathBuf;
+use std::sync::Mutex;
+use crate::character::Character;
+use crate::keymap::KeyboardLayout;
+use crate::locale::Language;
+use crate::sound::SoundSettings;
+use crate::stripe::Block;
+use crate::theme::{parse_hex, Accessibility, Glyphs, PlayerMarker, Theme};
+
+const PROFILE_FILE: &str = "profile.conf";
+
+/// Directory inside [data_dir] holding a directory for each named profile.
+pub const PROFILES_DIR: &str = "profiles";
+
+/// The longest name a profile can have.
+pub const MAX_NAME_LEN: usize = 24;
+
+/// The named profile in use, [None] for the default one, see [select].
+static SELECTED: Mutex<Option<String>> = Mutex::new(None);
+
+/// The slowest and fastest simulation speed, relative to the default.
+pub const SPEED_RANGE: std::ops::RangeInclusive<f32> = 0.5..=2.0;
+
+/// The frame rates frames can be capped at.
+pub const FPS_RANGE: std::ops::RangeInclusive<u32> = 1..=240;
+
+/// Frames drawn per second at most unless the player picks another cap.
+pub const DEFAULT_FPS: u32 = 30;
+
+#[derive(Debug, Clone, Default)]
+pub struct Profile {
+    /// The keyboard layout movement keys are chosen for, [None] until the player picked one.
+    pub layout: Option<KeyboardLayout>,
+    pub theme: Theme,
+    /// The glyphs picked by the player, [None] to detect them. The theme uses the detected ones.
+    pub glyphs: Option<Glyphs>,
+    /// Whether hazards get their own shapes, [None] to use the themes default.
+    pub hazard_shapes: Option<bool>,
+    /// Whether games are described in words instead of drawn, see [crate::narration].
+    pub screen_reader: bool,
+    /// Applied to every theme.
+    pub accessibility: Accessibility,
+    pub sound: SoundSettings,
+    pub character: Character,
+    /// The language picked by the player, [None] to follow the locale.
+    pub language: Option<Language>,
+    /// How fast the simulation runs relative to the default, [None] for the default. Always in
+    /// [SPEED_RANGE].
+    pub speed: Option<f32>,
+    /// How many frames are drawn per second at most, [None] for [DEFAULT_FPS]. Always in
+    /// [FPS_RANGE].
+    pub fps: Option<u32>,
+}
+
+impl Profile {
+    /// Loads the profile, falling back to defaults for everything that is missing or invalid.
+    pub fn load() -> Profile {
+        let mut profile = Profile::default();
+        let Ok(source) = std::fs::read_to_string(profile_dir().join(PROFILE_FILE)) else {
+            return profile;
+        };
+        let mut theme_overrides = Vec::new();
+        for (key, value) in parse_entries(&source) {
+            match key {
+                "layout" => profile.layout = KeyboardLayout::from_name(value),
+                "theme" => profile.theme = Theme::from_name(value).unwrap_or_default(),
+                "glyphs" => profile.glyphs = Glyphs::from_name(value),
+                "hazard_shapes" => profile.hazard_shapes = value.parse().ok(),
+                "screen_reader" => profile.screen_reader = value == "true",
+                "reduced_motion" => profile.accessibility.reduced_motion = value == "true",
+                "high_contrast" => profile.accessibility.high_contrast = value == "true",
+                "mute" => profile.sound.muted = value == "true",
+                "music" => profile.sound.music = value == "true",
+                "volume" => if let Ok(volume) = value.parse::<u8>() {
+                    profile.sound.volume = volume.min(100);
+                },
+                "speed" => profile.speed = value.parse().ok().filter(|speed| SPEED_RANGE.contains(speed) && *speed != 1.0),
+                "fps" => profile.fps = value.parse().ok().filter(|fps| FPS_RANGE.contains(fps) && *fps != DEFAULT_FPS),
+                "language" => profile.language = Language::from_name(value),
+                "character" => profile.character = Character::from_name(value).unwrap_or_default(),
+                "player_marker" => profile.accessibility.player_marker = PlayerMarker::from_name(value).unwrap_or_default(),
+                _ => if let Some(block) = key.strip_prefix("theme.").and_then(Block::from_name)
+                    && let Some(color) = parse_hex(value) {
+                    theme_overrides.push((block, color));
+                },
+            }
+        }
+        // Overrides apply to the preset no matter where they are in the file.
+        for (block, color) in theme_overrides {
+            profile.theme.set_color(block, color);
+        }
+        profile.theme.glyphs = profile.glyphs.unwrap_or_else(Glyphs::detect);
+        if let Some(shapes) = profile.hazard_shapes {
+            profile.theme.hazard_shapes = shapes;
+        }
+        profile.theme.accessibility = profile.accessibility;
+        profile.theme.language = profile.language.unwrap_or_else(Language::detect);
+        profile
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        let mut entries = Vec::new();
+        if let Some(layout) = self.layout {
+            entries.push(("layout", layout.name().to_string()));
+        }
+        if let Some(glyphs) = self.glyphs {
+            entries.push(("glyphs", glyphs.name().to_string()));
+        }
+        if let Some(shapes) = self.hazard_shapes {
+            entries.push(("hazard_shapes", shapes.to_string()));
+        }
+        if self.screen_reader {
+            entries.push(("screen_reader", "true".to_string()));
+        }
+        if self.accessibility.reduced_motion {
+            entries.push(("reduced_motion", "true".to_string()));
+        }
+        if self.accessibility.high_contrast {
+            entries.push(("high_contrast", "true".to_string()));
+        }
+        if self.accessibility.player_marker != PlayerMarker::Block {
+            entries.push(("player_marker", self.accessibility.player_marker.name().to_string()));
+        }
+        if self.sound.muted {
+            entries.push(("mute", "true".to_string()));
+        }
+        if self.sound.music {
+            entries.push(("music", "true".to_string()));
+        }
+        if self.sound.volume != SoundSettings::default().volume {
+            entries.push(("volume", self.sound.volume.to_string()));
+        }
+        if let Some(speed) = self.speed {
+            entries.push(("speed", speed.to_string()));
+        }
+        if let Some(fps) = self.fps {
+            entries.push(("fps", fps.to_string()));
+        }
+        if let Some(language) = self.language {
+            entries.push(("language", language.name().to_string()));
+        }
+        if self.character != Character::default() {
+            entries.push(("character", self.character.name().to_string()));
+        }
+        entries.push(("theme", self.theme.name.to_string()));
+        let overrides = self.theme.overrides();
+        let override_keys = overrides.iter().map(|(block, _)| format!("theme.{}", block.name())).collect::<Vec<_>>();
+        for (key, (_, color)) in override_keys.iter().zip(overrides) {
+            entries.push((key.as_str(), color));
+        }
+        std::fs::create_dir_all(profile_dir())?;
+        std::fs::write(profile_dir().join(PROFILE_FILE), format_entries(&entries))
+    }
+}
+
+/// Whether `name` can name a profile: up to [MAX_NAME_LEN] letters, digits, `-` and `_`, so it
+/// is a valid directory name everywhere.
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty() && name.len() <= MAX_NAME_LEN && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Switches to the profile called `name`, [None] for the default one. The profile is created
+/// once something is saved to it.
+///
+/// Panics if `name` isn't [is_valid_name].
+pub fn select(name: Option<&str>) {
+    assert!(name.is_none_or(is_valid_name), "invalid profile name");
+    *SELECTED.lock().unwrap() = name.map(str::to_string);
+}
+
+/// The name of the profile in use, [None] for the default one.
+pub fn selected() -> Option<String> {
+    SELECTED.lock().unwrap().clone()
+}
+
+/// The names of all named profiles, sorted.
+pub fn names() -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(data_dir().join(PROFILES_DIR)) else {
+        return Vec::new();
+    };
+    let mut names = entries.filter_map(Result::ok)
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| is_valid_name(name))
+        .collect::<Vec<_>>();
+    names.sort();
+    names
+}
+
+/// Directory the files of the [selected] profile are stored in: the settings, stats, high scores
+/// and the saved game. Screenshots, GIFs and logs stay in [data_dir].
+pub fn profile_dir() -> PathBuf {
+    match selected() {
+        Some(name) => data_dir().join(PROFILES_DIR).join(name),
+        None => data_dir(),
+    }
+}
+
+/// Directory all persistent files are stored in.
+///
+/// Can be overwritten with the `CROSSY_TERMINAL_HOME` environment variable.
+pub fn data_dir() -> PathBuf {
+    if let Some(dir) = std::env::var_os("CROSSY_TERMINAL_HOME") {
+        return PathBuf::from(dir);
+    }
+    let base = std::env::var_os("XDG_CONFIG_HOME").map(PathBuf::from)
+        .or_else(|| std::env::var_os("APPDATA").map(PathBuf::from))
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .unwrap_or_default();
+    base.join("crossy_terminal")
+}
+
+/// Parses `key = value` lines, skipping empty lines and `#` comments.
+pub(crate) fn parse_entries(source: &str) -> impl Iterator<Item = (&str, &str)> {
+    source.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.trim(), value.trim()))
+}
+
+pub(crate) fn format_entries(entries: &[(&str, String)]) -> String {
+    entries.iter()
+        .map(|(key, value)| format!("{key} = {value}\n"))
+        .collect()
+}
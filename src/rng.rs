@@ -0,0 +1,157 @@
+//! Seeded randomness with an optional audit log.
+//!
+//! When the crate is built with debug assertions or the `rng-audit` feature and the
+//! `CROSSY_RNG_AUDIT` environment variable is set, every draw is recorded together with the
+//! purpose of the rng and the context it was drawn in (the generated row or the tick). The log
+//! of a run is written to the directory `CROSSY_RNG_AUDIT` points to, so diffing the logs of two
+//! runs with the same seed shows the first draw where they diverged.
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Environment variable enabling the audit log and naming the directory it is written to.
+pub const AUDIT_ENV: &str = "CROSSY_RNG_AUDIT";
+
+#[derive(Debug, Clone)]
+pub struct GameRng {
+    inner: StdRng,
+    /// What this rng is used for, e.g. `row`.
+    purpose: &'static str,
+    /// Where the next draws happen, e.g. the y coordinate of a row that is generated.
+    context: u64,
+    #[cfg(any(debug_assertions, feature = "rng-audit"))]
+    audit: Option<Vec<String>>,
+    /// The seed and the number of 32 bit words drawn since, which is all a deserialized rng needs
+    /// to continue where this one is.
+    #[cfg(feature = "serde")]
+    seed: u64,
+    #[cfg(feature = "serde")]
+    words: u64,
+}
+
+impl GameRng {
+    pub fn new(seed: u64, purpose: &'static str) -> Self {
+        GameRng {
+            inner: StdRng::seed_from_u64(seed),
+            purpose,
+            context: 0,
+            #[cfg(any(debug_assertions, feature = "rng-audit"))]
+            audit: std::env::var_os(AUDIT_ENV).map(|_| Vec::new()),
+            #[cfg(feature = "serde")]
+            seed,
+            #[cfg(feature = "serde")]
+            words: 0,
+        }
+    }
+
+    pub fn purpose(&self) -> &'static str {
+        self.purpose
+    }
+
+    pub fn set_context(&mut self, context: u64) {
+        self.context = context;
+    }
+
+    /// The recorded draws, one per line, or [None] when auditing is disabled.
+    pub fn audit_log(&self) -> Option<String> {
+        #[cfg(any(debug_assertions, feature = "rng-audit"))]
+        if let Some(audit) = &self.audit {
+            return Some(audit.join("\n"));
+        }
+        None
+    }
+
+    #[cfg_attr(not(any(debug_assertions, feature = "rng-audit")), allow(unused_variables))]
+    fn record(&mut self, kind: &str, value: String) {
+        #[cfg(any(debug_assertions, feature = "rng-audit"))]
+        if let Some(audit) = &mut self.audit {
+            audit.push(format!("{} #{} @{} {kind} {value}", self.purpose, audit.len(), self.context));
+        }
+    }
+}
+
+impl RngCore for GameRng {
+    fn next_u32(&mut self) -> u32 {
+        let value = self.inner.next_u32();
+        #[cfg(feature = "serde")]
+        { self.words += 1; }
+        self.record("u32", format!("{value:08x}"));
+        value
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let value = self.inner.next_u64();
+        #[cfg(feature = "serde")]
+        { self.words += 2; }
+        self.record("u64", format!("{value:016x}"));
+        value
+    }
+
+    fn fill_bytes(&mut self, dst: &mut [u8]) {
+        self.inner.fill_bytes(dst);
+        #[cfg(feature = "serde")]
+        { self.words += dst.len().div_ceil(4) as u64; }
+        let bytes = dst.iter().map(|b| format!("{b:02x}")).collect();
+        self.record("bytes", bytes);
+    }
+}
+
+/// Rngs are serialized as their seed and the number of words drawn from them. Deserializing
+/// reseeds and draws as many words again, which lines up because [StdRng] hands out 32 bit words
+/// from a block in order, two for every `u64` and one for every started 4 bytes.
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct RngState {
+    seed: u64,
+    purpose: String,
+    context: u64,
+    words: u64,
+}
+
+/// The purposes rngs are created with.
+#[cfg(feature = "serde")]
+const PURPOSES: [&str; 2] = ["row", "tick"];
+
+#[cfg(feature = "serde")]
+impl Serialize for GameRng {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        RngState { seed: self.seed, purpose: self.purpose.to_string(), context: self.context, words: self.words }
+            .serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for GameRng {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let state = RngState::deserialize(deserializer)?;
+        let purpose = PURPOSES.into_iter().find(|purpose| *purpose == state.purpose)
+            .ok_or_else(|| serde::de::Error::unknown_variant(&state.purpose, &PURPOSES))?;
+        let mut rng = GameRng::new(state.seed, purpose);
+        rng.context = state.context;
+        for _ in 0..state.words {
+            rng.inner.next_u32();
+        }
+        rng.words = state.words;
+        Ok(rng)
+    }
+}
+
+/// Writes the audit logs of `rngs` into the audit directory, if auditing is enabled.
+///
+/// The file is named after the seed and the current time, so multiple runs of the same seed can
+/// be compared.
+pub fn write_audit_log(seed: u64, rngs: &[&GameRng]) -> std::io::Result<()> {
+    let Some(dir) = std::env::var_os(AUDIT_ENV) else { return Ok(()) };
+    let logs = rngs.iter().filter_map(|rng| rng.audit_log()).collect::<Vec<_>>();
+    if logs.is_empty() {
+        return Ok(());
+    }
+    let millis = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    let dir = std::path::PathBuf::from(dir);
+    std::fs::create_dir_all(&dir)?;
+    std::fs::write(dir.join(format!("rng-{seed}-{millis}.log")), logs.join("\n") + "\n")
+}
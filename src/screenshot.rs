@@ -0,0 +1,24 @@
+//! Saves what is on screen, for bug reports and bragging.
+//!
+//! Every screenshot is written twice into [SCREENSHOT_DIR] inside the data directory: as plain
+//! text (`.txt`) and with ANSI colors (`.ans`) to `cat` into a terminal.
+use std::path::PathBuf;
+use crate::grid::Grid;
+use crate::profile::data_dir;
+
+pub const SCREENSHOT_DIR: &str = "screenshots";
+
+/// Writes `grid` into files named after the current time and returns the path of the plain
+/// text one.
+pub fn save(grid: &Grid) -> std::io::Result<PathBuf> {
+    let millis = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    let dir = data_dir().join(SCREENSHOT_DIR);
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("screenshot-{millis}.txt"));
+    std::fs::write(&path, grid.to_plain() + "\n")?;
+    std::fs::write(path.with_extension("ans"), grid.to_ansi().replace("\r\n", "\n") + "\n")?;
+    Ok(path)
+}
@@ -0,0 +1,149 @@
+//! Runtime defined stripes and score rules written in [rhai](https://rhai.rs).
+//!
+//! Every `*.rhai` file in the script directory is loaded on startup. A script that defines
+//! `collides(tick, x)` becomes a new stripe type, optionally shaped by:
+//! - `weight()`: generation weight compared to the builtin stripes (green 5, rail 3, road 5),
+//!   defaults to 3
+//! - `block(tick, x)`: color name of the tile at `x`, one of `green`, `bright_green`, `white`,
+//!   `gray`, `dark_yellow`, `red` and `black`, defaults to `gray`
+//!
+//! A script that defines `score_row(score, y)` replaces the score gained for crossing a row.
+//! When multiple scripts define it, the first one in alphabetical order wins.
+use std::fmt;
+use std::path::Path;
+use std::sync::OnceLock;
+use rhai::{Engine, Scope, AST};
+use crate::stripe::{Block, StripeRender, STRIPE_LENGTH};
+
+static SCRIPTS: OnceLock<Scripts> = OnceLock::new();
+
+struct Scripts {
+    engine: Engine,
+    stripes: Vec<AST>,
+    score_rule: Option<AST>,
+}
+
+#[derive(Debug)]
+pub enum ScriptError {
+    Io(std::io::Error),
+    Parse(String, rhai::ParseError),
+    AlreadyLoaded,
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScriptError::Io(err) => write!(f, "Can't read scripts: {err}"),
+            ScriptError::Parse(file, err) => write!(f, "Can't parse {file}: {err}"),
+            ScriptError::AlreadyLoaded => write!(f, "Scripts are already loaded"),
+        }
+    }
+}
+
+impl std::error::Error for ScriptError {}
+
+/// Loads all scripts in `dir`. Can only be called once per process.
+///
+/// Returns the amount of scripts loaded.
+pub fn load(dir: &Path) -> Result<usize, ScriptError> {
+    let engine = Engine::new();
+    let mut paths = std::fs::read_dir(dir)
+        .map_err(ScriptError::Io)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "rhai"))
+        .collect::<Vec<_>>();
+    paths.sort();
+
+    let mut scripts = Scripts { engine, stripes: Vec::new(), score_rule: None };
+    for path in &paths {
+        let source = std::fs::read_to_string(path).map_err(ScriptError::Io)?;
+        let ast = scripts.engine.compile(source)
+            .map_err(|err| ScriptError::Parse(path.display().to_string(), err))?;
+        if scripts.score_rule.is_none() && defines(&ast, "score_row") {
+            scripts.score_rule = Some(ast.clone());
+        }
+        if defines(&ast, "collides") {
+            scripts.stripes.push(ast);
+        }
+    }
+
+    SCRIPTS.set(scripts).map_err(|_| ScriptError::AlreadyLoaded)?;
+    Ok(paths.len())
+}
+
+fn defines(ast: &AST, name: &str) -> bool {
+    ast.iter_functions().any(|f| f.name == name)
+}
+
+/// Calls `name` in `ast`, falling back to `default` if the script doesn't define it or fails.
+fn call<T: Clone + Send + Sync + 'static>(ast: &AST, name: &str, args: impl rhai::FuncArgs, default: T) -> T {
+    let Some(scripts) = SCRIPTS.get() else { return default };
+    if !defines(ast, name) {
+        return default;
+    }
+    scripts.engine.call_fn(&mut Scope::new(), ast, name, args).unwrap_or(default)
+}
+
+/// Generation weights of all scripted stripes, indexed like [ScriptedStripe::new].
+pub fn stripe_weights() -> Vec<i64> {
+    match SCRIPTS.get() {
+        Some(scripts) => scripts.stripes.iter()
+            .map(|ast| call(ast, "weight", (), 3_i64).max(0))
+            .collect(),
+        None => Vec::new(),
+    }
+}
+
+/// The score after crossing row `y`, or [None] if no script defines a score rule.
+pub fn score_row(score: u64, y: u64) -> Option<u64> {
+    let ast = SCRIPTS.get()?.score_rule.as_ref()?;
+    let new_score = call(ast, "score_row", (score as i64, y as i64), score as i64 + 1);
+    Some(new_score.max(0) as u64)
+}
+
+/// A stripe whose behavior is defined by a loaded script.
+#[derive(Debug, Copy, Clone)]
+pub struct ScriptedStripe {
+    /// Index into the loaded stripe scripts.
+    script: usize,
+    tick: u64,
+}
+
+impl ScriptedStripe {
+    pub fn new(script: usize) -> Self {
+        ScriptedStripe { script, tick: 0 }
+    }
+
+    /// The script and tick of the stripe, for [crate::stripe::Stripe::debug_state].
+    pub(crate) fn debug_state(&self) -> String {
+        format!("script {} tick {}", self.script, self.tick)
+    }
+
+    fn ast(&self) -> Option<&'static AST> {
+        SCRIPTS.get()?.stripes.get(self.script)
+    }
+
+    pub(crate) fn fingerprint(&self) -> Vec<u8> {
+        [(self.script as u64).to_le_bytes(), self.tick.to_le_bytes()].concat()
+    }
+
+    pub fn update(&mut self) {
+        self.tick += 1;
+    }
+
+    pub fn collides(&self, x: u8) -> bool {
+        self.ast().is_some_and(|ast| call(ast, "collides", (self.tick as i64, x as i64), false))
+    }
+
+    pub fn visualize(&self) -> StripeRender {
+        let blocks: [Block; STRIPE_LENGTH] = core::array::from_fn(|x| {
+            let name = match self.ast() {
+                Some(ast) => call(ast, "block", (self.tick as i64, x as i64), String::new()),
+                None => String::new(),
+            };
+            Block::from_name(&name).unwrap_or(Block::Gray)
+        });
+        StripeRender::new(blocks, None)
+    }
+}
+
@@ -0,0 +1,248 @@
+//! Short codes to challenge friends to the exact same run.
+//!
+//! A code packs the seed, the difficulty and the modifiers of a generated board into one number
+//! written in base 36. From the least significant bit:
+//!
+//! | bits | content                                   |
+//! |------|-------------------------------------------|
+//! | 4    | [SEED_CODE_VERSION]                       |
+//! | 3    | difficulty, index into [Difficulty::ALL]  |
+//! | 2    | weather, index into [Weather::ALL]        |
+//! | 1    | whether two players play                  |
+//! | 8    | lives, 0 without lives                    |
+//! | 20   | steps between automatic scrolls, 0 if off |
+//! | 64   | seed                                      |
+//! | 8    | checksum of everything below              |
+//!
+//! The checksum catches most typos, which would otherwise start a different run.
+use std::fmt;
+use crate::character::Character;
+use crate::difficulty::Difficulty;
+use crate::hash::hash_bytes;
+use crate::map::MapState;
+use crate::weather::Weather;
+
+/// Changes whenever the layout of codes changes, so old codes are rejected instead of starting a
+/// different run.
+pub const SEED_CODE_VERSION: u8 = 3;
+
+/// Bits below the checksum.
+const PAYLOAD_BITS: u32 = 102;
+
+const DIGITS: &[u8; 36] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SeedCode {
+    pub seed: u64,
+    pub difficulty: Difficulty,
+    pub weather: Weather,
+    pub coop: bool,
+    pub lives: Option<u8>,
+    /// Steps between automatic scrolls, see [MapState::with_auto_scroll].
+    pub auto_scroll: Option<u32>,
+}
+
+#[derive(Debug)]
+pub enum SeedCodeError {
+    InvalidCharacter(char),
+    TooLong,
+    /// The code was made by a version of the game that packs codes differently.
+    UnknownVersion(u8),
+    /// The difficulty or weather index is out of range.
+    Invalid,
+    /// The checksum doesn't match, the code was probably mistyped.
+    Checksum,
+}
+
+impl fmt::Display for SeedCodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SeedCodeError::InvalidCharacter(c) => write!(f, "Invalid character '{c}' in seed code"),
+            SeedCodeError::TooLong => write!(f, "Seed code is too long"),
+            SeedCodeError::UnknownVersion(version) => write!(f, "Seed code is from an incompatible version ({version})"),
+            SeedCodeError::Invalid => write!(f, "Invalid seed code"),
+            SeedCodeError::Checksum => write!(f, "Seed code is mistyped"),
+        }
+    }
+}
+
+impl std::error::Error for SeedCodeError {}
+
+impl SeedCode {
+    /// The code of the run starting on `map`, [None] for levels, which have no seed, and for
+    /// adapted difficulties. Modifiers that don't fit into a code are left out.
+    pub fn of(map: &MapState) -> Option<SeedCode> {
+        if map.is_level() || !Difficulty::ALL.contains(&map.difficulty()) {
+            return None;
+        }
+        Some(SeedCode {
+            seed: map.seed(),
+            difficulty: map.difficulty(),
+            weather: map.weather(),
+            coop: map.player_count() > 1,
+            lives: map.lives().map(|lives| lives.min(u8::MAX as u32) as u8),
+            auto_scroll: map.auto_scroll().map(|steps| steps.min(0xfffff) as u32),
+        })
+    }
+
+    pub fn parse(code: &str) -> Result<SeedCode, SeedCodeError> {
+        let mut value: u128 = 0;
+        for c in code.trim().chars() {
+            let digit = c.to_digit(36).ok_or(SeedCodeError::InvalidCharacter(c))?;
+            value = value.checked_mul(36).and_then(|value| value.checked_add(digit as u128))
+                .ok_or(SeedCodeError::TooLong)?;
+        }
+        let checksum = value >> PAYLOAD_BITS;
+        if checksum > u8::MAX as u128 {
+            return Err(SeedCodeError::TooLong);
+        }
+        let payload = value & ((1 << PAYLOAD_BITS) - 1);
+        let mut value = payload;
+        let mut bits = |count: u32| {
+            let taken = value & ((1 << count) - 1);
+            value >>= count;
+            taken
+        };
+        let version = bits(4) as u8;
+        if version != SEED_CODE_VERSION {
+            return Err(SeedCodeError::UnknownVersion(version));
+        }
+        if checksum != checksum_of(payload) {
+            return Err(SeedCodeError::Checksum);
+        }
+        let difficulty = *Difficulty::ALL.get(bits(3) as usize).ok_or(SeedCodeError::Invalid)?;
+        let weather = *Weather::ALL.get(bits(2) as usize).ok_or(SeedCodeError::Invalid)?;
+        let coop = bits(1) == 1;
+        let lives = Some(bits(8) as u8).filter(|lives| *lives > 0);
+        let auto_scroll = Some(bits(20) as u32).filter(|steps| *steps > 0);
+        let seed = bits(64) as u64;
+        Ok(SeedCode { seed, difficulty, weather, coop, lives, auto_scroll })
+    }
+
+    /// Adds the modifiers of the code to `map`, which has to be generated from its seed and at
+    /// its difficulty. A second player crosses as `partner`.
+    pub fn apply(&self, mut map: MapState, partner: Character) -> MapState {
+        map = map.with_weather(self.weather);
+        if self.coop {
+            map = map.with_second_player(partner);
+        }
+        if let Some(lives) = self.lives {
+            map = map.with_lives(lives as u32);
+        }
+        if let Some(steps) = self.auto_scroll {
+            map = map.with_auto_scroll(steps as u64);
+        }
+        map
+    }
+
+    fn to_bits(self) -> u128 {
+        let difficulty = Difficulty::ALL.iter().position(|difficulty| *difficulty == self.difficulty).unwrap_or_default();
+        let weather = Weather::ALL.iter().position(|weather| *weather == self.weather).unwrap_or_default();
+        let payload = [
+            (self.seed as u128, 64),
+            (self.auto_scroll.unwrap_or_default() as u128, 20),
+            (self.lives.unwrap_or_default() as u128, 8),
+            (self.coop as u128, 1),
+            (weather as u128, 2),
+            (difficulty as u128, 3),
+            (SEED_CODE_VERSION as u128, 4),
+        ].into_iter().fold(0, |bits, (value, count)| (bits << count) | value);
+        checksum_of(payload) << PAYLOAD_BITS | payload
+    }
+}
+
+/// The checksum stored above `payload`.
+fn checksum_of(payload: u128) -> u128 {
+    (hash_bytes(payload.to_le_bytes()).0 & 0xff) as u128
+}
+
+impl fmt::Display for SeedCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut value = self.to_bits();
+        let mut digits = Vec::new();
+        while value > 0 {
+            digits.push(DIGITS[(value % 36) as usize]);
+            value /= 36;
+        }
+        digits.reverse();
+        write!(f, "{}", String::from_utf8_lossy(&digits))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn codes() -> Vec<SeedCode> {
+        let mut codes = Vec::new();
+        for (idx, difficulty) in Difficulty::ALL.into_iter().enumerate() {
+            for weather in Weather::ALL {
+                codes.push(SeedCode {
+                    seed: [0, 1, 0xdead_beef, u64::MAX][idx % 4],
+                    difficulty,
+                    weather,
+                    coop: idx % 2 == 0,
+                    lives: Some(idx as u8 + 1).filter(|_| weather != Weather::Clear),
+                    auto_scroll: Some(0xfffff).filter(|_| idx % 2 == 1),
+                });
+            }
+        }
+        codes
+    }
+
+    fn code() -> String {
+        SeedCode { seed: 0x1234_5678_9abc_def0, difficulty: Difficulty::HARD, weather: Weather::Rain, coop: true, lives: Some(3), auto_scroll: None }.to_string()
+    }
+
+    #[test]
+    fn codes_parse_back() {
+        for code in codes() {
+            let text = code.to_string();
+            assert_eq!(SeedCode::parse(&text).unwrap(), code, "{text}");
+            assert_eq!(SeedCode::parse(&format!(" {} ", text.to_uppercase())).unwrap(), code, "{text}");
+        }
+    }
+
+    #[test]
+    fn codes_reproduce_their_run() {
+        for code in codes() {
+            let map = code.apply(MapState::with_difficulty(code.seed, code.difficulty), Character::default());
+            assert_eq!(SeedCode::of(&map), Some(code));
+            assert_eq!(map.board_hash(), MapState::with_difficulty(code.seed, code.difficulty).board_hash());
+        }
+    }
+
+    #[test]
+    fn mistyped_codes_fail_the_checksum() {
+        let code = code();
+        // The last digits hold the version, which is checked first.
+        for idx in 0..code.len() - 1 {
+            let mut typo = code.clone().into_bytes();
+            typo[idx] = if typo[idx] == b'7' { b'8' } else { b'7' };
+            let typo = String::from_utf8(typo).unwrap();
+            assert!(matches!(SeedCode::parse(&typo), Err(SeedCodeError::Checksum | SeedCodeError::UnknownVersion(_))), "{typo}");
+        }
+        let mut swapped = code.clone().into_bytes();
+        swapped.swap(3, 4);
+        assert!(matches!(SeedCode::parse(&String::from_utf8(swapped).unwrap()), Err(SeedCodeError::Checksum)));
+    }
+
+    #[test]
+    fn codes_of_the_wrong_length_are_rejected() {
+        let code = code();
+        assert!(matches!(SeedCode::parse(&format!("{code}0")), Err(SeedCodeError::TooLong)));
+        assert!(matches!(SeedCode::parse(&"z".repeat(40)), Err(SeedCodeError::TooLong)));
+        assert!(SeedCode::parse(&code[1..]).is_err());
+        assert!(SeedCode::parse(&code[..code.len() - 1]).is_err());
+        assert!(matches!(SeedCode::parse(""), Err(SeedCodeError::UnknownVersion(0))));
+    }
+
+    #[test]
+    fn codes_only_use_base_36() {
+        let code = code();
+        for c in ['-', '_', ' ', 'ä', '!'] {
+            let typo = format!("{}{c}{}", &code[..5], &code[5..]);
+            assert!(matches!(SeedCode::parse(&typo), Err(SeedCodeError::InvalidCharacter(found)) if found == c), "{typo}");
+        }
+    }
+}
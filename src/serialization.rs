@@ -0,0 +1,651 @@
+//! Serde support for the game types, behind the `serde` feature.
+//!
+//! The types derive `Serialize` and `Deserialize` where they are defined. Structs are serialized
+//! with their field names, fieldless enums as their variant names, which keeps e.g. JSON readable
+//! and lets fields be reordered without breaking saved games.
+//!
+//! [to_bytes] and [from_bytes] store values in a compact binary format for the saves of the game
+//! itself: integers as LEB128 varints (signed ones zigzag encoded), strings, sequences and maps
+//! prefixed with their length, variants by name and structs as their fields in order. It isn't
+//! self-describing, so fields can't be skipped or reordered.
+use std::fmt::Display;
+use serde::de::{self, DeserializeSeed, Visitor};
+use serde::ser::{self, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FormatError {
+    /// Raised by the implementation of a type, e.g. for a scripted stripe.
+    Custom(String),
+    /// The data ended in the middle of a value.
+    UnexpectedEnd,
+    /// A value the format has no encoding for, e.g. a sequence of unknown length.
+    Unsupported(&'static str),
+    /// Bytes left over after the value.
+    TrailingBytes,
+}
+
+impl Display for FormatError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FormatError::Custom(msg) => write!(f, "{msg}"),
+            FormatError::UnexpectedEnd => write!(f, "unexpected end of data"),
+            FormatError::Unsupported(what) => write!(f, "{what} can't be stored"),
+            FormatError::TrailingBytes => write!(f, "unexpected data after the end"),
+        }
+    }
+}
+
+impl std::error::Error for FormatError {}
+
+impl ser::Error for FormatError {
+    fn custom<T: Display>(msg: T) -> Self {
+        FormatError::Custom(msg.to_string())
+    }
+}
+
+impl de::Error for FormatError {
+    fn custom<T: Display>(msg: T) -> Self {
+        FormatError::Custom(msg.to_string())
+    }
+}
+
+/// Encodes `value` in the binary format.
+pub(crate) fn to_bytes<T: Serialize>(value: &T) -> Result<Vec<u8>, FormatError> {
+    let mut encoder = Encoder { out: Vec::new() };
+    value.serialize(&mut encoder)?;
+    Ok(encoder.out)
+}
+
+/// Decodes a value from `bytes` in the binary format, which must hold nothing else.
+pub(crate) fn from_bytes<'de, T: de::Deserialize<'de>>(bytes: &'de [u8]) -> Result<T, FormatError> {
+    let mut decoder = Decoder { bytes };
+    let value = T::deserialize(&mut decoder)?;
+    if decoder.bytes.is_empty() {
+        Ok(value)
+    } else {
+        Err(FormatError::TrailingBytes)
+    }
+}
+
+struct Encoder {
+    out: Vec<u8>,
+}
+
+impl Encoder {
+    fn varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.out.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        self.out.push(value as u8);
+    }
+
+    fn signed(&mut self, value: i64) {
+        self.varint(((value << 1) ^ (value >> 63)) as u64);
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.varint(bytes.len() as u64);
+        self.out.extend_from_slice(bytes);
+    }
+}
+
+impl ser::Serializer for &mut Encoder {
+    type Ok = ();
+    type Error = FormatError;
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Self;
+    type SerializeMap = Self;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Self;
+
+    fn serialize_bool(self, v: bool) -> Result<(), FormatError> {
+        self.out.push(v as u8);
+        Ok(())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<(), FormatError> {
+        self.signed(v as i64);
+        Ok(())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<(), FormatError> {
+        self.signed(v as i64);
+        Ok(())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<(), FormatError> {
+        self.signed(v as i64);
+        Ok(())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<(), FormatError> {
+        self.signed(v);
+        Ok(())
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<(), FormatError> {
+        self.varint(v as u64);
+        Ok(())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<(), FormatError> {
+        self.varint(v as u64);
+        Ok(())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<(), FormatError> {
+        self.varint(v as u64);
+        Ok(())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<(), FormatError> {
+        self.varint(v);
+        Ok(())
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<(), FormatError> {
+        self.out.extend_from_slice(&v.to_le_bytes());
+        Ok(())
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<(), FormatError> {
+        self.out.extend_from_slice(&v.to_le_bytes());
+        Ok(())
+    }
+
+    fn serialize_char(self, v: char) -> Result<(), FormatError> {
+        self.varint(v as u64);
+        Ok(())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<(), FormatError> {
+        self.bytes(v.as_bytes());
+        Ok(())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), FormatError> {
+        self.bytes(v);
+        Ok(())
+    }
+
+    fn serialize_none(self) -> Result<(), FormatError> {
+        self.out.push(0);
+        Ok(())
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<(), FormatError> {
+        self.out.push(1);
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), FormatError> {
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), FormatError> {
+        Ok(())
+    }
+
+    fn serialize_unit_variant(self, _name: &'static str, _idx: u32, variant: &'static str) -> Result<(), FormatError> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(self, _name: &'static str, value: &T) -> Result<(), FormatError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(self, _name: &'static str, _idx: u32, variant: &'static str, value: &T) -> Result<(), FormatError> {
+        self.bytes(variant.as_bytes());
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self, FormatError> {
+        let len = len.ok_or(FormatError::Unsupported("sequences of unknown length"))?;
+        self.varint(len as u64);
+        Ok(self)
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self, FormatError> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Self, FormatError> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_variant(self, _name: &'static str, _idx: u32, variant: &'static str, _len: usize) -> Result<Self, FormatError> {
+        self.bytes(variant.as_bytes());
+        Ok(self)
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Self, FormatError> {
+        let len = len.ok_or(FormatError::Unsupported("maps of unknown length"))?;
+        self.varint(len as u64);
+        Ok(self)
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self, FormatError> {
+        Ok(self)
+    }
+
+    fn serialize_struct_variant(self, _name: &'static str, _idx: u32, variant: &'static str, _len: usize) -> Result<Self, FormatError> {
+        self.bytes(variant.as_bytes());
+        Ok(self)
+    }
+}
+
+impl ser::SerializeSeq for &mut Encoder {
+    type Ok = ();
+    type Error = FormatError;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), FormatError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), FormatError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTuple for &mut Encoder {
+    type Ok = ();
+    type Error = FormatError;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), FormatError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), FormatError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTupleStruct for &mut Encoder {
+    type Ok = ();
+    type Error = FormatError;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), FormatError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), FormatError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTupleVariant for &mut Encoder {
+    type Ok = ();
+    type Error = FormatError;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), FormatError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), FormatError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeMap for &mut Encoder {
+    type Ok = ();
+    type Error = FormatError;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<(), FormatError> {
+        key.serialize(&mut **self)
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), FormatError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), FormatError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeStruct for &mut Encoder {
+    type Ok = ();
+    type Error = FormatError;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, _key: &'static str, value: &T) -> Result<(), FormatError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), FormatError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeStructVariant for &mut Encoder {
+    type Ok = ();
+    type Error = FormatError;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, _key: &'static str, value: &T) -> Result<(), FormatError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), FormatError> {
+        Ok(())
+    }
+}
+
+struct Decoder<'de> {
+    bytes: &'de [u8],
+}
+
+impl<'de> Decoder<'de> {
+    fn take(&mut self, len: usize) -> Result<&'de [u8], FormatError> {
+        if self.bytes.len() < len {
+            return Err(FormatError::UnexpectedEnd);
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn byte(&mut self) -> Result<u8, FormatError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn varint(&mut self) -> Result<u64, FormatError> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(FormatError::Custom("varint too long".to_string()))
+    }
+
+    fn signed(&mut self) -> Result<i64, FormatError> {
+        let value = self.varint()?;
+        Ok((value >> 1) as i64 ^ -((value & 1) as i64))
+    }
+
+    /// A varint that has to fit into `T`.
+    fn unsigned<T: TryFrom<u64>>(&mut self) -> Result<T, FormatError> {
+        T::try_from(self.varint()?).map_err(|_| FormatError::Custom("integer out of range".to_string()))
+    }
+
+    fn signed_as<T: TryFrom<i64>>(&mut self) -> Result<T, FormatError> {
+        T::try_from(self.signed()?).map_err(|_| FormatError::Custom("integer out of range".to_string()))
+    }
+
+    fn bytes(&mut self) -> Result<&'de [u8], FormatError> {
+        let len = self.unsigned()?;
+        self.take(len)
+    }
+
+    fn str(&mut self) -> Result<&'de str, FormatError> {
+        std::str::from_utf8(self.bytes()?).map_err(|err| FormatError::Custom(err.to_string()))
+    }
+}
+
+impl<'de> de::Deserializer<'de> for &mut Decoder<'de> {
+    type Error = FormatError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, FormatError> {
+        Err(FormatError::Unsupported("values of unknown type"))
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, FormatError> {
+        visitor.visit_bool(self.byte()? != 0)
+    }
+
+    fn deserialize_i8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, FormatError> {
+        visitor.visit_i8(self.signed_as()?)
+    }
+
+    fn deserialize_i16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, FormatError> {
+        visitor.visit_i16(self.signed_as()?)
+    }
+
+    fn deserialize_i32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, FormatError> {
+        visitor.visit_i32(self.signed_as()?)
+    }
+
+    fn deserialize_i64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, FormatError> {
+        visitor.visit_i64(self.signed()?)
+    }
+
+    fn deserialize_u8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, FormatError> {
+        visitor.visit_u8(self.unsigned()?)
+    }
+
+    fn deserialize_u16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, FormatError> {
+        visitor.visit_u16(self.unsigned()?)
+    }
+
+    fn deserialize_u32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, FormatError> {
+        visitor.visit_u32(self.unsigned()?)
+    }
+
+    fn deserialize_u64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, FormatError> {
+        visitor.visit_u64(self.varint()?)
+    }
+
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, FormatError> {
+        let bytes = self.take(4)?.try_into().map_err(|_| FormatError::UnexpectedEnd)?;
+        visitor.visit_f32(f32::from_le_bytes(bytes))
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, FormatError> {
+        let bytes = self.take(8)?.try_into().map_err(|_| FormatError::UnexpectedEnd)?;
+        visitor.visit_f64(f64::from_le_bytes(bytes))
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, FormatError> {
+        let char = char::from_u32(self.unsigned()?).ok_or_else(|| FormatError::Custom("invalid char".to_string()))?;
+        visitor.visit_char(char)
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, FormatError> {
+        visitor.visit_borrowed_str(self.str()?)
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, FormatError> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, FormatError> {
+        visitor.visit_borrowed_bytes(self.bytes()?)
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, FormatError> {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, FormatError> {
+        match self.byte()? {
+            0 => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, FormatError> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, FormatError> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, FormatError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, FormatError> {
+        let len = self.unsigned()?;
+        visitor.visit_seq(Elements { decoder: self, left: len })
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, FormatError> {
+        visitor.visit_seq(Elements { decoder: self, left: len })
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(self, _name: &'static str, len: usize, visitor: V) -> Result<V::Value, FormatError> {
+        self.deserialize_tuple(len, visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, FormatError> {
+        let len = self.unsigned()?;
+        visitor.visit_map(Elements { decoder: self, left: len })
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(self, _name: &'static str, fields: &'static [&'static str], visitor: V) -> Result<V::Value, FormatError> {
+        self.deserialize_tuple(fields.len(), visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(self, _name: &'static str, _variants: &'static [&'static str], visitor: V) -> Result<V::Value, FormatError> {
+        visitor.visit_enum(self)
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, FormatError> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, FormatError> {
+        Err(FormatError::Unsupported("skipped values"))
+    }
+}
+
+/// The elements of a sequence, tuple or struct, or the entries of a map.
+struct Elements<'a, 'de> {
+    decoder: &'a mut Decoder<'de>,
+    left: usize,
+}
+
+impl<'de> de::SeqAccess<'de> for Elements<'_, 'de> {
+    type Error = FormatError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>, FormatError> {
+        if self.left == 0 {
+            return Ok(None);
+        }
+        self.left -= 1;
+        seed.deserialize(&mut *self.decoder).map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.left)
+    }
+}
+
+impl<'de> de::MapAccess<'de> for Elements<'_, 'de> {
+    type Error = FormatError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, FormatError> {
+        if self.left == 0 {
+            return Ok(None);
+        }
+        self.left -= 1;
+        seed.deserialize(&mut *self.decoder).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, FormatError> {
+        seed.deserialize(&mut *self.decoder)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.left)
+    }
+}
+
+impl<'de> de::EnumAccess<'de> for &mut Decoder<'de> {
+    type Error = FormatError;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self), FormatError> {
+        let variant = seed.deserialize(&mut *self)?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for &mut Decoder<'de> {
+    type Error = FormatError;
+
+    fn unit_variant(self) -> Result<(), FormatError> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, FormatError> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, FormatError> {
+        de::Deserializer::deserialize_tuple(self, len, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(self, fields: &'static [&'static str], visitor: V) -> Result<V::Value, FormatError> {
+        de::Deserializer::deserialize_tuple(self, fields.len(), visitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::character::Character;
+    use crate::difficulty::Difficulty;
+    use crate::map::{Facing, MapState};
+    use crate::mode::GameMode;
+    use crate::weather::Weather;
+
+    /// Hops up every fourth tick and sideways in between now and then.
+    fn input(tick: u64) -> Option<Facing> {
+        match tick % 12 {
+            0 | 4 => Some(Facing::Up),
+            8 => Some(if tick % 24 == 8 { Facing::Left } else { Facing::Right }),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn saved_maps_play_on_like_the_original() {
+        let mut map = MapState::with_difficulty(42, Difficulty::NORMAL)
+            .with_weather(Weather::Snow)
+            .with_lives(3)
+            .with_second_player(Character::Frog);
+        for tick in 0..200 {
+            map.step(input(tick));
+        }
+        let bytes = to_bytes(&map).unwrap();
+        let mut loaded: MapState = from_bytes(&bytes).unwrap();
+        assert_eq!(to_bytes(&loaded).unwrap(), bytes);
+        assert_eq!(loaded.state_hash(), map.state_hash());
+        for tick in 200..400 {
+            map.step(input(tick));
+            loaded.step(input(tick));
+            assert_eq!(loaded.state_hash(), map.state_hash(), "diverged at tick {tick}");
+        }
+    }
+
+    #[test]
+    fn every_mode_survives_a_save() {
+        let modes = [
+            GameMode::Endless,
+            GameMode::TimeAttack { seconds: 120 },
+            GameMode::Daily { day: 20_377 },
+            GameMode::Level("first-steps".to_string()),
+            GameMode::Zen,
+            GameMode::Practice,
+            GameMode::Casual,
+            GameMode::Night,
+            GameMode::Tutorial,
+        ];
+        for mode in modes {
+            assert_eq!(from_bytes::<GameMode>(&to_bytes(&mode).unwrap()).unwrap(), mode);
+        }
+    }
+
+    #[test]
+    fn trailing_bytes_are_rejected() {
+        let mut bytes = to_bytes(&GameMode::Endless).unwrap();
+        bytes.push(0);
+        assert!(matches!(from_bytes::<GameMode>(&bytes), Err(FormatError::TrailingBytes)));
+    }
+}
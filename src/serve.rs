@@ -0,0 +1,300 @@
+//! Hosts games over SSH for everyone who connects, each in their own [App].
+//!
+//! Anyone can play with `ssh -p 7532 play@<host>`: the user [SERVE_USER] gets in without a
+//! password or key, all other users are turned away. Each shell is a fresh game at the size of
+//! the client's terminal, ended by quitting the game or with Ctrl+C or Ctrl+D. Sessions are
+//! guests, see [App::guest]. The server identifies itself with an Ed25519 host key generated on
+//! the first start and kept in [HOST_KEY_FILE] inside [data_dir], so clients can pin it.
+//!
+//! [session] plays a game reading what the client sends from a [Receiver] and drawing to any
+//! [Write], so the SSH side only connects a channel to both.
+use std::io::{ErrorKind, Write};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use crossterm::event::KeyCode;
+use crossterm::{cursor, terminal, QueueableCommand};
+use russh::keys::ssh_key::private::Ed25519Keypair;
+use russh::keys::ssh_key::LineEnding;
+use russh::keys::PrivateKey;
+use russh::server::{Auth, ChannelOpenHandle, Config, Handle, Handler, Msg, Server, Session};
+use russh::{Channel, ChannelId};
+use crate::app::{App, Event};
+use crate::diff::DiffRenderer;
+use crate::map::TICKS_PER_SECOND;
+use crate::profile::{data_dir, Profile};
+use crate::sound::Sound;
+
+/// Port `serve` listens on if none is given.
+pub const SERVE_PORT: u16 = 7532;
+
+/// The user everyone logs in as.
+pub const SERVE_USER: &str = "play";
+
+/// The private host key in the OpenSSH format.
+pub const HOST_KEY_FILE: &str = "serve_host_key";
+
+/// Terminal size of clients that don't request a pty.
+const DEFAULT_SIZE: (u16, u16) = (80, 24);
+
+/// How long to wait for input before checking whether a tick is due.
+const INPUT_POLL_INTERVAL: Duration = Duration::from_millis(15);
+
+/// Time between two ticks of the simulation.
+const TICK_INTERVAL: Duration = Duration::from_millis(1000 / TICKS_PER_SECOND);
+
+/// Connections without any input for this long are closed.
+const INACTIVITY_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+/// Bytes clients send to end their session, Ctrl+C and Ctrl+D.
+const HANG_UP: [u8; 2] = [0x03, 0x04];
+
+/// What the client of a [session] sent.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClientInput {
+    /// Raw bytes typed into the terminal, see [decode_keys].
+    Bytes(Vec<u8>),
+    Resize { width: u16, height: u16 },
+}
+
+/// Accepts SSH connections on `port` forever, playing each shell on its own thread.
+pub fn serve(port: u16) -> std::io::Result<()> {
+    let config = Config {
+        keys: vec![host_key()?],
+        inactivity_timeout: Some(INACTIVITY_TIMEOUT),
+        auth_rejection_time: Duration::from_secs(1),
+        // OpenSSH clients start with the none method, which is how players get in.
+        auth_rejection_time_initial: Some(Duration::ZERO),
+        nodelay: true,
+        ..Config::default()
+    };
+    let runtime = tokio::runtime::Runtime::new()?;
+    let mut server = GameServer { runtime: runtime.handle().clone() };
+    runtime.block_on(server.run_on_address(Arc::new(config), ("0.0.0.0", port)))
+}
+
+/// Plays a game reading keys from `input` and drawing frames of `width` by `height` cells to
+/// `output` until the player quits or the client goes away.
+pub fn session(input: Receiver<ClientInput>, output: &mut impl Write, width: u16, height: u16) -> std::io::Result<()> {
+    let mut app = App::guest(Profile::default());
+    app.handle_event(Event::Resize { width, height });
+    let mut renderer = DiffRenderer::new();
+    output.queue(cursor::Hide)?;
+    let mut last_update = Instant::now();
+    // Time that passed but wasn't simulated yet.
+    let mut lag = Duration::ZERO;
+    while !app.should_quit() {
+        match input.recv_timeout(INPUT_POLL_INTERVAL) {
+            Ok(ClientInput::Bytes(bytes)) if bytes.iter().any(|byte| HANG_UP.contains(byte)) => break,
+            Ok(ClientInput::Bytes(bytes)) => {
+                for key in decode_keys(&bytes) {
+                    app.handle_event(Event::Key(key));
+                }
+            }
+            Ok(ClientInput::Resize { width, height }) => {
+                output.queue(terminal::Clear(terminal::ClearType::All))?;
+                renderer.invalidate();
+                app.handle_event(Event::Resize { width, height });
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return Ok(()),
+        }
+
+        let now = Instant::now();
+        lag += now - last_update;
+        last_update = now;
+        while lag >= TICK_INTERVAL {
+            app.handle_event(Event::Tick);
+            lag -= TICK_INTERVAL;
+        }
+
+        if app.take_sounds().contains(&Sound::TrainHorn) && !app.profile().sound.muted {
+            write!(output, "\x07")?;
+        }
+        if app.take_dirty() {
+            renderer.draw(output, &app.view().grid)?;
+            output.flush()?;
+        }
+    }
+    output.queue(terminal::Clear(terminal::ClearType::All))?;
+    output.queue(cursor::MoveTo(0, 0))?;
+    output.queue(cursor::Show)?;
+    output.flush()
+}
+
+/// The keys in the raw bytes a terminal sends. Arrow keys arrive as `ESC [ A` to `ESC [ D`, or
+/// with `O` instead of `[` in application mode.
+pub fn decode_keys(bytes: &[u8]) -> Vec<KeyCode> {
+    let text = String::from_utf8_lossy(bytes);
+    let mut chars = text.chars().peekable();
+    let mut keys = Vec::new();
+    while let Some(c) = chars.next() {
+        let key = match c {
+            '\x1b' if chars.next_if(|c| *c == '[' || *c == 'O').is_some() => match chars.next() {
+                Some('A') => KeyCode::Up,
+                Some('B') => KeyCode::Down,
+                Some('C') => KeyCode::Right,
+                Some('D') => KeyCode::Left,
+                _ => continue,
+            },
+            '\x1b' => KeyCode::Esc,
+            // Some clients end lines with `\r\n` or `\r\0`.
+            '\r' => {
+                chars.next_if(|c| *c == '\n' || *c == '\0');
+                KeyCode::Enter
+            }
+            '\n' => KeyCode::Enter,
+            '\x7f' | '\x08' => KeyCode::Backspace,
+            c if c.is_control() => continue,
+            c => KeyCode::Char(c),
+        };
+        keys.push(key);
+    }
+    keys
+}
+
+/// The key the server identifies itself with, generated and saved on the first start.
+fn host_key() -> std::io::Result<PrivateKey> {
+    let path = data_dir().join(HOST_KEY_FILE);
+    match std::fs::read(&path) {
+        Ok(pem) => PrivateKey::from_openssh(pem).map_err(std::io::Error::other),
+        Err(err) if err.kind() == ErrorKind::NotFound => {
+            let key = PrivateKey::from(Ed25519Keypair::from_seed(&rand::random()));
+            let pem = key.to_openssh(LineEnding::LF).map_err(std::io::Error::other)?;
+            std::fs::create_dir_all(data_dir())?;
+            let mut options = std::fs::OpenOptions::new();
+            options.write(true).create_new(true);
+            // Only the user running the server may read it.
+            #[cfg(unix)]
+            std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+            options.open(&path)?.write_all(pem.as_bytes())?;
+            Ok(key)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+struct GameServer {
+    runtime: tokio::runtime::Handle,
+}
+
+impl Server for GameServer {
+    type Handler = Client;
+
+    fn new_client(&mut self, _peer: Option<std::net::SocketAddr>) -> Client {
+        Client { runtime: self.runtime.clone(), size: DEFAULT_SIZE, input: None }
+    }
+
+    #[cfg_attr(not(feature = "logging"), allow(unused_variables))]
+    fn handle_session_error(&mut self, err: russh::Error) {
+        #[cfg(feature = "logging")]
+        tracing::warn!("Session ended: {err}");
+    }
+}
+
+/// A connection, which plays at most one game.
+struct Client {
+    runtime: tokio::runtime::Handle,
+    /// The terminal size of the pty, until the game started.
+    size: (u16, u16),
+    /// Where what the client sends goes, once the game started.
+    input: Option<Sender<ClientInput>>,
+}
+
+impl Handler for Client {
+    type Error = russh::Error;
+
+    async fn auth_none(&mut self, user: &str) -> Result<Auth, Self::Error> {
+        Ok(if user == SERVE_USER { Auth::Accept } else { Auth::reject() })
+    }
+
+    async fn channel_open_session(&mut self, _channel: Channel<Msg>, reply: ChannelOpenHandle, _session: &mut Session) -> Result<(), Self::Error> {
+        if self.input.is_none() {
+            reply.accept().await;
+        }
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn pty_request(&mut self, channel: ChannelId, _term: &str, width: u32, height: u32, _pix_width: u32, _pix_height: u32, _modes: &[(russh::Pty, u32)], session: &mut Session) -> Result<(), Self::Error> {
+        // Clients that don't know the size of their terminal send zeroes.
+        if width > 0 && height > 0 {
+            self.size = (clamp_size(width), clamp_size(height));
+        }
+        session.channel_success(channel)
+    }
+
+    async fn window_change_request(&mut self, _channel: ChannelId, width: u32, height: u32, _pix_width: u32, _pix_height: u32, _session: &mut Session) -> Result<(), Self::Error> {
+        if let Some(input) = self.input.as_ref().filter(|_| width > 0 && height > 0) {
+            let _ = input.send(ClientInput::Resize { width: clamp_size(width), height: clamp_size(height) });
+        }
+        Ok(())
+    }
+
+    async fn shell_request(&mut self, channel: ChannelId, session: &mut Session) -> Result<(), Self::Error> {
+        let (sender, receiver) = mpsc::channel();
+        self.input = Some(sender);
+        let mut output = ChannelWriter { handle: session.handle(), channel, runtime: self.runtime.clone(), buffer: Vec::new() };
+        let (width, height) = self.size;
+        std::thread::spawn(move || {
+            #[cfg_attr(not(feature = "logging"), allow(unused_variables))]
+            let status = match self::session(receiver, &mut output, width, height) {
+                Ok(()) => 0,
+                Err(err) => {
+                    #[cfg(feature = "logging")]
+                    tracing::warn!("Game ended: {err}");
+                    1
+                }
+            };
+            output.close(status);
+        });
+        session.channel_success(channel)
+    }
+
+    async fn data(&mut self, _channel: ChannelId, data: &[u8], _session: &mut Session) -> Result<(), Self::Error> {
+        if let Some(input) = &self.input {
+            let _ = input.send(ClientInput::Bytes(data.to_vec()));
+        }
+        Ok(())
+    }
+}
+
+/// Sizes in cells beyond what a terminal can have are cut off.
+fn clamp_size(cells: u32) -> u16 {
+    cells.clamp(1, u16::MAX as u32) as u16
+}
+
+/// Sends what a game thread writes over an SSH channel, a frame at a time on every flush.
+struct ChannelWriter {
+    handle: Handle,
+    channel: ChannelId,
+    runtime: tokio::runtime::Handle,
+    buffer: Vec<u8>,
+}
+
+impl ChannelWriter {
+    /// Ends the channel with the exit `status` of the game, which closes the client's ssh.
+    fn close(self, status: u32) {
+        self.runtime.block_on(async {
+            let _ = self.handle.exit_status_request(self.channel, status).await;
+            let _ = self.handle.eof(self.channel).await;
+            let _ = self.handle.close(self.channel).await;
+        });
+    }
+}
+
+impl Write for ChannelWriter {
+    fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+        self.buffer.extend_from_slice(bytes);
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        let data = std::mem::take(&mut self.buffer);
+        self.runtime.block_on(self.handle.data(self.channel, data))
+            .map_err(|_| std::io::Error::from(ErrorKind::BrokenPipe))
+    }
+}
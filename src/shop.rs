@@ -0,0 +1,239 @@
+//! Coins and the cosmetics they buy.
+//!
+//! Runs that go into a high score table earn a coin for every [COIN_SCORE] points. The shop in
+//! the main menu sells [Skin]s, which color the tile of the first player, and [Trail]s, marks
+//! left on the tiles they hop away from. Neither changes how the game plays, so seed codes and
+//! replays don't know about them.
+//!
+//! The coins, the items bought and the ones worn are stored as `key = value` lines in `shop.conf`
+//! inside [profile_dir].
+use crate::color::{Color, ColorDepth};
+use crate::grid::Grid;
+use crate::locale::{Language, Text};
+use crate::profile::{format_entries, parse_entries, profile_dir};
+use crate::stripe::{Block, TILE_WIDTH};
+use crate::theme::{Glyphs, Theme};
+
+const SHOP_FILE: &str = "shop.conf";
+
+/// Points per coin earned.
+pub const COIN_SCORE: u64 = 10;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Skin {
+    Gold,
+    Midnight,
+    Candy,
+    Mint,
+}
+
+impl Skin {
+    pub const ALL: [Skin; 4] = [Skin::Gold, Skin::Midnight, Skin::Candy, Skin::Mint];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Skin::Gold => "gold",
+            Skin::Midnight => "midnight",
+            Skin::Candy => "candy",
+            Skin::Mint => "mint",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|skin| skin.name() == name)
+    }
+
+    pub fn color(self) -> Color {
+        match self {
+            Skin::Gold => Color::Rgb { r: 255, g: 200, b: 40 },
+            Skin::Midnight => Color::Rgb { r: 70, g: 90, b: 200 },
+            Skin::Candy => Color::Rgb { r: 255, g: 120, b: 200 },
+            Skin::Mint => Color::Rgb { r: 120, g: 230, b: 180 },
+        }
+    }
+
+    /// Colors the player tile on `line` of `board` starting at column `start` with the skin, where
+    /// it is drawn in the white of `theme`. Row `y` is the one on that line.
+    pub fn paint(self, board: &mut Grid, theme: &Theme, line: usize, start: usize, y: u64) {
+        let white = theme.color_at(Block::White, y);
+        let color = ColorDepth::current().adapt(self.color());
+        board.map_cells(|x, cell_line, mut cell, _| {
+            if cell_line == line && (start..start + TILE_WIDTH).contains(&x) {
+                if cell.fg == white {
+                    cell.fg = color;
+                }
+                if cell.bg == white {
+                    cell.bg = color;
+                }
+            }
+            cell
+        });
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Trail {
+    Footprints,
+    Bubbles,
+    Sparkles,
+}
+
+impl Trail {
+    pub const ALL: [Trail; 3] = [Trail::Footprints, Trail::Bubbles, Trail::Sparkles];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Trail::Footprints => "footprints",
+            Trail::Bubbles => "bubbles",
+            Trail::Sparkles => "sparkles",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|trail| trail.name() == name)
+    }
+
+    pub fn glyph(self, glyphs: Glyphs) -> char {
+        match (self, glyphs) {
+            (Trail::Footprints, Glyphs::Ascii) => '.',
+            (Trail::Bubbles, Glyphs::Ascii) => 'o',
+            (Trail::Sparkles, Glyphs::Ascii) => '*',
+            (Trail::Footprints, _) => '·',
+            (Trail::Bubbles, _) => '∘',
+            (Trail::Sparkles, _) => '✦',
+        }
+    }
+
+    pub fn color(self) -> Color {
+        match self {
+            Trail::Footprints => Color::Rgb { r: 90, g: 70, b: 50 },
+            Trail::Bubbles => Color::Rgb { r: 120, g: 200, b: 255 },
+            Trail::Sparkles => Color::Rgb { r: 255, g: 230, b: 90 },
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Item {
+    Skin(Skin),
+    Trail(Trail),
+}
+
+impl Item {
+    pub const ALL: [Item; 7] = [
+        Item::Skin(Skin::Gold),
+        Item::Skin(Skin::Midnight),
+        Item::Skin(Skin::Candy),
+        Item::Skin(Skin::Mint),
+        Item::Trail(Trail::Footprints),
+        Item::Trail(Trail::Bubbles),
+        Item::Trail(Trail::Sparkles),
+    ];
+
+    /// Identifies the item in `shop.conf`, e.g. `skin.gold`.
+    pub fn id(self) -> String {
+        match self {
+            Item::Skin(skin) => format!("skin.{}", skin.name()),
+            Item::Trail(trail) => format!("trail.{}", trail.name()),
+        }
+    }
+
+    /// Coins the item costs.
+    pub fn price(self) -> u64 {
+        match self {
+            Item::Skin(Skin::Gold | Skin::Midnight) => 30,
+            Item::Skin(Skin::Candy | Skin::Mint) => 50,
+            Item::Trail(Trail::Footprints) => 20,
+            Item::Trail(Trail::Bubbles) => 40,
+            Item::Trail(Trail::Sparkles) => 60,
+        }
+    }
+
+    /// Shown in the shop.
+    pub fn title(self, language: Language) -> String {
+        match self {
+            Item::Skin(skin) => language.format(Text::SkinItem, &[&skin.name()]),
+            Item::Trail(trail) => language.format(Text::TrailItem, &[&trail.name()]),
+        }
+    }
+}
+
+/// The coins and cosmetics of the current profile.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Wallet {
+    pub coins: u64,
+    /// Ids of the items bought, see [Item::id].
+    owned: Vec<String>,
+    pub skin: Option<Skin>,
+    pub trail: Option<Trail>,
+}
+
+impl Wallet {
+    /// Loads the wallet, starting empty if nothing was earned yet.
+    pub fn load() -> Wallet {
+        let mut wallet = Wallet::default();
+        let Ok(source) = std::fs::read_to_string(profile_dir().join(SHOP_FILE)) else {
+            return wallet;
+        };
+        for (key, value) in parse_entries(&source) {
+            match key {
+                "coins" => wallet.coins = value.parse().unwrap_or_default(),
+                "owned" => wallet.owned = value.split(',').map(str::trim).filter(|id| !id.is_empty()).map(str::to_string).collect(),
+                "skin" => wallet.skin = Skin::from_name(value),
+                "trail" => wallet.trail = Trail::from_name(value),
+                _ => {}
+            }
+        }
+        wallet
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        let mut entries = vec![("coins", self.coins.to_string()), ("owned", self.owned.join(","))];
+        if let Some(skin) = self.skin {
+            entries.push(("skin", skin.name().to_string()));
+        }
+        if let Some(trail) = self.trail {
+            entries.push(("trail", trail.name().to_string()));
+        }
+        std::fs::create_dir_all(profile_dir())?;
+        std::fs::write(profile_dir().join(SHOP_FILE), format_entries(&entries))
+    }
+
+    /// Adds the coins a run reaching `score` earned and returns how many.
+    pub fn earn(&mut self, score: u64) -> u64 {
+        let coins = score / COIN_SCORE;
+        self.coins += coins;
+        coins
+    }
+
+    pub fn owns(&self, item: Item) -> bool {
+        self.owned.contains(&item.id())
+    }
+
+    pub fn is_worn(&self, item: Item) -> bool {
+        match item {
+            Item::Skin(skin) => self.skin == Some(skin),
+            Item::Trail(trail) => self.trail == Some(trail),
+        }
+    }
+
+    /// Buys `item` if there are enough coins and it isn't owned yet. Returns whether it was
+    /// bought.
+    pub fn buy(&mut self, item: Item) -> bool {
+        if self.owns(item) || self.coins < item.price() {
+            return false;
+        }
+        self.coins -= item.price();
+        self.owned.push(item.id());
+        true
+    }
+
+    /// Puts on `item` if it is owned and not worn, takes it off otherwise.
+    pub fn toggle(&mut self, item: Item) {
+        let worn = self.is_worn(item) || !self.owns(item);
+        match item {
+            Item::Skin(skin) => self.skin = (!worn).then_some(skin),
+            Item::Trail(trail) => self.trail = (!worn).then_some(trail),
+        }
+    }
+}
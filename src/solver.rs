@@ -0,0 +1,63 @@
+//! Checks that freshly generated rows can be crossed.
+//!
+//! The check hops through the rows like a player would, one move per tick, while the rows move
+//! on with their own rng. The traffic it sees therefore isn't exactly the traffic of the game, it
+//! only rules out sections that can't be crossed however the vehicles spawn, like a row of
+//! rocks above a river whose platforms never line up.
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use crate::difficulty::Difficulty;
+use crate::stripe::{Stripe, STRIPE_LENGTH};
+
+/// Ticks the player gets to get from the bottom to the top row.
+const MAX_TICKS: usize = 150;
+
+/// Whether the top row of `rows` can be reached from below the bottom one.
+pub fn passable(rows: &[Stripe], difficulty: &Difficulty) -> bool {
+    let Some(top) = rows.len().checked_sub(1) else {
+        return true;
+    };
+    let mut rows = rows.to_vec();
+    let mut rng = StdRng::seed_from_u64(0);
+    let mut positions = vec![[false; STRIPE_LENGTH]; rows.len()];
+    for _ in 0..MAX_TICKS {
+        if positions[top].contains(&true) {
+            return true;
+        }
+        // The player can wait below the rows and hop onto the bottom one at any time.
+        let mut next = vec![[false; STRIPE_LENGTH]; rows.len()];
+        next[0] = core::array::from_fn(|x| !rows[0].blocks(x as u8));
+        for (y, row) in positions.iter().enumerate() {
+            for x in (0..STRIPE_LENGTH).filter(|x| row[*x]) {
+                let targets = [(y, Some(x)), (y, x.checked_sub(1)), (y, Some(x + 1)), (y + 1, Some(x))];
+                for (y, x) in targets {
+                    if let Some(x) = x.filter(|x| *x < STRIPE_LENGTH) && y <= top && !rows[y].blocks(x as u8) {
+                        next[y][x] = true;
+                    }
+                }
+            }
+        }
+        remove_collisions(&rows, &mut next);
+        for _ in 0..difficulty.speed {
+            for (row, positions) in rows.iter_mut().zip(&mut next) {
+                row.update(&mut rng);
+                match row.carries() {
+                    Some(1) => *positions = core::array::from_fn(|x| x > 0 && positions[x - 1]),
+                    Some(_) => *positions = core::array::from_fn(|x| x + 1 < STRIPE_LENGTH && positions[x + 1]),
+                    None => {}
+                }
+            }
+            remove_collisions(&rows, &mut next);
+        }
+        positions = next;
+    }
+    false
+}
+
+fn remove_collisions(rows: &[Stripe], positions: &mut [[bool; STRIPE_LENGTH]]) {
+    for (row, positions) in rows.iter().zip(positions) {
+        for (x, position) in positions.iter_mut().enumerate() {
+            *position &= !row.collides(x as u8);
+        }
+    }
+}
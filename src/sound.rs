@@ -0,0 +1,91 @@
+//! Sound effects and music the game asks frontends to play.
+//!
+//! The [App](crate::app::App) only names the sounds and tracks, frontends play them. Built with
+//! the `sound` feature the terminal frontend synthesizes them from [Sound::notes] and
+//! [Track::notes], otherwise it rings the terminal bell for trains.
+use crate::mode::GameMode;
+
+/// Points between two chimes.
+pub const CHIME_SCORE: u64 = 10;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Sound {
+    /// The player moved.
+    Hop,
+    /// The player was hit by a car or train.
+    Crash,
+    /// A railroad next to the player started warning about a train.
+    TrainHorn,
+    /// The score reached another multiple of [CHIME_SCORE].
+    Chime,
+}
+
+impl Sound {
+    /// The tones of the sound as frequency in Hz and duration in milliseconds.
+    pub fn notes(self) -> &'static [(f32, u64)] {
+        match self {
+            Sound::Hop => &[(880.0, 40)],
+            Sound::Crash => &[(196.0, 60), (131.0, 60), (98.0, 160)],
+            Sound::TrainHorn => &[(392.0, 250), (330.0, 350)],
+            Sound::Chime => &[(1319.0, 80), (1760.0, 160)],
+        }
+    }
+}
+
+/// Background music looping while a game runs.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Track {
+    /// A bouncy tune for the regular modes.
+    Classic,
+    /// A fast tune for time attack.
+    Rush,
+    /// A slow tune for zen runs.
+    Calm,
+}
+
+impl Track {
+    pub fn for_mode(mode: &GameMode) -> Self {
+        match mode {
+            GameMode::TimeAttack { .. } => Track::Rush,
+            GameMode::Zen => Track::Calm,
+            _ => Track::Classic,
+        }
+    }
+
+    /// One loop of the melody like [Sound::notes], a frequency of 0 is a rest.
+    pub fn notes(self) -> &'static [(f32, u64)] {
+        match self {
+            Track::Classic => &[
+                (523.3, 200), (659.3, 200), (784.0, 200), (659.3, 200),
+                (587.3, 200), (698.5, 200), (880.0, 400),
+                (784.0, 200), (659.3, 200), (523.3, 200), (587.3, 200),
+                (659.3, 400), (0.0, 400),
+            ],
+            Track::Rush => &[
+                (659.3, 120), (659.3, 120), (784.0, 120), (659.3, 120),
+                (880.0, 120), (784.0, 120), (659.3, 120), (587.3, 120),
+                (523.3, 120), (587.3, 120), (659.3, 240), (0.0, 240),
+            ],
+            Track::Calm => &[
+                (392.0, 600), (440.0, 600), (523.3, 900), (0.0, 300),
+                (493.9, 600), (440.0, 600), (392.0, 900), (0.0, 900),
+            ],
+        }
+    }
+}
+
+/// How loud sounds are played.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SoundSettings {
+    pub muted: bool,
+    /// Volume in percent.
+    pub volume: u8,
+    /// Whether [Track]s play during games.
+    pub music: bool,
+}
+
+impl Default for SoundSettings {
+    fn default() -> Self {
+        SoundSettings { muted: false, volume: 50, music: false }
+    }
+}
@@ -0,0 +1,106 @@
+//! Lets others watch a run live over TCP.
+//!
+//! The player opens a [Broadcast] with `--broadcast`, which sends every frame to the connected
+//! spectators as escape sequences below a "LIVE" line. Spectators connect with `--watch`, which
+//! copies what arrives to their terminal and ignores all keys but quitting.
+use std::io::{ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::time::Duration;
+use crossterm::event::{self, Event, KeyCode};
+use crossterm::{cursor, terminal, QueueableCommand};
+use crate::backend::is_interrupt;
+use crate::color::Color;
+use crate::diff::DiffRenderer;
+use crate::grid::{text, Cell, Grid};
+
+/// Port `--broadcast` listens on and `--watch` connects to for addresses without one.
+pub const SPECTATE_PORT: u16 = 7533;
+
+/// How long `--watch` waits for a key before copying the next frames.
+const WATCH_POLL_INTERVAL: Duration = Duration::from_millis(15);
+
+/// The spectators of a run.
+pub struct Broadcast {
+    listener: TcpListener,
+    /// Connected spectators with what they have on screen.
+    spectators: Vec<(TcpStream, DiffRenderer)>,
+    /// The frame sent last, drawn for spectators as soon as they connect.
+    frame: Grid,
+}
+
+impl Broadcast {
+    /// Listens for spectators on `port`.
+    pub fn open(port: u16) -> std::io::Result<Broadcast> {
+        let listener = TcpListener::bind(("0.0.0.0", port))?;
+        listener.set_nonblocking(true)?;
+        Ok(Broadcast { listener, spectators: Vec::new(), frame: Grid::new() })
+    }
+
+    /// Accepts waiting spectators and shows them the current frame. Call this regularly, as
+    /// [Broadcast::send] is only called when the frame changes.
+    pub fn accept(&mut self) {
+        while let Ok((mut stream, _)) = self.listener.accept() {
+            let mut renderer = DiffRenderer::new();
+            if stream.set_nodelay(true).and_then(|_| stream.queue(cursor::Hide).map(|_| ()))
+                .and_then(|_| draw(&mut stream, &mut renderer, &self.frame)).is_ok() {
+                self.spectators.push((stream, renderer));
+            }
+        }
+    }
+
+    /// Shows `grid` to all spectators, dropping those who disconnected.
+    pub fn send(&mut self, grid: &Grid) {
+        self.frame = live(grid);
+        self.spectators.retain_mut(|(stream, renderer)| draw(stream, renderer, &self.frame).is_ok());
+    }
+}
+
+/// `grid` below the "LIVE" indicator.
+fn live(grid: &Grid) -> Grid {
+    let mut line = vec![Cell::colored('●', Color::Red)];
+    line.extend(text(" LIVE"));
+    let mut live = Grid::new();
+    live.push_line(line);
+    live.append(grid.clone());
+    live
+}
+
+fn draw(stream: &mut TcpStream, renderer: &mut DiffRenderer, grid: &Grid) -> std::io::Result<()> {
+    renderer.draw(stream, grid)?;
+    stream.flush()
+}
+
+/// Connects to the run broadcast at `address`, on [SPECTATE_PORT] unless it names a port.
+pub fn connect(address: &str) -> std::io::Result<TcpStream> {
+    if address.contains(':') {
+        TcpStream::connect(address)
+    } else {
+        TcpStream::connect((address, SPECTATE_PORT))
+    }
+}
+
+/// Shows the run broadcast on `stream` on `out` until it ends or `q`, Esc or Ctrl+C is pressed.
+/// The terminal has to be in raw mode.
+pub fn watch(mut stream: TcpStream, out: &mut impl Write) -> std::io::Result<()> {
+    stream.set_nonblocking(true)?;
+    let mut buffer = [0; 4096];
+    loop {
+        if event::poll(WATCH_POLL_INTERVAL)?
+            && let Event::Key(key) = event::read()?
+            && (matches!(key.code, KeyCode::Char('q') | KeyCode::Esc) || is_interrupt(&key)) {
+            break;
+        }
+        match stream.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(len) => {
+                out.write_all(&buffer[..len])?;
+                out.flush()?;
+            }
+            Err(err) if err.kind() == ErrorKind::WouldBlock => {}
+            Err(err) => return Err(err),
+        }
+    }
+    out.queue(terminal::Clear(terminal::ClearType::All))?;
+    out.queue(cursor::MoveTo(0, 0))?;
+    out.flush()
+}
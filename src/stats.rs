@@ -0,0 +1,170 @@
+//! Lifetime statistics across all runs.
+//!
+//! Stored as `key = value` lines in `stats.conf` inside [profile_dir].
+use std::collections::BTreeMap;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use crate::difficulty::{Difficulty, ADAPTIVE_LEVELS};
+use crate::map::{DeathCause, GameEvent, MapState};
+use crate::profile::{format_entries, parse_entries, profile_dir};
+
+const STATS_FILE: &str = "stats.conf";
+
+/// Adaptive runs lost below this score count as early deaths.
+const EARLY_DEATH_SCORE: u64 = 15;
+
+/// Early deaths in a row before the adaptive difficulty gets easier.
+const EARLY_DEATHS: u8 = 3;
+
+/// Adaptive runs reaching this score make the next one harder right away.
+const BREEZE_SCORE: u64 = 75;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Stats {
+    pub games_played: u64,
+    pub rows_crossed: u64,
+    /// Sum of all scores, for the average.
+    pub total_score: u64,
+    /// Best score of a run that could be lost, so not of zen runs.
+    pub best_score: u64,
+    /// Cars and trains that passed right next to the player.
+    pub near_misses: u64,
+    pub deaths: BTreeMap<DeathCause, u64>,
+    /// Current level of the adaptive difficulty, see [Difficulty::adapted].
+    pub adaptive_level: i8,
+    /// Adaptive runs in a row that ended early.
+    pub early_deaths: u8,
+}
+
+/// What a single run adds to the [Stats] beyond what its [MapState] tells, counted from its
+/// events as they happen.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct RunStats {
+    /// Cars and trains that passed right next to the player.
+    pub near_misses: u64,
+}
+
+impl RunStats {
+    /// Counts `event` if the stats track it.
+    pub fn count(&mut self, event: GameEvent) {
+        if let GameEvent::NearMiss { .. } = event {
+            self.near_misses += 1;
+        }
+    }
+}
+
+impl Stats {
+    /// Loads the stats, starting fresh if there are none yet.
+    pub fn load() -> Stats {
+        let mut stats = Stats::default();
+        let Ok(source) = std::fs::read_to_string(profile_dir().join(STATS_FILE)) else {
+            return stats;
+        };
+        for (key, value) in parse_entries(&source) {
+            match key {
+                "adaptive_level" => stats.adaptive_level = value.parse().unwrap_or_default(),
+                "early_deaths" => stats.early_deaths = value.parse().unwrap_or_default(),
+                _ => {
+                    let Ok(value) = value.parse() else { continue };
+                    match key {
+                        "games_played" => stats.games_played = value,
+                        "rows_crossed" => stats.rows_crossed = value,
+                        "total_score" => stats.total_score = value,
+                        "best_score" => stats.best_score = value,
+                        "near_misses" => stats.near_misses = value,
+                        _ => if let Some(cause) = key.strip_prefix("deaths.").and_then(DeathCause::from_id) {
+                            stats.deaths.insert(cause, value);
+                        },
+                    }
+                }
+            }
+        }
+        stats
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        let mut entries = vec![
+            ("games_played", self.games_played.to_string()),
+            ("rows_crossed", self.rows_crossed.to_string()),
+            ("total_score", self.total_score.to_string()),
+            ("best_score", self.best_score.to_string()),
+            ("near_misses", self.near_misses.to_string()),
+            ("adaptive_level", self.adaptive_level.to_string()),
+            ("early_deaths", self.early_deaths.to_string()),
+        ];
+        let death_keys = self.deaths.keys().map(|cause| format!("deaths.{}", cause.id())).collect::<Vec<_>>();
+        for (key, count) in death_keys.iter().zip(self.deaths.values()) {
+            entries.push((key.as_str(), count.to_string()));
+        }
+        std::fs::create_dir_all(profile_dir())?;
+        std::fs::write(profile_dir().join(STATS_FILE), format_entries(&entries))
+    }
+
+    /// Adds a finished run on `map` with the counters of what happened in it. Runs where the
+    /// player never moved don't count.
+    pub fn record(&mut self, map: &MapState, run: &RunStats) {
+        if !map.started() {
+            return;
+        }
+        self.near_misses += run.near_misses;
+        self.games_played += 1;
+        self.rows_crossed += map.rows_crossed();
+        self.total_score += map.score();
+        if !map.is_harmless() {
+            self.best_score = self.best_score.max(map.score());
+        }
+        if let Some(cause) = map.death_cause() {
+            *self.deaths.entry(cause).or_default() += 1;
+        }
+        if map.difficulty().name == Difficulty::ADAPTIVE.name && !map.is_harmless() {
+            self.update_adaptive_level(map);
+        }
+    }
+
+    /// The difficulty to play a run at `difficulty` with, adapted to the last runs if it is
+    /// [Difficulty::ADAPTIVE].
+    pub fn adapt(&self, difficulty: Difficulty) -> Difficulty {
+        if difficulty == Difficulty::ADAPTIVE {
+            difficulty.adapted(self.adaptive_level)
+        } else {
+            difficulty
+        }
+    }
+
+    /// Eases the adaptive difficulty after [EARLY_DEATHS] early deaths in a row and makes it
+    /// harder after every run reaching [BREEZE_SCORE].
+    fn update_adaptive_level(&mut self, map: &MapState) {
+        if !map.alive && map.score() < EARLY_DEATH_SCORE {
+            self.early_deaths += 1;
+            if self.early_deaths >= EARLY_DEATHS {
+                self.early_deaths = 0;
+                self.adaptive_level = (self.adaptive_level - 1).max(*ADAPTIVE_LEVELS.start());
+            }
+        } else {
+            self.early_deaths = 0;
+            if map.score() >= BREEZE_SCORE {
+                self.adaptive_level = (self.adaptive_level + 1).min(*ADAPTIVE_LEVELS.end());
+            }
+        }
+    }
+
+    pub fn average_score(&self) -> f64 {
+        if self.games_played == 0 {
+            0.0
+        } else {
+            self.total_score as f64 / self.games_played as f64
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        let deaths = DeathCause::ALL.iter()
+            .map(|cause| format!("\"{}\":{}", cause.id(), self.deaths.get(cause).unwrap_or(&0)))
+            .collect::<Vec<_>>()
+            .join(",");
+        format!(
+            "{{\"games_played\":{},\"rows_crossed\":{},\"average_score\":{:.2},\"best_score\":{},\"near_misses\":{},\"adaptive_level\":{},\"deaths_by_cause\":{{{deaths}}}}}",
+            self.games_played, self.rows_crossed, self.average_score(), self.best_score, self.near_misses, self.adaptive_level,
+        )
+    }
+}
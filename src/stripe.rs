@@ -1,73 +1,417 @@
 use std::ops::Div;
-use crossterm::style::Stylize;
 use rand::distr::weighted::WeightedIndex;
 use rand::prelude::Distribution;
-use rayon::prelude::*;
+use rand::Rng;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use crate::animation::Hop;
+use crate::biome::Biome;
+use crate::character::Character;
+use crate::color::Color;
+use crate::difficulty::Difficulty;
+use crate::grid::{Cell, Grid};
+use crate::map::Facing;
+use crate::theme::{Glyphs, PlayerMarker, Theme};
 
-// TODO: add 2 for padding, allowing to display more from the side
-pub const STRIPE_LENGTH: usize = 7;
+/// Tiles of every row, more than fit on the screen at once.
+pub const STRIPE_LENGTH: usize = 15;
 
-const TILE_WIDTH: usize = 3;
+/// Tiles of every row shown around the players, see [crate::map::MapState::visible_columns].
+pub const VISIBLE_TILES: usize = 7;
+
+pub const TILE_WIDTH: usize = 3;
+
+/// The tiles of a row that only sets the [VISIBLE_TILES] in the middle, with `outside` on both
+/// sides, e.g. for levels made for the screen.
+pub fn centered<T: Copy>(visible: [T; VISIBLE_TILES], outside: T) -> [T; STRIPE_LENGTH] {
+    let left = (STRIPE_LENGTH - VISIBLE_TILES) / 2;
+    core::array::from_fn(|x| x.checked_sub(left).and_then(|x| visible.get(x)).copied().unwrap_or(outside))
+}
+
+/// Keeps `len` of the [TILE_WIDTH] columns of every tile of a rendered `board`, for terminals too
+/// narrow for the full board. The middle column with the player and decor stays, for `len` 2
+/// together with the one right of it.
+///
+/// Emoji take two columns that may belong to different tiles, so render narrowed boards with
+/// [Glyphs::Blocks] or [Glyphs::Ascii].
+pub fn narrow(board: Grid, len: usize) -> Grid {
+    if len >= TILE_WIDTH {
+        return board;
+    }
+    let kept = TILE_WIDTH / 2..TILE_WIDTH / 2 + len;
+    let mut narrowed = Grid::new();
+    for line in board.lines() {
+        narrowed.push_line(line.iter().enumerate().filter(|(x, _)| kept.contains(&(x % TILE_WIDTH))).map(|(_, cell)| *cell).collect());
+    }
+    narrowed
+}
+
+/// Steps per tile of slow, normal and fast lanes, weighted by [Difficulty::lane_speed_weights].
+const LANE_STEPS_PER_TILE: [usize; 3] = [4, 3, 2];
+
+/// Most trains passing a railroad per cycle.
+const MAX_TRAIN_PASSES: usize = 3;
+
+/// Steps a train takes to pass.
+const TRAIN_LENGTHS: std::ops::RangeInclusive<usize> = 2..=5;
+
+/// Steps between two trains of the same cycle.
+const TRAIN_GAPS: std::ops::RangeInclusive<usize> = 3..=8;
+
+/// Steps a railroad warns before a train arrives.
+pub const TRAIN_WARN_STEPS: usize = 9;
+
+/// Amount of lanes of a highway.
+const HIGHWAY_LANES: std::ops::RangeInclusive<u8> = 2..=4;
+
+/// Cycle lengths of generated traffic lights.
+const TRAFFIC_LIGHT_CYCLES: std::ops::Range<usize> = 40..70;
+
+/// Steps the light of a [TrafficRoad] is red for.
+pub const RED_LIGHT_STEPS: usize = 18;
+
+/// Steps the light of a [TrafficRoad] is yellow for before turning red.
+pub const YELLOW_LIGHT_STEPS: usize = 6;
+
+/// Steps a bush holds the player in place.
+pub const BUSH_STEPS: u64 = 6;
+
+/// Steps between two dives of the turtles in a river.
+const DIVE_CYCLE: usize = 45;
+
+/// Steps turtles stay under water.
+const DIVE_STEPS: usize = 9;
+
+/// Steps turtles blink before diving.
+const DIVE_WARN_STEPS: usize = 12;
+
+/// Steps per tile of roads from levels.
+const DEFAULT_STEPS_PER_TILE: usize = 3;
 
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Stripe {
     Empty,
     Green(GreenStripe),
     Rail(Railroad),
     Road(Road),
+    TrafficRoad(TrafficRoad),
+    River(River),
+    /// End of a level, reaching it wins the game.
+    Finish,
+    /// Safe row marked with flags, reaching it moves the respawn point to it.
+    Checkpoint,
+    /// Lives in the script that made it and can't be serialized.
+    #[cfg(feature = "scripting")]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    Scripted(crate::scripting::ScriptedStripe),
 }
 
 impl Stripe {
-    pub fn generate() -> Self {
-        let dist = WeightedIndex::new([5, 3, 5]).unwrap();
-        match dist.sample(&mut rand::rng()) {
-            0 => Stripe::Green(GreenStripe::generate()),
-            1 => Stripe::Rail(Railroad::generate()),
-            2 => Stripe::Road(Road::generate()),
+    /// Generates the row above `below`, continuing its highway if it is a road with lanes left.
+    pub fn generate(rng: &mut impl Rng, difficulty: &Difficulty, biome: Biome, below: Option<&Stripe>) -> Self {
+        if let Some(Stripe::Road(road)) = below && road.highway_lanes_left > 0 {
+            return Stripe::Road(road.next_lane(rng));
+        }
+        #[allow(unused_mut)]
+        let mut weights = biome.stripe_weights(difficulty.stripe_weights).map(i64::from).to_vec();
+        #[cfg(feature = "scripting")]
+        weights.extend(crate::scripting::stripe_weights());
+        let dist = WeightedIndex::new(weights).unwrap();
+        match dist.sample(rng) {
+            0 if rng.random_ratio(difficulty.river_percent, 100) => Stripe::River(River::generate(rng, difficulty)),
+            0 => Stripe::Green(GreenStripe::generate(rng, difficulty, biome, below)),
+            1 => Stripe::Rail(Railroad::generate(rng, difficulty)),
+            2 => {
+                let road = Road::generate(rng, difficulty);
+                if road.highway_lanes_left == 0 && rng.random_ratio(difficulty.traffic_light_percent, 100) {
+                    Stripe::TrafficRoad(TrafficRoad::new(road, rng.random_range(TRAFFIC_LIGHT_CYCLES)))
+                } else {
+                    Stripe::Road(road)
+                }
+            }
+            #[cfg(feature = "scripting")]
+            idx => Stripe::Scripted(crate::scripting::ScriptedStripe::new(idx - 3)),
+            #[cfg(not(feature = "scripting"))]
             _ => panic!("Weighted index out of expected range"),
         }
 
     }
 
-    pub fn update(&mut self) {
+    pub fn update(&mut self, rng: &mut impl Rng) {
         match self {
-            Stripe::Empty => {},
+            Stripe::Empty | Stripe::Finish | Stripe::Checkpoint => {},
             Stripe::Green(stripe) => stripe.update(),
             Stripe::Rail(stripe) => stripe.update(),
-            Stripe::Road(stripe) => stripe.update(),
+            Stripe::Road(stripe) => stripe.update(rng),
+            Stripe::TrafficRoad(stripe) => stripe.update(rng),
+            Stripe::River(stripe) => stripe.update(rng),
+            #[cfg(feature = "scripting")]
+            Stripe::Scripted(stripe) => stripe.update(),
         }
     }
 
     pub fn collides(&self, x: u8) -> bool {
         match self {
-            Stripe::Empty => false,
+            Stripe::Empty | Stripe::Finish | Stripe::Checkpoint => false,
             Stripe::Green(stripe) => stripe.collides(x),
             Stripe::Rail(stripe) => stripe.collides(x),
-            Stripe::Road(stripe) => stripe.collides(x),
+            Stripe::Road(stripe) | Stripe::TrafficRoad(TrafficRoad { road: stripe, .. }) => stripe.collides(x),
+            Stripe::River(stripe) => stripe.collides(x),
+            #[cfg(feature = "scripting")]
+            Stripe::Scripted(stripe) => stripe.collides(x),
+        }
+    }
+
+    /// Whether the tile at column `x` can't be hopped onto.
+    pub fn blocks(&self, x: u8) -> bool {
+        matches!(self, Stripe::Green(green) if green.tiles[x as usize] == GrassTile::Rock)
+    }
+
+    /// Columns the player standing on this stripe is moved by during the last update.
+    pub fn carries(&self) -> Option<i8> {
+        match self {
+            Stripe::River(river) if river.moved => Some(if river.left { -1 } else { 1 }),
+            _ => None,
+        }
+    }
+
+    /// Stable byte representation of the stripes current state, used for hashing boards.
+    pub(crate) fn fingerprint(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        match self {
+            Stripe::Empty => bytes.push(0),
+            Stripe::Green(stripe) => {
+                bytes.push(1);
+                bytes.extend(stripe.trees.map(u8::from));
+                bytes.extend(stripe.tiles.map(|tile| tile as u8));
+            }
+            Stripe::Rail(stripe) => {
+                bytes.push(2);
+                bytes.extend((stripe.cycle_length as u64).to_le_bytes());
+                bytes.extend((stripe.cycle_pos as u64).to_le_bytes());
+                for pass in stripe.passes.into_iter().flatten() {
+                    bytes.extend((pass.end as u64).to_le_bytes());
+                    bytes.extend((pass.length as u64).to_le_bytes());
+                    bytes.push(pass.left.into());
+                }
+            }
+            Stripe::TrafficRoad(stripe) => {
+                bytes.push(6);
+                bytes.extend((stripe.cycle_length as u64).to_le_bytes());
+                bytes.extend((stripe.cycle_pos as u64).to_le_bytes());
+                bytes.extend(Stripe::Road(stripe.road).fingerprint());
+            }
+            Stripe::Road(stripe) => {
+                bytes.push(3);
+                bytes.push(stripe.left.into());
+                bytes.extend(stripe.cars.map(u8::from));
+                bytes.extend(stripe.trucks.map(u8::from));
+                bytes.extend([stripe.vehicle_left, stripe.spawning_truck.into(), stripe.gap]);
+                bytes.push(stripe.offset as u8);
+                bytes.push(stripe.steps_per_tile as u8);
+                bytes.push(stripe.highway_lanes_left);
+                bytes.push(stripe.crossing.unwrap_or(u8::MAX));
+            }
+            Stripe::River(stripe) => {
+                bytes.push(7);
+                bytes.push(stripe.left.into());
+                bytes.extend(stripe.cells.map(|cell| cell as u8));
+                bytes.extend([stripe.platform_left, stripe.crocodile.into(), stripe.gap]);
+                bytes.push(stripe.steps_per_tile as u8);
+                bytes.push(stripe.offset as u8);
+                bytes.extend((stripe.dive_pos as u64).to_le_bytes());
+            }
+            Stripe::Finish => bytes.push(5),
+            Stripe::Checkpoint => bytes.push(8),
+            #[cfg(feature = "scripting")]
+            Stripe::Scripted(stripe) => {
+                bytes.push(4);
+                bytes.extend(stripe.fingerprint());
+            }
+        }
+        bytes
+    }
+
+    /// The kind of row, named like in level files.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Stripe::Empty => "empty",
+            Stripe::Green(_) => "green",
+            Stripe::Rail(_) => "rail",
+            Stripe::Road(_) => "road",
+            Stripe::TrafficRoad(_) => "traffic",
+            Stripe::River(_) => "river",
+            Stripe::Finish => "finish",
+            Stripe::Checkpoint => "checkpoint",
+            #[cfg(feature = "scripting")]
+            Stripe::Scripted(_) => "scripted",
+        }
+    }
+
+    /// The kind of row and the state it moves by, e.g. `rail cycle 12/80`, for the debug overlay.
+    pub fn debug_state(&self) -> String {
+        let direction = |left: bool| if left { '<' } else { '>' };
+        let road = |road: &Road| format!("{} offset {}/{}", direction(road.left), road.offset, road.steps_per_tile);
+        match self {
+            Stripe::Rail(rail) => {
+                let warning = if rail.warns() { " warning" } else { "" };
+                format!("rail cycle {}/{}{warning}", rail.cycle_pos, rail.cycle_length)
+            }
+            Stripe::Road(stripe) => format!("road {}", road(stripe)),
+            Stripe::TrafficRoad(traffic) => {
+                format!("traffic {} light {}/{}", road(&traffic.road), traffic.cycle_pos, traffic.cycle_length)
+            }
+            Stripe::River(river) => format!("river {} offset {}/{} dive {}/{DIVE_CYCLE}",
+                direction(river.left), river.offset, river.steps_per_tile, river.dive_pos),
+            #[cfg(feature = "scripting")]
+            Stripe::Scripted(stripe) => format!("scripted {}", stripe.debug_state()),
+            _ => self.kind().to_string(),
         }
     }
 
     pub fn visualize(&self) -> StripeRender {
         match self {
             Stripe::Empty => StripeRender::default(),
+            Stripe::Finish => StripeRender::new(core::array::from_fn(|i| {
+                if i % 2 == 0 { Block::White } else { Block::Black }
+            }), None),
+            Stripe::Checkpoint => {
+                let mut render = StripeRender::new([Block::BrightGreen; STRIPE_LENGTH], None);
+                render.flags = true;
+                render
+            }
             Stripe::Green(stripe) => stripe.visualize(),
             Stripe::Rail(stripe) => stripe.visualize(),
             Stripe::Road(stripe) => stripe.visualize(),
+            Stripe::TrafficRoad(stripe) => stripe.visualize(),
+            Stripe::River(stripe) => stripe.visualize(),
+            #[cfg(feature = "scripting")]
+            Stripe::Scripted(stripe) => stripe.visualize(),
+        }
+    }
+}
+
+/// What grows on a grass tile without a tree.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum GrassTile {
+    Grass,
+    /// Can't be hopped onto.
+    Rock,
+    /// Holds the player in place for [BUSH_STEPS] steps after hopping in.
+    Bush,
+    /// Only decoration.
+    Flower,
+}
+
+impl GrassTile {
+    /// The character drawn in the middle of the tile.
+    fn glyph(self, glyphs: Glyphs) -> char {
+        match (self, glyphs) {
+            (GrassTile::Grass, _) => ' ',
+            (GrassTile::Rock, Glyphs::Blocks) => '▲',
+            (GrassTile::Rock, Glyphs::Ascii) => 'A',
+            (GrassTile::Rock, Glyphs::Emoji) => '🪨',
+            (GrassTile::Bush, Glyphs::Blocks) => '♣',
+            (GrassTile::Bush, Glyphs::Ascii) => '&',
+            (GrassTile::Bush, Glyphs::Emoji) => '🌿',
+            (GrassTile::Flower, Glyphs::Blocks) => '✿',
+            (GrassTile::Flower, Glyphs::Ascii) => '*',
+            (GrassTile::Flower, Glyphs::Emoji) => '🌼',
+        }
+    }
+
+    fn block(self) -> Block {
+        match self {
+            GrassTile::Grass => Block::BrightGreen,
+            GrassTile::Rock => Block::Gray,
+            GrassTile::Bush => Block::Green,
+            GrassTile::Flower => Block::DarkYellow,
         }
     }
 }
 
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct GreenStripe {
     trees: [bool; STRIPE_LENGTH],
+    tiles: [GrassTile; STRIPE_LENGTH],
+    /// Columns the player can get to from the start of the board.
+    reachable: [bool; STRIPE_LENGTH],
 }
 
 impl GreenStripe {
-    pub fn generate() -> Self {
+    /// Places trees and, on the remaining tiles, rocks, bushes and flowers with the densities of
+    /// [Difficulty::grass_tile_percents]. The center column is never blocked and the row is
+    /// always passable from the row `below`.
+    pub fn generate(rng: &mut impl Rng, difficulty: &Difficulty, biome: Biome, below: Option<&Stripe>) -> Self {
         let mut trees = [false; STRIPE_LENGTH];
-        rand::fill(&mut trees);
+        if biome == Biome::Meadow {
+            rng.fill(&mut trees);
+        } else {
+            trees = core::array::from_fn(|_| rng.random_ratio(biome.tree_percent(), 100));
+        }
         trees[STRIPE_LENGTH.div(2)] = false;
-        GreenStripe { trees }
+        let [rock, bush, flower] = difficulty.grass_tile_percents;
+        let tiles = core::array::from_fn(|x| {
+            if trees[x] {
+                return GrassTile::Grass;
+            }
+            match rng.random_range(0..100) {
+                roll if roll < rock && x != STRIPE_LENGTH.div(2) => GrassTile::Rock,
+                roll if (rock..rock + bush).contains(&roll) => GrassTile::Bush,
+                roll if (rock + bush..rock + bush + flower).contains(&roll) => GrassTile::Flower,
+                _ => GrassTile::Grass,
+            }
+        });
+        let mut green = GreenStripe { trees, tiles, reachable: [true; STRIPE_LENGTH] };
+        green.connect(match below {
+            Some(Stripe::Green(below)) => below.reachable,
+            _ => [true; STRIPE_LENGTH],
+        });
+        green
+    }
+
+    pub fn new(trees: [bool; STRIPE_LENGTH]) -> Self {
+        GreenStripe { trees, tiles: [GrassTile::Grass; STRIPE_LENGTH], reachable: [true; STRIPE_LENGTH] }
+    }
+
+    /// The row with rocks, bushes and flowers on `tiles`. Tiles with trees stay grass.
+    pub fn with_tiles(mut self, tiles: [GrassTile; STRIPE_LENGTH]) -> Self {
+        self.tiles = core::array::from_fn(|x| if self.trees[x] { GrassTile::Grass } else { tiles[x] });
+        self
+    }
+
+    /// Finds the columns that can be reached from the `below` ones by hopping up and sideways
+    /// over free tiles. If there are none, the tile closest to the center that can be hopped onto
+    /// from below is cleared.
+    fn connect(&mut self, below: [bool; STRIPE_LENGTH]) {
+        let free: [bool; STRIPE_LENGTH] = core::array::from_fn(|x| !self.trees[x] && self.tiles[x] != GrassTile::Rock);
+        let mut reachable: [bool; STRIPE_LENGTH] = core::array::from_fn(|x| free[x] && below[x]);
+        if !reachable.contains(&true) {
+            let Some(x) = (0..STRIPE_LENGTH).filter(|x| below[*x]).min_by_key(|x| x.abs_diff(STRIPE_LENGTH.div(2))) else {
+                return;
+            };
+            self.trees[x] = false;
+            self.tiles[x] = GrassTile::Grass;
+            return self.connect(below);
+        }
+        for x in 1..STRIPE_LENGTH {
+            reachable[x] |= reachable[x - 1] && free[x];
+        }
+        for x in (0..STRIPE_LENGTH - 1).rev() {
+            reachable[x] |= reachable[x + 1] && free[x];
+        }
+        self.reachable = reachable;
+    }
+
+    pub fn trees(&self) -> [bool; STRIPE_LENGTH] {
+        self.trees
+    }
+
+    pub fn tiles(&self) -> [GrassTile; STRIPE_LENGTH] {
+        self.tiles
     }
 
     fn update(&mut self) {}
@@ -82,30 +426,83 @@ impl GreenStripe {
         let blocks: [Block; STRIPE_LENGTH] = core::array::from_fn(|i| {
             if self.trees[i] { tree } else { grass }
         });
-        StripeRender::new(blocks, None)
+        let mut render = StripeRender::new(blocks, None);
+        render.decor = self.tiles.map(|tile| Some(tile).filter(|tile| *tile != GrassTile::Grass));
+        render
     }
 }
 
 /// Railroads are deadly as a whole.
 ///
-/// [cycle_pos] is initialized to cycle length and counts downward.
-/// - On values 0..3 it is deadly
-/// - On values 3..12 it warns
+/// [cycle_pos] is initialized to cycle length and counts downward. Each cycle up to
+/// [MAX_TRAIN_PASSES] trains pass, the last one ending when [cycle_pos] reaches 0.
+/// - While a train passes it is deadly
+/// - On the [warn_steps] values before a train it warns with a blinking pattern while gates close
+///   at both edges
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Railroad {
     cycle_length: usize,
     cycle_pos: usize,
+    passes: [Option<TrainPass>; MAX_TRAIN_PASSES],
+    /// Steps the railroad warns before a train, [TRAIN_WARN_STEPS] unless generated at a
+    /// [Difficulty] with other warnings.
+    warn_steps: usize,
+}
+
+/// A train passing during the [cycle_pos] values `end..end + length`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+struct TrainPass {
+    end: usize,
+    length: usize,
+    left: bool,
 }
 
 impl Railroad {
-    fn generate() -> Self {
-        let cycle_length = rand::random_range(20..50);
+    /// A railroad with a single train of three steps going right at the end of each cycle.
+    pub fn new(cycle_length: usize) -> Self {
+        let mut passes = [None; MAX_TRAIN_PASSES];
+        passes[0] = Some(TrainPass { end: 0, length: 3, left: false });
         Railroad {
             cycle_length,
             cycle_pos: cycle_length,
+            passes,
+            warn_steps: TRAIN_WARN_STEPS,
         }
     }
 
+    /// Starts the cycle at `cycle_pos` instead of its beginning.
+    pub fn with_cycle_pos(mut self, cycle_pos: usize) -> Self {
+        self.cycle_pos = cycle_pos.min(self.cycle_length);
+        self
+    }
+
+    pub fn cycle_length(&self) -> usize {
+        self.cycle_length
+    }
+
+    pub fn cycle_pos(&self) -> usize {
+        self.cycle_pos
+    }
+
+    /// Picks a cycle length and as many trains of random lengths and directions as fit into it,
+    /// each with its warning.
+    fn generate(rng: &mut impl Rng, difficulty: &Difficulty) -> Self {
+        let mut rail = Self::new(rng.random_range(difficulty.min_rail_cycle..difficulty.max_rail_cycle));
+        rail.warn_steps = difficulty.train_warn_steps;
+        let mut end = 0;
+        for idx in 0..rng.random_range(1..=MAX_TRAIN_PASSES) {
+            let length = rng.random_range(TRAIN_LENGTHS);
+            if end + length + rail.warn_steps > rail.cycle_length {
+                break;
+            }
+            rail.passes[idx] = Some(TrainPass { end, length, left: rng.random() });
+            end += length + rng.random_range(TRAIN_GAPS);
+        }
+        rail
+    }
+
     fn update(&mut self) {
         if self.cycle_pos == 0 {
             self.cycle_pos = self.cycle_length;
@@ -114,137 +511,439 @@ impl Railroad {
         }
     }
 
+    /// The train passing right now.
+    fn current_pass(&self) -> Option<TrainPass> {
+        self.passes.into_iter().flatten()
+            .find(|pass| (pass.end..pass.end + pass.length).contains(&self.cycle_pos))
+    }
+
+    /// Steps until the next train arrives, if it is close enough to be warned about.
+    fn steps_until_train(&self) -> Option<usize> {
+        self.passes.into_iter().flatten()
+            .filter_map(|pass| self.cycle_pos.checked_sub(pass.end + pass.length))
+            .filter(|steps| *steps < self.warn_steps)
+            .min()
+    }
+
     fn collides(&self, _x: u8) -> bool {
-        self.cycle_pos < 3
+        self.current_pass().is_some()
+    }
+
+    /// Whether the warning before a train is shown.
+    pub fn warns(&self) -> bool {
+        self.current_pass().is_none() && self.steps_until_train().is_some()
     }
 
     fn visualize(&self) -> StripeRender {
-        let blocks = match self.cycle_pos {
-            0..3 => [Block::Red; STRIPE_LENGTH],
-            3..12 => [Block::DarkYellow; STRIPE_LENGTH],
-            _ => [Block::Gray; STRIPE_LENGTH],
-        };
-        StripeRender::new(blocks, None)
+        if let Some(pass) = self.current_pass() {
+            // Wagons separated by gaps, moving by a third of a tile per tick.
+            let blocks = core::array::from_fn(|i| if i % 3 == 0 { Block::Black } else { Block::Red });
+            let mut render = StripeRender::new(blocks, Some(Offset {
+                offset: (pass.end + pass.length - 1 - self.cycle_pos) % TILE_WIDTH,
+                left: pass.left,
+                fill: Block::Red,
+            }));
+            render.train = true;
+            render
+        } else if let Some(steps) = self.steps_until_train() {
+            let blocks = core::array::from_fn(|i| {
+                if (i + self.cycle_pos / 2).is_multiple_of(2) { Block::DarkYellow } else { Block::Gray }
+            });
+            let mut render = StripeRender::new(blocks, None);
+            render.gates = ((self.warn_steps - steps) / 3 + 1).min(TILE_WIDTH);
+            render
+        } else {
+            StripeRender::new([Block::Gray; STRIPE_LENGTH], None)
+        }
     }
 }
 
+/// Roads spawn bikes of one tile, cars of two to three tiles and trucks of four to five tiles,
+/// weighted by [Difficulty::vehicle_weights] and separated by at least
+/// [Difficulty::min_vehicle_gap] empty tiles. Vehicles move a tile every two to four steps,
+/// depending on the lane.
+///
+/// Some roads start a highway of [HIGHWAY_LANES] adjacent roads with alternating directions that
+/// share their speed. Others have a zebra crossing, a column vehicles never hit the player in.
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Road {
+    /// Tiles occupied by any vehicle.
     cars: [bool; STRIPE_LENGTH],
+    /// Tiles occupied by trucks, which are drawn in their own color.
+    trucks: [bool; STRIPE_LENGTH],
     left: bool,
-    current_car_len: i32,
-    /// Cycles in 0..=2.
+    /// Tiles of the vehicle entering the road that are still to come.
+    vehicle_left: u8,
+    spawning_truck: bool,
+    /// Empty tiles since the last vehicle, saturating at [Difficulty::min_vehicle_gap].
+    gap: u8,
+    vehicle_weights: [u32; 3],
+    min_gap: u8,
+    /// Steps it takes vehicles to move a tile.
+    steps_per_tile: usize,
+    /// Cycles in `0..steps_per_tile`.
     offset: usize,
+    /// Lanes of the highway this road belongs to that are still to be generated above it.
+    highway_lanes_left: u8,
+    /// Column of the zebra crossing.
+    crossing: Option<u8>,
 }
 
 impl Road {
-    fn generate() -> Self {
-        let mut road = Road {
-            cars: [false; STRIPE_LENGTH],
-            current_car_len: 0,
+    /// A road starting with the given cars that spawns random vehicles at normal difficulty
+    /// afterward.
+    pub fn new(left: bool, cars: [bool; STRIPE_LENGTH]) -> Self {
+        Road {
+            cars,
+            trucks: [false; STRIPE_LENGTH],
+            left,
+            vehicle_left: 0,
+            spawning_truck: false,
+            gap: 0,
+            vehicle_weights: Difficulty::NORMAL.vehicle_weights,
+            min_gap: Difficulty::NORMAL.min_vehicle_gap,
+            steps_per_tile: DEFAULT_STEPS_PER_TILE,
             offset: 0,
-            left: rand::random(),
-        };
+            highway_lanes_left: 0,
+            crossing: None,
+        }
+    }
+
+    pub fn left(&self) -> bool {
+        self.left
+    }
+
+    pub fn cars(&self) -> [bool; STRIPE_LENGTH] {
+        self.cars
+    }
+
+    pub fn trucks(&self) -> [bool; STRIPE_LENGTH] {
+        self.trucks
+    }
+
+    pub fn crossing(&self) -> Option<u8> {
+        self.crossing
+    }
+
+    /// The road with trucks instead of cars on `trucks`.
+    pub fn with_trucks(mut self, trucks: [bool; STRIPE_LENGTH]) -> Self {
+        self.trucks = trucks;
+        self.cars = core::array::from_fn(|x| self.cars[x] && !trucks[x]);
+        self
+    }
+
+    /// The road with a zebra crossing at column `x`.
+    pub fn with_crossing(mut self, x: u8) -> Self {
+        self.crossing = Some(x.min(STRIPE_LENGTH as u8 - 1));
+        self
+    }
+
+    /// The road with vehicles moving at the speed of the slowest lanes.
+    pub fn slow(mut self) -> Self {
+        self.steps_per_tile = LANE_STEPS_PER_TILE[0];
+        self
+    }
+
+    fn generate(rng: &mut impl Rng, difficulty: &Difficulty) -> Self {
+        let mut road = Self::new(rng.random(), [false; STRIPE_LENGTH]);
+        road.vehicle_weights = difficulty.vehicle_weights;
+        road.min_gap = difficulty.min_vehicle_gap;
+        road.steps_per_tile = LANE_STEPS_PER_TILE[WeightedIndex::new(difficulty.lane_speed_weights).unwrap().sample(rng)];
+        if rng.random_ratio(difficulty.highway_percent, 100) {
+            road.highway_lanes_left = rng.random_range(HIGHWAY_LANES) - 1;
+        } else if rng.random_ratio(difficulty.crossing_percent, 100) {
+            road.crossing = Some(rng.random_range(0..STRIPE_LENGTH as u8));
+        }
         for _ in 0..STRIPE_LENGTH {
-            road.advance_road();
+            road.advance_road(rng, true);
         }
 
         road
     }
 
-    fn update(&mut self) {
+    /// The lane above this one on a highway, going the other way at the same speed.
+    fn next_lane(&self, rng: &mut impl Rng) -> Self {
+        let mut road = Self::new(!self.left, [false; STRIPE_LENGTH]);
+        road.vehicle_weights = self.vehicle_weights;
+        road.min_gap = self.min_gap;
+        road.steps_per_tile = self.steps_per_tile;
+        road.highway_lanes_left = self.highway_lanes_left - 1;
+        for _ in 0..STRIPE_LENGTH {
+            road.advance_road(rng, true);
+        }
+        road
+    }
+
+    fn update(&mut self, rng: &mut impl Rng) {
+        self.update_with_entry(rng, true);
+    }
+
+    /// Moves the vehicles, new ones only enter the road if `open`.
+    fn update_with_entry(&mut self, rng: &mut impl Rng, open: bool) {
         self.offset += 1;
-        self.offset %= TILE_WIDTH;
+        self.offset %= self.steps_per_tile;
         if self.offset == 0 {
-            self.advance_road();
+            self.advance_road(rng, open);
         }
     }
 
-    fn advance_road(&mut self) {
-        let new_tile = match self.current_car_len {
-            -1..=0 => false, // 2 tiles space between cars
-            ..-1 => rand::random(),
-            1 => true,
-            2 => rand::random(),
-            3.. => false,
+    fn advance_road(&mut self, rng: &mut impl Rng, open: bool) {
+        let (new_tile, truck) = if self.vehicle_left > 0 {
+            self.vehicle_left -= 1;
+            (true, self.spawning_truck)
+        } else if self.gap >= self.min_gap && open && rng.random() {
+            let (len, truck) = match WeightedIndex::new(self.vehicle_weights).unwrap().sample(rng) {
+                0 => (1, false),
+                1 => (2 + u8::from(rng.random::<bool>()), false),
+                _ => (4 + u8::from(rng.random::<bool>()), true),
+            };
+            self.vehicle_left = len - 1;
+            self.spawning_truck = truck;
+            self.gap = 0;
+            (true, truck)
+        } else {
+            self.gap = (self.gap + 1).min(self.min_gap);
+            (false, false)
         };
-        if new_tile {
-            self.current_car_len = (self.current_car_len + 1).max(1);
-        } else { // The car ended
-            if self.current_car_len > 1 {
-                self.current_car_len = 1;
-            }
-            self.current_car_len -= 1;
-        }
 
         if self.left {
             self.cars.rotate_left(1);
             self.cars[STRIPE_LENGTH - 1] = new_tile;
+            self.trucks.rotate_left(1);
+            self.trucks[STRIPE_LENGTH - 1] = truck;
         } else {
             self.cars.rotate_right(1);
             self.cars[0] = new_tile;
+            self.trucks.rotate_right(1);
+            self.trucks[0] = truck;
         }
     }
 
     fn collides(&self, x: u8) -> bool {
-        self.cars[x as usize]
+        self.crossing != Some(x) && self.cars[x as usize]
     }
 
     fn visualize(&self) -> StripeRender {
         let car = Block::Red;
+        let truck = Block::Magenta;
         let road = Block::Gray;
         let blocks: [Block; STRIPE_LENGTH] = core::array::from_fn(|i| {
-            if self.cars[i] { car } else { road }
+            if self.trucks[i] { truck } else if self.cars[i] { car } else { road }
         });
-        StripeRender::new(blocks, Some(Offset {
-            offset: self.offset,
+        let mut render = StripeRender::new(blocks, Some(Offset {
+            offset: self.offset * TILE_WIDTH / self.steps_per_tile,
             left: self.left,
             fill: Block::Gray,
-        }))
+        }));
+        render.crossing = self.crossing.map(usize::from);
+        render
+    }
+}
+
+/// A road with a traffic light at the edge vehicles enter from.
+///
+/// [cycle_pos] is initialized to cycle length and counts downward.
+/// - On values 0..[RED_LIGHT_STEPS] the light is red and no vehicles enter
+/// - On values [RED_LIGHT_STEPS]..[RED_LIGHT_STEPS] + [YELLOW_LIGHT_STEPS] it is yellow
+#[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TrafficRoad {
+    road: Road,
+    cycle_length: usize,
+    cycle_pos: usize,
+}
+
+impl TrafficRoad {
+    pub fn new(road: Road, cycle_length: usize) -> Self {
+        TrafficRoad {
+            road,
+            cycle_length,
+            cycle_pos: cycle_length,
+        }
+    }
+
+    pub fn road(&self) -> Road {
+        self.road
+    }
+
+    pub fn cycle_length(&self) -> usize {
+        self.cycle_length
+    }
+
+    /// Whether vehicles are stopped at the light.
+    pub fn red(&self) -> bool {
+        self.cycle_pos < RED_LIGHT_STEPS
+    }
+
+    fn update(&mut self, rng: &mut impl Rng) {
+        if self.cycle_pos == 0 {
+            self.cycle_pos = self.cycle_length;
+        } else {
+            self.cycle_pos -= 1;
+        }
+        let red = self.red();
+        self.road.update_with_entry(rng, !red);
+    }
+
+    fn visualize(&self) -> StripeRender {
+        let mut render = self.road.visualize();
+        render.light = Some(if self.red() {
+            Block::Red
+        } else if self.cycle_pos < RED_LIGHT_STEPS + YELLOW_LIGHT_STEPS {
+            Block::DarkYellow
+        } else {
+            Block::BrightGreen
+        });
+        render
     }
 }
 
+/// What floats on a tile of a [River].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum RiverCell {
+    Water,
+    /// Safe unless it is under water.
+    Turtle,
+    CrocodileBack,
+    /// The front of a crocodile, which bites.
+    CrocodileHead,
+}
 
-#[derive(Debug, Copy, Clone, PartialEq)]
-pub enum Block {
-    Green,
-    BrightGreen,
-    White,
-    Gray,
-    DarkYellow,
-    Red,
-    Black,
+/// Water with groups of turtles and crocodiles floating by, which carry the player along.
+///
+/// Landing in the water, on a turtle that dove or on the head of a crocodile is deadly.
+/// [dive_pos] is initialized to [DIVE_CYCLE] and counts downward.
+/// - On values 0..[DIVE_STEPS] the turtles are under water
+/// - On values [DIVE_STEPS]..[DIVE_STEPS] + [DIVE_WARN_STEPS] they blink
+#[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct River {
+    cells: [RiverCell; STRIPE_LENGTH],
+    left: bool,
+    /// Tiles of the turtles or crocodile entering the river that are still to come.
+    platform_left: u8,
+    crocodile: bool,
+    /// Water tiles since the last platform.
+    gap: u8,
+    /// Steps it takes the river to move a tile.
+    steps_per_tile: usize,
+    /// Cycles in `0..steps_per_tile`.
+    offset: usize,
+    dive_pos: usize,
+    /// Whether the cells moved a tile during the last update.
+    moved: bool,
 }
 
-impl Block {
-    fn color_coded(&self) -> Vec<ColoredChar> {
-        self.render_len(TILE_WIDTH)
+impl River {
+    /// A river starting with `cells` flowing at the default speed, random turtles and
+    /// crocodiles follow.
+    pub fn new(left: bool, cells: [RiverCell; STRIPE_LENGTH]) -> Self {
+        River {
+            cells,
+            left,
+            platform_left: 0,
+            crocodile: false,
+            gap: 0,
+            steps_per_tile: DEFAULT_STEPS_PER_TILE,
+            offset: 0,
+            dive_pos: DIVE_CYCLE,
+            moved: false,
+        }
     }
 
-    fn render_len(&self, len: usize) -> Vec<ColoredChar> {
-        let mut block = Vec::new();
-        for _ in 0..len {
-            block.push(self.to_char())
+    fn generate(rng: &mut impl Rng, difficulty: &Difficulty) -> Self {
+        let mut river = River::new(rng.random(), [RiverCell::Water; STRIPE_LENGTH]);
+        river.steps_per_tile = LANE_STEPS_PER_TILE[WeightedIndex::new(difficulty.lane_speed_weights).unwrap().sample(rng)];
+        for _ in 0..STRIPE_LENGTH {
+            river.advance_river(rng);
         }
-        block
+        river
     }
 
-    fn to_char(&self) -> ColoredChar {
-        match self {
-            Block::Green => ColoredChar::Green,
-            Block::BrightGreen => ColoredChar::BrightGreen,
-            Block::White => ColoredChar::White,
-            Block::Gray => ColoredChar::Gray,
-            Block::DarkYellow => ColoredChar::DarkYellow,
-            Block::Red => ColoredChar::Red,
-            Block::Black => ColoredChar::Black,
+    pub fn left(&self) -> bool {
+        self.left
+    }
+
+    pub fn cells(&self) -> [RiverCell; STRIPE_LENGTH] {
+        self.cells
+    }
+
+    /// Whether the turtles are under water.
+    pub fn diving(&self) -> bool {
+        self.dive_pos < DIVE_STEPS
+    }
+
+    fn update(&mut self, rng: &mut impl Rng) {
+        self.dive_pos = self.dive_pos.checked_sub(1).unwrap_or(DIVE_CYCLE);
+        self.offset += 1;
+        self.offset %= self.steps_per_tile;
+        self.moved = self.offset == 0;
+        if self.moved {
+            self.advance_river(rng);
         }
     }
-}
 
+    /// Moves the cells by a tile. Turtles come in groups of two to three, crocodiles are three
+    /// tiles long and head first, with one to two tiles of water in between.
+    fn advance_river(&mut self, rng: &mut impl Rng) {
+        let new_cell = if self.platform_left > 0 {
+            self.platform_left -= 1;
+            if self.crocodile { RiverCell::CrocodileBack } else { RiverCell::Turtle }
+        } else if self.gap >= 2 || (self.gap == 1 && rng.random()) {
+            self.gap = 0;
+            self.crocodile = rng.random_ratio(1, 3);
+            if self.crocodile {
+                self.platform_left = 2;
+                RiverCell::CrocodileHead
+            } else {
+                self.platform_left = rng.random_range(1..=2);
+                RiverCell::Turtle
+            }
+        } else {
+            self.gap += 1;
+            RiverCell::Water
+        };
+
+        if self.left {
+            self.cells.rotate_left(1);
+            self.cells[STRIPE_LENGTH - 1] = new_cell;
+        } else {
+            self.cells.rotate_right(1);
+            self.cells[0] = new_cell;
+        }
+    }
+
+    fn collides(&self, x: u8) -> bool {
+        match self.cells[x as usize] {
+            RiverCell::Water | RiverCell::CrocodileHead => true,
+            RiverCell::Turtle => self.diving(),
+            RiverCell::CrocodileBack => false,
+        }
+    }
+
+    fn visualize(&self) -> StripeRender {
+        let blinking = self.dive_pos < DIVE_STEPS + DIVE_WARN_STEPS && (self.dive_pos / 3).is_multiple_of(2);
+        let blocks: [Block; STRIPE_LENGTH] = core::array::from_fn(|i| match self.cells[i] {
+            RiverCell::Water => Block::Blue,
+            RiverCell::Turtle if blinking || self.diving() => Block::Blue,
+            RiverCell::Turtle => Block::Brown,
+            RiverCell::CrocodileBack => Block::Green,
+            RiverCell::CrocodileHead => Block::Red,
+        });
+        let mut render = StripeRender::new(blocks, Some(Offset {
+            offset: self.offset * TILE_WIDTH / self.steps_per_tile,
+            left: self.left,
+            fill: Block::Blue,
+        }));
+        render.river = true;
+        render
+    }
+}
 
-#[derive(Debug, Copy, Clone, PartialEq)]
-enum ColoredChar {
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Block {
     Green,
     BrightGreen,
     White,
@@ -252,20 +951,52 @@ enum ColoredChar {
     DarkYellow,
     Red,
     Black,
+    Magenta,
+    Blue,
+    Brown,
 }
 
-impl ColoredChar {
-    fn get_color(&self, block: &char) -> String {
+impl Block {
+    pub const ALL: [Block; 10] = [
+        Block::Green,
+        Block::BrightGreen,
+        Block::White,
+        Block::Gray,
+        Block::DarkYellow,
+        Block::Red,
+        Block::Black,
+        Block::Magenta,
+        Block::Blue,
+        Block::Brown,
+    ];
+
+    /// Name used by scripts and themes.
+    pub fn name(self) -> &'static str {
         match self {
-            ColoredChar::Green => block.dark_green().to_string(),
-            ColoredChar::BrightGreen => block.green().to_string(),
-            ColoredChar::White => block.white().to_string(),
-            ColoredChar::Gray => block.dark_grey().to_string(),
-            ColoredChar::DarkYellow => block.dark_yellow().to_string(),
-            ColoredChar::Red => block.red().to_string(),
-            ColoredChar::Black => block.black().to_string(),
+            Block::Green => "green",
+            Block::BrightGreen => "bright_green",
+            Block::White => "white",
+            Block::Gray => "gray",
+            Block::DarkYellow => "dark_yellow",
+            Block::Red => "red",
+            Block::Black => "black",
+            Block::Magenta => "magenta",
+            Block::Blue => "blue",
+            Block::Brown => "brown",
         }
     }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|block| block.name() == name)
+    }
+
+    fn color_coded(self) -> Vec<Block> {
+        self.render_len(TILE_WIDTH)
+    }
+
+    fn render_len(self, len: usize) -> Vec<Block> {
+        vec![self; len]
+    }
 }
 
 pub struct StripeRender {
@@ -274,6 +1005,41 @@ pub struct StripeRender {
     offset: Option<Offset>,
 
     overlay: [Option<Block>; STRIPE_LENGTH],
+
+    /// Whether red blocks are train wagons instead of cars.
+    train: bool,
+
+    /// Whether green and red blocks are crocodiles instead of trees and cars.
+    river: bool,
+
+    /// Traffic light drawn in the column vehicles enter from.
+    light: Option<Block>,
+
+    /// Tile drawn as a zebra crossing on top of vehicles.
+    crossing: Option<usize>,
+
+    /// Characters of the railroad crossing gates closing in from both edges.
+    gates: usize,
+
+    /// Rocks, bushes and flowers drawn in the middle of grass tiles.
+    decor: [Option<GrassTile>; STRIPE_LENGTH],
+
+    /// Whether checkpoint flags are drawn in the outer tiles.
+    flags: bool,
+
+    /// Players whose arrows are drawn on top of the overlay.
+    players: Vec<PlayerSprite>,
+}
+
+/// A player drawn on a stripe.
+#[derive(Debug, Copy, Clone)]
+struct PlayerSprite {
+    /// The tile the player stands on.
+    idx: usize,
+    character: Character,
+    facing: Facing,
+    /// Hop the player is in the middle of.
+    hop: Option<Hop>,
 }
 
 impl StripeRender {
@@ -281,80 +1047,230 @@ impl StripeRender {
         Self::new([Block::Black; STRIPE_LENGTH], None)
     }
 
-    fn new(blocks: [Block; STRIPE_LENGTH], offset: Option<Offset>) -> Self {
+    pub(crate) fn new(blocks: [Block; STRIPE_LENGTH], offset: Option<Offset>) -> Self {
         StripeRender {
             blocks,
             offset,
             overlay: [None; STRIPE_LENGTH],
+            train: false,
+            river: false,
+            light: None,
+            crossing: None,
+            gates: 0,
+            decor: [None; STRIPE_LENGTH],
+            flags: false,
+            players: Vec::new(),
         }
     }
 
-    /// Renders everything but the overlay to row of colored characters.
-    fn render_base(&self) -> Vec<ColoredChar> {
-        let mut res = Vec::new();
-        let mut blocks = self.blocks.iter().enumerate();
-
-        // First block. Requires special handling to apply offset for the rest of the stripe
-        if let Some(offset) = &self.offset {
+    /// Renders everything but the overlay to a row of blocks, one per character.
+    ///
+    /// Moving stripes are shifted by their offset unless `animate` is false, the gap at the edge
+    /// they move away from is filled and the part moving past the other edge is cut off.
+    fn render_base(&self, animate: bool) -> Vec<Block> {
+        let mut res = self.blocks.iter().flat_map(|block| block.color_coded()).collect::<Vec<_>>();
+        if let Some(offset) = self.offset.as_ref().filter(|_| animate) {
+            let width = res.len();
+            let fill = offset.fill.render_len(offset.offset);
             if offset.left {
-                let (_, first) = blocks.next().unwrap();
-                res.append(&mut first.render_len((TILE_WIDTH - offset.offset).max(0)));
+                res.drain(..offset.offset);
+                res.extend(fill);
             } else {
-                res.append(&mut offset.fill.render_len(offset.offset))
+                res.truncate(width - offset.offset);
+                res.splice(0..0, fill);
             }
         }
+        res
+    }
 
-        // Middle
-        while let Some((idx, block)) = blocks.next() {
-            res.append(&mut block.color_coded());
-            if idx == 5 && self.offset.as_ref().is_some_and(|o| !o.left) {
-                break;
-            }
+    /// Renders the `tiles` of the stripe as row `y`, which some themes use for shading. Wide
+    /// glyphs cut in half at the edges become spaces.
+    pub fn render(&self, phase: WallOfDeathPhase, theme: &Theme, y: u64, tiles: std::ops::Range<usize>) -> Vec<Cell> {
+        let mut cells = self.render_all(phase, theme, y);
+        let (start, end) = (tiles.start * TILE_WIDTH, tiles.end * TILE_WIDTH);
+        if cells[start].is_continuation() {
+            cells[start].glyph = ' ';
         }
-
-        // End. Requires special handling to cancel offset effects
-        if let Some(offset) = &self.offset {
-            if offset.left {
-                res.append(&mut offset.fill.render_len(offset.offset));
-            } else {
-                let (_, first) = blocks.next().unwrap();
-                res.append(&mut first.render_len((3 - offset.offset).max(0)));
-            }
+        if cells.get(end).is_some_and(Cell::is_continuation) {
+            cells[end - 1].glyph = ' ';
         }
-        res
+        cells[start..end].to_vec()
     }
 
-    pub fn render(&self, phase: WallOfDeathPhase) -> String {
-        let mut stripe = self.render_base();
+    /// Renders all tiles of the stripe as row `y`.
+    fn render_all(&self, phase: WallOfDeathPhase, theme: &Theme, y: u64) -> Vec<Cell> {
+        let mut base = self.render_base(!theme.accessibility.reduced_motion);
+        if let Some(crossing) = self.crossing {
+            for i in 0..TILE_WIDTH {
+                base[crossing * TILE_WIDTH + i] = if i % 2 == 0 { Block::White } else { Block::Gray };
+            }
+        }
+        for i in 0..self.gates {
+            let block = if i % 2 == 0 { Block::White } else { Block::Red };
+            let last = base.len() - 1;
+            base[i] = block;
+            base[last - i] = block;
+        }
+        if let Some(light) = self.light {
+            let entry = if self.offset.as_ref().is_some_and(|offset| offset.left) { base.len() - 1 } else { 0 };
+            base[entry] = light;
+        }
+        let mut stripe = base.clone();
 
         // apply overlay
+        let animate = matches!(phase, WallOfDeathPhase::Normal)
+            && !theme.accessibility.reduced_motion
+            && theme.accessibility.player_marker == PlayerMarker::Block;
+        let hop = |player: &PlayerSprite| player.hop.filter(|_| animate);
         for (idx, block) in self.overlay.iter().enumerate() {
+            if self.players.iter().any(|player| player.idx == idx && hop(player).is_some_and(|hop| hop.shift() != 0)) {
+                continue;
+            }
             if let Some(block) = block {
                 for i in 0..TILE_WIDTH {
-                    stripe[idx * TILE_WIDTH + i] = block.to_char();
+                    stripe[idx * TILE_WIDTH + i] = *block;
                 }
             }
         }
 
+        if theme.glyphs == Glyphs::Emoji && matches!(phase, WallOfDeathPhase::Normal) {
+            return self.render_emoji(&base, &stripe, theme, y);
+        }
+
         // render
-        let block = phase.to_block_char();
-        stripe.par_iter().map(|e| e.get_color(&block)).collect()
+        let mut cells = stripe.into_iter()
+            .map(|e| Cell::colored(theme.glyph(e, phase), theme.color_at(e, y)))
+            .collect::<Vec<_>>();
+        if matches!(phase, WallOfDeathPhase::Normal) {
+            for (idx, tile) in self.decor.iter().enumerate() {
+                if let Some(tile) = tile && self.overlay[idx].is_none() {
+                    let x = idx * TILE_WIDTH + TILE_WIDTH / 2;
+                    let bg = if theme.glyphs == Glyphs::Blocks { theme.color_at(base[x], y) } else { Color::Reset };
+                    cells[x] = Cell { glyph: tile.glyph(theme.glyphs), fg: theme.color_at(tile.block(), y), bg };
+                }
+            }
+        }
+        if self.flags && matches!(phase, WallOfDeathPhase::Normal) {
+            for idx in [0, STRIPE_LENGTH - 1].into_iter().filter(|idx| self.overlay[*idx].is_none()) {
+                let x = idx * TILE_WIDTH + TILE_WIDTH / 2;
+                let bg = if theme.glyphs == Glyphs::Blocks { theme.color_at(base[x], y) } else { Color::Reset };
+                let glyph = if theme.glyphs == Glyphs::Ascii { 'P' } else { '⚑' };
+                cells[x] = Cell { glyph, fg: theme.color_at(Block::White, y), bg };
+            }
+        }
+        for player in self.players.iter().filter(|_| matches!(phase, WallOfDeathPhase::Normal)) {
+            let shift = hop(player).map_or(0, Hop::shift);
+            let start = (player.idx * TILE_WIDTH).saturating_add_signed(shift).min(cells.len() - TILE_WIDTH);
+            if hop(player).is_some() {
+                // lifted off the ground
+                for x in start..start + TILE_WIDTH {
+                    cells[x] = if theme.glyphs == Glyphs::Blocks {
+                        Cell { glyph: '▀', fg: theme.color_at(Block::White, y), bg: theme.color_at(base[x], y) }
+                    } else {
+                        Cell::colored(theme.glyph(Block::White, phase), theme.color_at(Block::White, y))
+                    };
+                }
+            }
+            let (glyph, block) = player.character.face(theme.glyphs, player.facing);
+            let x = start + TILE_WIDTH / 2;
+            let tile = self.overlay[player.idx].unwrap_or(Block::White);
+            let bg = if theme.glyphs == Glyphs::Blocks { theme.color_at(tile, y) } else { Color::Reset };
+            cells[x] = Cell { glyph, fg: theme.color_at(block, y), bg };
+        }
+        if theme.accessibility.player_marker == PlayerMarker::Bracketed {
+            for (idx, block) in self.overlay.iter().enumerate() {
+                if let Some(block) = block {
+                    let x = idx * TILE_WIDTH;
+                    let fg = theme.color_at(*block, y);
+                    cells[x] = Cell { glyph: '[', fg, bg: theme.color_at(base[x], y) };
+                    cells[x + TILE_WIDTH - 1] = Cell { glyph: ']', fg, bg: theme.color_at(base[x + TILE_WIDTH - 1], y) };
+                }
+            }
+        }
+        cells
+    }
+
+    /// Draws the player, trees and vehicles as emoji on the color of the ground below them.
+    ///
+    /// An emoji takes two of the three columns of a tile, a single column left of a tree or a
+    /// shifted vehicle is only drawn in its color.
+    fn render_emoji(&self, base: &[Block], stripe: &[Block], theme: &Theme, y: u64) -> Vec<Cell> {
+        let sprite = |x: usize| if stripe[x] != base[x] {
+            let player = self.players.iter().find(|player| player.idx == x / TILE_WIDTH);
+            Some(player.map_or(Character::Chicken, |player| player.character).emoji())
+        } else if let Some(tile) = self.decor[x / TILE_WIDTH] {
+            Some(tile.glyph(Glyphs::Emoji))
+        } else if self.flags && [0, STRIPE_LENGTH - 1].contains(&(x / TILE_WIDTH)) {
+            Some('🚩')
+        } else {
+            match base[x] {
+                Block::Green if self.river => Some('🐊'),
+                Block::Brown => Some('🐢'),
+                Block::Red if self.river => None,
+                Block::Green => Some('🌲'),
+                Block::Red if self.train => Some('🚆'),
+                Block::Red => Some('🚗'),
+                Block::Magenta => Some('🚚'),
+                _ => None,
+            }
+        };
+        let ground = |block: Block| match block {
+            Block::Green | Block::Red | Block::Brown if self.river => Block::Blue,
+            Block::Green => Block::BrightGreen,
+            Block::Red | Block::Magenta => Block::Gray,
+            block => block,
+        };
+
+        let mut cells = Vec::with_capacity(stripe.len());
+        let mut x = 0;
+        while x < stripe.len() {
+            let bg = theme.color_at(ground(base[x]), y);
+            match sprite(x) {
+                Some(glyph) if x + 1 < stripe.len() && sprite(x + 1) == Some(glyph) => {
+                    cells.push(Cell { glyph, fg: Color::Reset, bg });
+                    cells.push(Cell::continuation(bg));
+                    x += 2;
+                }
+                sprite => {
+                    let bg = if sprite.is_some() && stripe[x] == base[x] { theme.color_at(stripe[x], y) } else { bg };
+                    cells.push(Cell { glyph: ' ', fg: Color::Reset, bg });
+                    x += 1;
+                }
+            }
+        }
+        cells
     }
 
     pub fn add_overlay(&mut self, idx: usize, block: Block) {
         self.overlay[idx] = Some(block);
     }
+
+    /// Draws a player as `character` facing `facing` on tile `idx`.
+    pub fn add_player(&mut self, idx: usize, character: Character, facing: Facing) {
+        self.add_overlay(idx, Block::White);
+        self.players.push(PlayerSprite { idx, character, facing, hop: None });
+    }
+
+    /// Draws the player added last in the middle of `hop`, see [StripeRender::add_player].
+    pub fn animate_player(&mut self, hop: Hop) {
+        if let Some(player) = self.players.last_mut() {
+            player.hop = Some(hop);
+        }
+    }
 }
 
-struct Offset {
-    /// How many third of a block the render should be offset.
+/// Movement of a stripe between two tiles, used to animate it by thirds of a tile.
+pub(crate) struct Offset {
+    /// How many thirds of a tile the blocks moved since they were aligned with the tiles.
     offset: usize,
+    /// Block moving in at the edge the stripe moves away from.
     fill: Block,
     /// Weather offset should be applied to the left instead of the right.
     left: bool,
 }
 
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum WallOfDeathPhase {
     Normal,
     Muddy,
@@ -364,7 +1280,7 @@ pub enum WallOfDeathPhase {
 }
 
 impl WallOfDeathPhase {
-    fn to_block_char(&self) -> char {
+    pub(crate) fn to_block_char(self) -> char {
         match self {
             WallOfDeathPhase::Normal => '█',
             WallOfDeathPhase::Muddy => '▓',
@@ -373,4 +1289,4 @@ impl WallOfDeathPhase {
             WallOfDeathPhase::Gone => ' ',
         }
     }
-}
\ No newline at end of file
+}
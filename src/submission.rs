@@ -0,0 +1,324 @@
+//! Signed claims of a score, for leaderboards that want to check them.
+//!
+//! A [Submission] holds everything needed to play a run again: the mode, the character, the
+//! keyboard layout the keys were pressed on, the [SeedCode] of the board and the keys as an input
+//! script, see [crate::input]. It is written with `--submission <file>` and stored as
+//! `key = value` lines followed by an empty line and the script:
+//!
+//! ```text
+//! # Check with crossy_terminal verify <this file>
+//! mode = endless
+//! character = chicken
+//! layout = qwerty
+//! seed-code = 2kq8rbx0f3
+//! score = 42
+//! signature = 8f3c…
+//!
+//! 10 up
+//! 10 check 5bd1e9954c6f0a31
+//! ```
+//!
+//! The signature is an HMAC-SHA256 over all other fields and the script, keyed with the
+//! [KEY_ENV] environment variable at runtime, or at build time for official builds. It keeps
+//! submissions from being edited on the way to the server, which then confirms the score itself
+//! with [Submission::verify] instead of trusting it. Builds without a key neither sign nor
+//! verify submissions, as a key everyone knows would let anyone forge them.
+//!
+//! The day of a daily run isn't written down, the seed in its code belongs to exactly one day.
+use std::fmt;
+use std::path::Path;
+use std::time::Duration;
+use crate::app::{App, Event};
+use crate::character::Character;
+use crate::input::{Desync, InputSource, ScriptError, ScriptedInput};
+use crate::keymap::KeyboardLayout;
+use crate::map::TICKS_PER_SECOND;
+use crate::mode::GameMode;
+use crate::profile::Profile;
+use crate::seed_code::{SeedCode, SeedCodeError};
+
+/// Environment variable holding the key submissions are signed with.
+pub const KEY_ENV: &str = "CROSSY_SUBMISSION_KEY";
+
+/// How long a replay goes on after the last key before the run is ended like it was quit.
+const OUTRO_TICKS: u64 = 10 * TICKS_PER_SECOND;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Submission {
+    pub mode: GameMode,
+    /// The character of the first player, some hop further than others.
+    pub character: Character,
+    pub layout: KeyboardLayout,
+    pub seed_code: SeedCode,
+    /// The score the run is claimed to have reached.
+    pub score: u64,
+    /// The keys of the run as an input script.
+    pub script: String,
+    pub signature: [u8; 32],
+}
+
+#[derive(Debug)]
+pub enum SubmissionError {
+    Io(std::io::Error),
+    /// A field is missing or can't be parsed, named by its key.
+    Invalid(&'static str),
+    SeedCode(SeedCodeError),
+    Script(ScriptError),
+    /// The run wasn't started from a seed code, or runs of its mode don't count.
+    NotSubmittable,
+    /// The signature doesn't match the contents, which were changed or signed with another key.
+    BadSignature,
+    /// Neither the environment nor the build provide a key, see [KEY_ENV].
+    NoKey,
+    Desync(Desync),
+    /// Playing the run again reached another score, [None] if it didn't end.
+    WrongScore { claimed: u64, actual: Option<u64> },
+}
+
+impl fmt::Display for SubmissionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SubmissionError::Io(err) => write!(f, "Can't access the submission: {err}"),
+            SubmissionError::Invalid(key) => write!(f, "Invalid submission: missing or invalid {key}"),
+            SubmissionError::SeedCode(err) => write!(f, "Invalid submission: {err}"),
+            SubmissionError::Script(err) => write!(f, "Invalid submission: {err}"),
+            SubmissionError::NotSubmittable => write!(f, "Only runs of modes with high scores started from a seed code can be submitted"),
+            SubmissionError::BadSignature => write!(f, "The signature of the submission doesn't match its contents"),
+            SubmissionError::NoKey => write!(f, "Submissions need a key in {KEY_ENV}, at build time or when running"),
+            SubmissionError::Desync(desync) => write!(f, "{desync}"),
+            SubmissionError::WrongScore { claimed, actual: Some(actual) } => write!(f, "The submission claims a score of {claimed}, but the run reached {actual}"),
+            SubmissionError::WrongScore { claimed, actual: None } => write!(f, "The submission claims a score of {claimed}, but the run never ended"),
+        }
+    }
+}
+
+impl std::error::Error for SubmissionError {}
+
+impl Submission {
+    /// Signs the run of `mode` on `seed_code` that reached `score` by pressing the keys of
+    /// `script`, as set up by `profile`.
+    pub fn new(mode: GameMode, profile: &Profile, seed_code: SeedCode, score: u64, script: String) -> Result<Submission, SubmissionError> {
+        if mode.name().is_none() || mode.high_score_table(&seed_code.difficulty).is_none() {
+            return Err(SubmissionError::NotSubmittable);
+        }
+        let (character, layout) = (profile.character, profile.layout.unwrap_or(KeyboardLayout::Qwerty));
+        let mut submission = Submission { mode, character, layout, seed_code, score, script, signature: [0; 32] };
+        submission.signature = hmac_sha256(&key()?, submission.signed_text().as_bytes());
+        Ok(submission)
+    }
+
+    pub fn load(path: &Path) -> Result<Submission, SubmissionError> {
+        Self::parse(&std::fs::read_to_string(path).map_err(SubmissionError::Io)?)
+    }
+
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        std::fs::write(path, self.to_text())
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = "# Check with crossy_terminal verify <this file>\n".to_string();
+        text.push_str(&self.fields());
+        text.push_str(&format!("signature = {}\n\n", to_hex(&self.signature)));
+        text.push_str(&self.script);
+        text
+    }
+
+    pub fn parse(text: &str) -> Result<Submission, SubmissionError> {
+        let (header, script) = text.split_once("\n\n").unwrap_or((text, ""));
+        let fields: Vec<(&str, &str)> = header.lines()
+            .filter(|line| !line.starts_with('#'))
+            .filter_map(|line| line.split_once('='))
+            .map(|(key, value)| (key.trim(), value.trim()))
+            .collect();
+        let field = |key: &'static str| fields.iter().find(|(name, _)| *name == key).map(|(_, value)| *value).ok_or(SubmissionError::Invalid(key));
+        let seed_code = SeedCode::parse(field("seed-code")?).map_err(SubmissionError::SeedCode)?;
+        let mut mode = GameMode::from_name(field("mode")?).ok_or(SubmissionError::Invalid("mode"))?;
+        match &mut mode {
+            GameMode::TimeAttack { seconds } => {
+                *seconds = field("time")?.parse().map_err(|_| SubmissionError::Invalid("time"))?;
+            }
+            // The seed of a daily run tells its day, so it can be checked on any later day.
+            GameMode::Daily { .. } => {
+                mode = GameMode::daily_from_seed(seed_code.seed).ok_or(SubmissionError::Invalid("seed-code"))?;
+            }
+            _ => {}
+        }
+        let character = Character::from_name(field("character")?).ok_or(SubmissionError::Invalid("character"))?;
+        let layout = KeyboardLayout::from_name(field("layout")?).ok_or(SubmissionError::Invalid("layout"))?;
+        let score = field("score")?.parse().map_err(|_| SubmissionError::Invalid("score"))?;
+        let signature = from_hex(field("signature")?).ok_or(SubmissionError::Invalid("signature"))?;
+        Ok(Submission { mode, character, layout, seed_code, score, script: script.to_string(), signature })
+    }
+
+    /// Checks the signature, then plays the run again and confirms it reaches the claimed score.
+    pub fn verify(&self) -> Result<(), SubmissionError> {
+        if hmac_sha256(&key()?, self.signed_text().as_bytes()) != self.signature {
+            return Err(SubmissionError::BadSignature);
+        }
+        let mut script = ScriptedInput::parse(&self.script).map_err(SubmissionError::Script)?;
+        let map = self.mode.create_seeded_map(self.seed_code.seed, self.seed_code.difficulty).map_err(|_| SubmissionError::NotSubmittable)?;
+        let profile = Profile { character: self.character, layout: Some(self.layout), ..Profile::default() };
+        let map = self.seed_code.apply(map, profile.character.partner());
+        let mut app = App::play(profile, self.mode.clone(), map).without_records();
+        let mut tick = 0;
+        let mut outro = OUTRO_TICKS;
+        while app.last_score().is_none() {
+            while let Some(event) = script.next_event(tick, Duration::ZERO).map_err(|err| SubmissionError::Script(ScriptError::Io(err)))? {
+                app.handle_event(event);
+            }
+            if outro == 0 {
+                // The player quit before the run ended, which scripts don't record.
+                app.shutdown();
+                break;
+            }
+            app.handle_event(Event::Tick);
+            if script.is_finished() {
+                outro -= 1;
+            }
+            tick += 1;
+            script.verify(tick, app.state_hash()).map_err(SubmissionError::Desync)?;
+        }
+        match app.last_score() {
+            Some(score) if score == self.score => Ok(()),
+            actual => Err(SubmissionError::WrongScore { claimed: self.score, actual }),
+        }
+    }
+
+    /// The `key = value` lines of everything but the signature.
+    fn fields(&self) -> String {
+        let mut fields = format!("mode = {}\n", self.mode.name().unwrap_or_default());
+        if let GameMode::TimeAttack { seconds } = self.mode {
+            fields.push_str(&format!("time = {seconds}\n"));
+        }
+        fields.push_str(&format!("character = {}\nlayout = {}\nseed-code = {}\nscore = {}\n", self.character.name(), self.layout.name(), self.seed_code, self.score));
+        fields
+    }
+
+    /// What the signature is computed over.
+    fn signed_text(&self) -> String {
+        format!("{}\n{}", self.fields(), self.script)
+    }
+}
+
+/// Whether this build can sign and verify submissions, see [KEY_ENV].
+pub fn has_key() -> bool {
+    key().is_ok()
+}
+
+/// The key submissions are signed with, see [KEY_ENV].
+fn key() -> Result<Vec<u8>, SubmissionError> {
+    std::env::var(KEY_ENV).ok()
+        .or_else(|| option_env!("CROSSY_SUBMISSION_KEY").map(str::to_string))
+        .filter(|key| !key.is_empty())
+        .map(String::into_bytes)
+        .ok_or(SubmissionError::NoKey)
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn from_hex(hex: &str) -> Option<[u8; 32]> {
+    if hex.len() != 64 || !hex.is_ascii() {
+        return None;
+    }
+    let mut bytes = [0; 32];
+    for (idx, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[idx * 2..idx * 2 + 2], 16).ok()?;
+    }
+    Some(bytes)
+}
+
+/// HMAC as in RFC 2104, with SHA-256.
+fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    let mut block = [0; 64];
+    if key.len() > block.len() {
+        block[..32].copy_from_slice(&sha256(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+    let inner: Vec<u8> = block.iter().map(|byte| byte ^ 0x36).chain(message.iter().copied()).collect();
+    let outer: Vec<u8> = block.iter().map(|byte| byte ^ 0x5c).chain(sha256(&inner)).collect();
+    sha256(&outer)
+}
+
+const SHA256_K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+/// SHA-256 as in FIPS 180-4.
+fn sha256(message: &[u8]) -> [u8; 32] {
+    let mut state: [u32; 8] = [0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19];
+    let mut padded = message.to_vec();
+    padded.push(0x80);
+    while padded.len() % 64 != 56 {
+        padded.push(0);
+    }
+    padded.extend_from_slice(&(message.len() as u64 * 8).to_be_bytes());
+    for chunk in padded.chunks_exact(64) {
+        let mut w = [0u32; 64];
+        for (idx, word) in chunk.chunks_exact(4).enumerate() {
+            w[idx] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for idx in 16..64 {
+            let s0 = w[idx - 15].rotate_right(7) ^ w[idx - 15].rotate_right(18) ^ (w[idx - 15] >> 3);
+            let s1 = w[idx - 2].rotate_right(17) ^ w[idx - 2].rotate_right(19) ^ (w[idx - 2] >> 10);
+            w[idx] = w[idx - 16].wrapping_add(s0).wrapping_add(w[idx - 7]).wrapping_add(s1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = state;
+        for idx in 0..64 {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let ch = (e & f) ^ (!e & g);
+            let t1 = h.wrapping_add(s1).wrapping_add(ch).wrapping_add(SHA256_K[idx]).wrapping_add(w[idx]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(maj);
+            (h, g, f, e, d, c, b, a) = (g, f, e, d.wrapping_add(t1), c, b, a, t1.wrapping_add(t2));
+        }
+        for (value, add) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+            *value = value.wrapping_add(add);
+        }
+    }
+    let mut digest = [0; 32];
+    for (bytes, value) in digest.chunks_exact_mut(4).zip(state) {
+        bytes.copy_from_slice(&value.to_be_bytes());
+    }
+    digest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The examples of FIPS 180-2, appendix B.
+    #[test]
+    fn sha256_matches_fips_180_2() {
+        assert_eq!(to_hex(&sha256(b"abc")), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+        assert_eq!(to_hex(&sha256(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq")), "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1");
+        assert_eq!(to_hex(&sha256(&vec![b'a'; 1_000_000])), "cdc76e5c9914fb9281a1c7e284d73e67f1809a48a497200e046d39ccc7112cd0");
+        assert_eq!(to_hex(&sha256(b"")), "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+    }
+
+    /// Test cases 1, 2, 3 and 6 of RFC 4231.
+    #[test]
+    fn hmac_sha256_matches_rfc_4231() {
+        assert_eq!(to_hex(&hmac_sha256(&[0x0b; 20], b"Hi There")), "b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7");
+        assert_eq!(to_hex(&hmac_sha256(b"Jefe", b"what do ya want for nothing?")), "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843");
+        assert_eq!(to_hex(&hmac_sha256(&[0xaa; 20], &[0xdd; 50])), "773ea91e36800e46854db8ebd09181a72959098b3ef8c122d9635514ced565fe");
+        assert_eq!(to_hex(&hmac_sha256(&[0xaa; 131], b"Test Using Larger Than Block-Size Key - Hash Key First")), "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54");
+    }
+
+    #[test]
+    fn hex_round_trips() {
+        let digest = sha256(b"crossy");
+        assert_eq!(from_hex(&to_hex(&digest)), Some(digest));
+        assert_eq!(from_hex("00"), None);
+    }
+}
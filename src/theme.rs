@@ -0,0 +1,350 @@
+//! How blocks are drawn.
+//!
+//! A theme starts from one of the presets and can override single blocks with hex colors in the
+//! profile, e.g. `theme.red = #ff8800`. Block names are the ones listed in [Block::name].
+//! Independent of the colors, blocks are drawn as shaded boxes, plain ASCII or emoji [Glyphs].
+//!
+//! The colorblind presets avoid colors that look alike with the respective deficiency and draw
+//! hazards in their own shapes, so color is never the only way to tell cars from the road or a
+//! railroad warning from a train.
+//!
+//! [Accessibility] options are kept by the profile and apply on top of any theme.
+use crate::biome::Biome;
+use crate::color::{darken, is_legacy_console, mix, Color, ColorDepth};
+use crate::locale::Language;
+use crate::stripe::{Block, WallOfDeathPhase};
+
+/// How much darker shaded rows are.
+const SHADE_PERCENT: u8 = 12;
+
+/// How far grass and trees are tinted toward the colors of their biome.
+const BIOME_TINT_PERCENT: u8 = 70;
+
+/// How much darker trees are with [Accessibility::high_contrast].
+const HIGH_CONTRAST_PERCENT: u8 = 50;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Theme {
+    /// Name of the preset the theme is based on.
+    pub name: &'static str,
+    /// Colors indexed like [Block::ALL].
+    colors: [Color; 10],
+    pub glyphs: Glyphs,
+    /// Whether cars, trains and railroad warnings get their own box glyphs.
+    pub hazard_shapes: bool,
+    /// Whether grass and trees take on the colors of their [Biome].
+    pub biome_tints: bool,
+    pub accessibility: Accessibility,
+    /// Language of the texts drawn with the board.
+    pub language: Language,
+}
+
+impl Theme {
+    pub const CLASSIC: Theme = Theme {
+        name: "classic",
+        colors: [
+            Color::DarkGreen,
+            Color::Green,
+            Color::White,
+            Color::DarkGrey,
+            Color::DarkYellow,
+            Color::Red,
+            Color::Black,
+            Color::DarkMagenta,
+            Color::DarkBlue,
+            Color::DarkYellow,
+        ],
+        glyphs: Glyphs::Blocks,
+        hazard_shapes: false,
+        biome_tints: true,
+        accessibility: Accessibility::DEFAULT,
+        language: Language::English,
+    };
+
+    pub const PASTEL: Theme = Theme {
+        name: "pastel",
+        colors: [
+            Color::Rgb { r: 0x7c, g: 0xb3, b: 0x8a },
+            Color::Rgb { r: 0xb8, g: 0xe0, b: 0xb0 },
+            Color::Rgb { r: 0xfa, g: 0xf6, b: 0xf0 },
+            Color::Rgb { r: 0xa8, g: 0xa8, b: 0xb8 },
+            Color::Rgb { r: 0xf5, g: 0xd7, b: 0x8e },
+            Color::Rgb { r: 0xf2, g: 0x8b, b: 0x8b },
+            Color::Rgb { r: 0x4a, g: 0x4a, b: 0x58 },
+            Color::Rgb { r: 0xc9, g: 0xa0, b: 0xdc },
+            Color::Rgb { r: 0x9e, g: 0xc9, b: 0xe8 },
+            Color::Rgb { r: 0xc8, g: 0xa8, b: 0x82 },
+        ],
+        glyphs: Glyphs::Blocks,
+        hazard_shapes: false,
+        biome_tints: true,
+        accessibility: Accessibility::DEFAULT,
+        language: Language::English,
+    };
+
+    pub const MONOCHROME: Theme = Theme {
+        name: "monochrome",
+        colors: [
+            Color::Grey,
+            Color::DarkGrey,
+            Color::White,
+            Color::Black,
+            Color::DarkGrey,
+            Color::Grey,
+            Color::Black,
+            Color::White,
+            Color::Black,
+            Color::Grey,
+        ],
+        glyphs: Glyphs::Blocks,
+        hazard_shapes: false,
+        biome_tints: false,
+        accessibility: Accessibility::DEFAULT,
+        language: Language::English,
+    };
+
+    /// For deuteranopia and protanopia: cars are blue instead of red next to the yellow warning.
+    pub const DEUTERANOPIA: Theme = Theme {
+        name: "deuteranopia",
+        colors: [
+            Color::Rgb { r: 0x00, g: 0x6e, b: 0x55 },
+            Color::Rgb { r: 0xb8, g: 0xe3, b: 0xcf },
+            Color::Rgb { r: 0xff, g: 0xff, b: 0xff },
+            Color::Rgb { r: 0x5a, g: 0x5a, b: 0x5a },
+            Color::Rgb { r: 0xf0, g: 0xe4, b: 0x42 },
+            Color::Rgb { r: 0x00, g: 0x72, b: 0xb2 },
+            Color::Rgb { r: 0x00, g: 0x00, b: 0x00 },
+            Color::Rgb { r: 0x56, g: 0xb4, b: 0xe9 },
+            Color::Rgb { r: 0x1f, g: 0x3a, b: 0x68 },
+            Color::Rgb { r: 0xe6, g: 0x9f, b: 0x00 },
+        ],
+        glyphs: Glyphs::Blocks,
+        hazard_shapes: true,
+        biome_tints: true,
+        accessibility: Accessibility::DEFAULT,
+        language: Language::English,
+    };
+
+    /// For tritanopia: the warning is pink and much lighter than the dark red vehicles.
+    pub const TRITANOPIA: Theme = Theme {
+        name: "tritanopia",
+        colors: [
+            Color::Rgb { r: 0x1b, g: 0x5e, b: 0x20 },
+            Color::Rgb { r: 0x9c, g: 0xd3, b: 0x8a },
+            Color::Rgb { r: 0xff, g: 0xff, b: 0xff },
+            Color::Rgb { r: 0x60, g: 0x60, b: 0x60 },
+            Color::Rgb { r: 0xff, g: 0xa0, b: 0xc8 },
+            Color::Rgb { r: 0xb0, g: 0x00, b: 0x00 },
+            Color::Rgb { r: 0x00, g: 0x00, b: 0x00 },
+            Color::Rgb { r: 0x6a, g: 0x1b, b: 0x9a },
+            Color::Rgb { r: 0x00, g: 0x4d, b: 0x60 },
+            Color::Rgb { r: 0xd0, g: 0xb0, b: 0x80 },
+        ],
+        glyphs: Glyphs::Blocks,
+        hazard_shapes: true,
+        biome_tints: true,
+        accessibility: Accessibility::DEFAULT,
+        language: Language::English,
+    };
+
+    pub const ALL: [Theme; 5] = [
+        Theme::CLASSIC,
+        Theme::PASTEL,
+        Theme::MONOCHROME,
+        Theme::DEUTERANOPIA,
+        Theme::TRITANOPIA,
+    ];
+
+    pub fn from_name(name: &str) -> Option<Theme> {
+        Self::ALL.into_iter().find(|theme| theme.name == name)
+    }
+
+    pub fn color(&self, block: Block) -> Color {
+        self.colors[Self::index(block)]
+    }
+
+    /// The color of `block` in row `y` as the current terminal can display it.
+    ///
+    /// With true colors grass and trees are tinted by the [Biome] of the row and every other row
+    /// of them is slightly darker. With high contrast trees are much darker than usual.
+    pub fn color_at(&self, block: Block, y: u64) -> Color {
+        let mut color = self.color(block);
+        let truecolor = ColorDepth::current() == ColorDepth::TrueColor;
+        if truecolor && self.biome_tints && let Some((grass, tree)) = Biome::at(y).tint() {
+            match block {
+                Block::BrightGreen => color = mix(color, grass, BIOME_TINT_PERCENT),
+                Block::Green => color = mix(color, tree, BIOME_TINT_PERCENT),
+                _ => {}
+            }
+        }
+        if self.accessibility.high_contrast && block == Block::Green {
+            color = darken(color, HIGH_CONTRAST_PERCENT);
+        }
+        match ColorDepth::current() {
+            ColorDepth::TrueColor if y % 2 == 1 && matches!(block, Block::Green | Block::BrightGreen) => {
+                darken(color, SHADE_PERCENT)
+            }
+            depth => depth.adapt(color),
+        }
+    }
+
+    pub fn set_color(&mut self, block: Block, color: Color) {
+        self.colors[Self::index(block)] = color;
+    }
+
+    /// The blocks whose color differs from the preset, as `(block, #rrggbb)`.
+    pub fn overrides(&self) -> Vec<(Block, String)> {
+        let preset = Theme::from_name(self.name).unwrap_or_default();
+        Block::ALL.into_iter()
+            .filter(|block| self.color(*block) != preset.color(*block))
+            .filter_map(|block| match self.color(block) {
+                Color::Rgb { r, g, b } => Some((block, format!("#{r:02x}{g:02x}{b:02x}"))),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// [Glyphs::glyph] of the themes glyphs, with hazard shapes if the theme uses them.
+    pub fn glyph(&self, block: Block, phase: WallOfDeathPhase) -> char {
+        let shapes = self.hazard_shapes && self.glyphs == Glyphs::Blocks;
+        match (block, phase) {
+            (Block::Red | Block::Magenta, WallOfDeathPhase::Normal) if shapes => '▚',
+            (Block::DarkYellow, WallOfDeathPhase::Normal) if shapes => '╳',
+            _ => self.glyphs.glyph(block, phase),
+        }
+    }
+
+    fn index(block: Block) -> usize {
+        Block::ALL.iter().position(|b| *b == block).unwrap()
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::CLASSIC
+    }
+}
+
+/// Settings for players that have trouble following the default rendering.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct Accessibility {
+    /// Moves vehicles tile by tile instead of animating them in thirds of a tile.
+    pub reduced_motion: bool,
+    /// Darkens trees to stand out from the grass.
+    pub high_contrast: bool,
+    pub player_marker: PlayerMarker,
+}
+
+impl Accessibility {
+    pub const DEFAULT: Accessibility = Accessibility {
+        reduced_motion: false,
+        high_contrast: false,
+        player_marker: PlayerMarker::Block,
+    };
+}
+
+/// How the player is told apart from the board.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum PlayerMarker {
+    /// A plain white tile.
+    #[default]
+    Block,
+    /// A white tile between brackets, like `[█]`.
+    Bracketed,
+    /// A white tile that blinks.
+    Blinking,
+}
+
+impl PlayerMarker {
+    pub const ALL: [PlayerMarker; 3] = [PlayerMarker::Block, PlayerMarker::Bracketed, PlayerMarker::Blinking];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PlayerMarker::Block => "block",
+            PlayerMarker::Bracketed => "bracketed",
+            PlayerMarker::Blinking => "blinking",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|marker| marker.name() == name)
+    }
+}
+
+/// The characters blocks are drawn with.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum Glyphs {
+    /// Full and shaded boxes like `█`.
+    #[default]
+    Blocks,
+    /// Plain characters for fonts and terminals that lack the box characters.
+    Ascii,
+    /// Double-width emoji for the player, trees and vehicles on colored backgrounds.
+    Emoji,
+}
+
+impl Glyphs {
+    pub const ALL: [Glyphs; 3] = [Glyphs::Blocks, Glyphs::Ascii, Glyphs::Emoji];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Glyphs::Blocks => "blocks",
+            Glyphs::Ascii => "ascii",
+            Glyphs::Emoji => "emoji",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|glyphs| glyphs.name() == name)
+    }
+
+    /// Picks ASCII when the locale doesn't use UTF-8, on legacy Windows consoles and when the
+    /// terminal shows no colors, which boxes and emoji can't be told apart without.
+    pub fn detect() -> Self {
+        if ColorDepth::current() == ColorDepth::NoColor || is_legacy_console() {
+            return Glyphs::Ascii;
+        }
+        let locale = ["LC_ALL", "LC_CTYPE", "LANG"].into_iter()
+            .find_map(|var| std::env::var(var).ok().filter(|value| !value.is_empty()));
+        match locale {
+            Some(locale) if !locale.to_lowercase().replace('-', "").contains("utf8") => Glyphs::Ascii,
+            _ => Glyphs::Blocks,
+        }
+    }
+
+    /// The character of `block` in a row at the given phase of the wall of death.
+    ///
+    /// Emoji are placed by [crate::stripe::StripeRender] itself, rows crumbling away fall back to
+    /// boxes.
+    pub fn glyph(self, block: Block, phase: WallOfDeathPhase) -> char {
+        match (self, phase) {
+            (Glyphs::Blocks | Glyphs::Emoji, phase) => phase.to_block_char(),
+            (Glyphs::Ascii, WallOfDeathPhase::Normal) => match block {
+                Block::Green => '#',
+                Block::BrightGreen => '.',
+                Block::White => '@',
+                Block::Gray => '=',
+                Block::DarkYellow => '!',
+                Block::Red => 'o',
+                Block::Black => ' ',
+                Block::Magenta => 'H',
+                Block::Blue => '~',
+                Block::Brown => 'T',
+            },
+            (Glyphs::Ascii, WallOfDeathPhase::Muddy) => '%',
+            (Glyphs::Ascii, WallOfDeathPhase::Shaky) => ':',
+            (Glyphs::Ascii, WallOfDeathPhase::Risky) => ',',
+            (Glyphs::Ascii, WallOfDeathPhase::Gone) => ' ',
+        }
+    }
+}
+
+/// Parses colors like `#ff8800`.
+pub fn parse_hex(hex: &str) -> Option<Color> {
+    let hex = hex.strip_prefix('#')?;
+    if hex.len() != 6 {
+        return None;
+    }
+    let channel = |idx: usize| u8::from_str_radix(hex.get(idx..idx + 2)?, 16).ok();
+    Some(Color::Rgb { r: channel(0)?, g: channel(2)?, b: channel(4)? })
+}
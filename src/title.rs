@@ -0,0 +1,55 @@
+//! Terminal commands for the window title and taskbar progress that crossterm doesn't provide.
+use std::fmt;
+use crossterm::Command;
+
+/// Saves the current window title on the terminals title stack (XTWINOPS 22).
+pub struct PushTitle;
+
+impl Command for PushTitle {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        f.write_str("\x1b[22;0t")
+    }
+
+    #[cfg(windows)]
+    fn execute_winapi(&self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Restores the window title saved by [PushTitle] (XTWINOPS 23).
+pub struct PopTitle;
+
+impl Command for PopTitle {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        f.write_str("\x1b[23;0t")
+    }
+
+    #[cfg(windows)]
+    fn execute_winapi(&self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Taskbar progress through `OSC 9;4`, as supported by Windows Terminal and ConEmu.
+///
+/// Terminals that don't know the sequence ignore it.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SetProgress {
+    Hidden,
+    /// Progress in percent, values above 100 are capped.
+    Normal(u8),
+}
+
+impl Command for SetProgress {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        match self {
+            SetProgress::Hidden => f.write_str("\x1b]9;4;0;0\x07"),
+            SetProgress::Normal(percent) => write!(f, "\x1b]9;4;1;{}\x07", percent.min(&100)),
+        }
+    }
+
+    #[cfg(windows)]
+    fn execute_winapi(&self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
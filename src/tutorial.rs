@@ -0,0 +1,73 @@
+//! A guided first run teaching the controls and hazards one at a time.
+//!
+//! The board is fixed: a few safe rows, one slow road, one railroad and the finish line. Each
+//! [Lesson] shows a prompt and only moves on to the next one once the player did what it asks.
+use crate::level::Level;
+use crate::map::{Facing, MapState};
+use crate::stripe::{self, GreenStripe, Railroad, Road, Stripe, VISIBLE_TILES};
+
+/// Row of the road, counted from the bottom one.
+const ROAD_Y: u64 = 6;
+
+/// Row of the railroad, counted from the bottom one.
+const RAIL_Y: u64 = 9;
+
+/// Steps between two trains on the railroad.
+const RAIL_CYCLE: usize = 60;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum Lesson {
+    #[default]
+    Hop,
+    Sideways,
+    Back,
+    Road,
+    Rail,
+    Finish,
+}
+
+impl Lesson {
+    /// What the player is asked to do, with `keys` naming the keys for hopping up, left, down and
+    /// right.
+    pub fn prompt(self, keys: &[String; 4]) -> String {
+        let [up, left, down, right] = keys;
+        match self {
+            Lesson::Hop => format!("Press {up} to hop forward."),
+            Lesson::Sideways => format!("Press {left} or {right} to hop sideways. Trees block the way."),
+            Lesson::Back => format!("Press {down} to hop back."),
+            Lesson::Road => "Cars drive on the road ahead. Wait for a gap, then cross it.".to_string(),
+            Lesson::Rail => "Trains are fast. When the railroad flashes, one is coming. Cross it when it's quiet.".to_string(),
+            Lesson::Finish => "Well done! Hop over the finish line to complete the tutorial.".to_string(),
+        }
+    }
+
+    /// The lesson after this one if the player just hopped toward `hop`, if at all, and is on
+    /// `map` now.
+    pub fn advance(self, hop: Option<Facing>, map: &MapState) -> Lesson {
+        match (self, hop) {
+            (Lesson::Hop, Some(Facing::Up)) => Lesson::Sideways,
+            (Lesson::Sideways, Some(Facing::Left | Facing::Right)) => Lesson::Back,
+            (Lesson::Back, Some(Facing::Down)) => Lesson::Road,
+            (Lesson::Road, _) if map.player_y() > ROAD_Y => Lesson::Rail,
+            (Lesson::Rail, _) if map.player_y() > RAIL_Y => Lesson::Finish,
+            (lesson, _) => lesson,
+        }
+    }
+}
+
+/// The board of the tutorial. There is no wall of death, so the player can take their time.
+pub fn map() -> MapState {
+    // Trees keep the player on the tiles of the screen.
+    let green = |trees: [bool; VISIBLE_TILES]| Stripe::Green(GreenStripe::new(stripe::centered(trees, true)));
+    let empty = green([false; VISIBLE_TILES]);
+    let mut rows = vec![empty; ROAD_Y as usize];
+    rows[4] = green([true, true, false, false, false, true, true]);
+    let mut cars = [false; VISIBLE_TILES];
+    cars[0] = true;
+    rows.push(Stripe::Road(Road::new(true, stripe::centered(cars, false)).slow()));
+    rows.extend([empty; (RAIL_Y - ROAD_Y - 1) as usize]);
+    rows.push(Stripe::Rail(Railroad::new(RAIL_CYCLE)));
+    rows.extend([empty; 3]);
+    rows.push(Stripe::Finish);
+    MapState::from_level(&Level { name: Some("Tutorial".to_string()), rows }).without_wall_of_death()
+}
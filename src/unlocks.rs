@@ -0,0 +1,149 @@
+//! Characters, themes, modes and modifiers earned by playing.
+//!
+//! Each entry of [UNLOCKS] becomes available once the lifetime [Stats] of the profile meet its
+//! [Requirement]. Everything else is available from the start, including the themes for color
+//! vision deficiencies. Unlocked items are stored as a `key = value` line in `unlocks.conf`
+//! inside [profile_dir], so they stay unlocked if a requirement changes later.
+//!
+//! Menus show locked items greyed out with what is left to do, the command line refuses them.
+//! Seed codes and replays always play, so challenges from friends work for everyone.
+use crate::character::Character;
+use crate::locale::{Language, Text};
+use crate::profile::{format_entries, parse_entries, profile_dir};
+use crate::stats::Stats;
+use crate::weather::Weather;
+
+const UNLOCKS_FILE: &str = "unlocks.conf";
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Unlockable {
+    Character(Character),
+    /// A theme by name.
+    Theme(&'static str),
+    /// A mode by [crate::mode::GameMode::name].
+    Mode(&'static str),
+    /// A difficulty by name.
+    Difficulty(&'static str),
+    Weather(Weather),
+}
+
+impl Unlockable {
+    /// Identifies the item in `unlocks.conf`, e.g. `character.frog`.
+    pub fn id(self) -> String {
+        match self {
+            Unlockable::Character(character) => format!("character.{}", character.name()),
+            Unlockable::Theme(name) => format!("theme.{name}"),
+            Unlockable::Mode(name) => format!("mode.{name}"),
+            Unlockable::Difficulty(name) => format!("difficulty.{name}"),
+            Unlockable::Weather(weather) => format!("weather.{}", weather.name()),
+        }
+    }
+
+    /// Names the item in messages, e.g. `the night mode`.
+    pub fn title(self, language: Language) -> String {
+        match self {
+            Unlockable::Character(character) => language.format(Text::UnlockableCharacter, &[&character.name()]),
+            Unlockable::Theme(name) => language.format(Text::UnlockableTheme, &[&name]),
+            Unlockable::Mode(name) => language.format(Text::UnlockableMode, &[&name]),
+            Unlockable::Difficulty(name) => language.format(Text::UnlockableDifficulty, &[&name]),
+            Unlockable::Weather(weather) => language.format(Text::UnlockableWeather, &[&weather.name()]),
+        }
+    }
+}
+
+/// What the lifetime [Stats] have to reach to unlock an item.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Requirement {
+    RowsCrossed(u64),
+    GamesPlayed(u64),
+    BestScore(u64),
+    NearMisses(u64),
+}
+
+impl Requirement {
+    pub fn is_met(self, stats: &Stats) -> bool {
+        match self {
+            Requirement::RowsCrossed(rows) => stats.rows_crossed >= rows,
+            Requirement::GamesPlayed(games) => stats.games_played >= games,
+            Requirement::BestScore(score) => stats.best_score >= score,
+            Requirement::NearMisses(misses) => stats.near_misses >= misses,
+        }
+    }
+
+    /// What is left to do for it, e.g. `cross 120 more rows`.
+    pub fn describe(self, stats: &Stats, language: Language) -> String {
+        match self {
+            Requirement::RowsCrossed(rows) => language.format(Text::RequireRows, &[&rows.saturating_sub(stats.rows_crossed)]),
+            Requirement::GamesPlayed(games) => language.format(Text::RequireGames, &[&games.saturating_sub(stats.games_played)]),
+            Requirement::BestScore(score) => language.format(Text::RequireScore, &[&score]),
+            Requirement::NearMisses(misses) => language.format(Text::RequireNearMisses, &[&misses.saturating_sub(stats.near_misses)]),
+        }
+    }
+}
+
+/// The items that have to be unlocked and what unlocks them.
+pub const UNLOCKS: [(Unlockable, Requirement); 7] = [
+    (Unlockable::Character(Character::Frog), Requirement::RowsCrossed(100)),
+    (Unlockable::Character(Character::Duck), Requirement::BestScore(50)),
+    (Unlockable::Character(Character::Robot), Requirement::RowsCrossed(1000)),
+    (Unlockable::Theme("pastel"), Requirement::GamesPlayed(25)),
+    (Unlockable::Mode("night"), Requirement::RowsCrossed(500)),
+    (Unlockable::Difficulty("hardcore"), Requirement::BestScore(100)),
+    (Unlockable::Weather(Weather::Ice), Requirement::NearMisses(50)),
+];
+
+/// The items the current profile unlocked.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Unlocks {
+    /// Ids of the unlocked items, see [Unlockable::id].
+    unlocked: Vec<String>,
+}
+
+impl Unlocks {
+    /// Loads the unlocked items, starting with none if nothing was unlocked yet.
+    pub fn load() -> Unlocks {
+        let Ok(source) = std::fs::read_to_string(profile_dir().join(UNLOCKS_FILE)) else {
+            return Unlocks::default();
+        };
+        let unlocked = parse_entries(&source)
+            .filter(|(key, _)| *key == "unlocked")
+            .flat_map(|(_, value)| value.split(',').map(|id| id.trim().to_string()))
+            .filter(|id| !id.is_empty())
+            .collect();
+        Unlocks { unlocked }
+    }
+
+    /// Loads the unlocked items and unlocks what `stats` earned on top, e.g. in runs played before
+    /// unlocks existed.
+    pub fn load_with(stats: &Stats) -> Unlocks {
+        let mut unlocks = Self::load();
+        unlocks.update(stats);
+        unlocks
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        std::fs::create_dir_all(profile_dir())?;
+        std::fs::write(profile_dir().join(UNLOCKS_FILE), format_entries(&[("unlocked", self.unlocked.join(","))]))
+    }
+
+    /// The requirement left to meet for `item`, [None] if it is available.
+    pub fn requirement(&self, item: Unlockable) -> Option<Requirement> {
+        let (_, requirement) = UNLOCKS.into_iter().find(|(unlockable, _)| *unlockable == item)?;
+        (!self.unlocked.contains(&item.id())).then_some(requirement)
+    }
+
+    pub fn is_unlocked(&self, item: Unlockable) -> bool {
+        self.requirement(item).is_none()
+    }
+
+    /// Unlocks every item whose requirement `stats` meet and returns the ones that weren't
+    /// unlocked before.
+    pub fn update(&mut self, stats: &Stats) -> Vec<Unlockable> {
+        let new = UNLOCKS.into_iter()
+            .filter(|(item, requirement)| !self.is_unlocked(*item) && requirement.is_met(stats))
+            .map(|(item, _)| item)
+            .collect::<Vec<_>>();
+        self.unlocked.extend(new.iter().map(|item| item.id()));
+        new
+    }
+}
@@ -0,0 +1,136 @@
+use crate::animation::Hop;
+use crate::character::Character;
+use crate::grid::Grid;
+use crate::locale::{Language, Text};
+use crate::map::{Facing, GameEvent, MapState};
+use crate::theme::Theme;
+
+/// Rows a player has to cross to win a versus race.
+pub const VERSUS_ROWS: u64 = 100;
+
+/// Columns between the two boards.
+const BOARD_GAP: usize = 2;
+
+/// Two players racing on boards of the same seed, side by side.
+pub struct Versus {
+    boards: [MapState; 2],
+    /// The hops the players are in the middle of.
+    hops: [Option<Hop>; 2],
+    /// The player that crossed [VERSUS_ROWS] rows first.
+    first: Option<usize>,
+}
+
+impl Versus {
+    pub fn new(characters: [Character; 2]) -> Self {
+        Self::with_seed(rand::random(), characters)
+    }
+
+    pub fn with_seed(seed: u64, characters: [Character; 2]) -> Self {
+        Versus {
+            boards: characters.map(|character| MapState::with_seed(seed).with_character(character)),
+            hops: [None; 2],
+            first: None,
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.boards[0].seed()
+    }
+
+    pub fn board(&self, player: usize) -> &MapState {
+        &self.boards[player]
+    }
+
+    /// Hops `player` toward `direction` on their own board, if they are still in the race.
+    pub fn hop(&mut self, player: usize, direction: Facing) {
+        if self.is_over() || !self.boards[player].alive {
+            return;
+        }
+        let board = &mut self.boards[player];
+        let position = (board.player_x(), board.player_y());
+        board.hop(0, direction);
+        if position != (board.player_x(), board.player_y()) {
+            self.hops[player] = Some(Hop::new(direction));
+        }
+        self.check_goal(player);
+    }
+
+    /// Advances both boards by a tick until the race is over.
+    pub fn update(&mut self) {
+        for hop in &mut self.hops {
+            *hop = hop.and_then(Hop::next);
+        }
+        if self.is_over() {
+            return;
+        }
+        for player in 0..2 {
+            self.boards[player].update();
+            self.check_goal(player);
+        }
+    }
+
+    /// Whether the animations or one of the boards changed since the last call.
+    pub fn take_dirty(&mut self) -> bool {
+        let [left, right] = &mut self.boards;
+        left.take_dirty() | right.take_dirty() | self.hops.iter().any(Option::is_some)
+    }
+
+    /// What happened on both boards since the last call, see [MapState::take_events].
+    pub fn take_events(&mut self) -> Vec<GameEvent> {
+        self.boards.iter_mut().flat_map(MapState::take_events).collect()
+    }
+
+    fn check_goal(&mut self, player: usize) {
+        if self.first.is_none() && self.boards[player].alive && self.boards[player].rows_crossed() >= VERSUS_ROWS {
+            self.first = Some(player);
+        }
+    }
+
+    /// The race ends when a player crossed [VERSUS_ROWS] rows or both died.
+    pub fn is_over(&self) -> bool {
+        self.first.is_some() || self.boards.iter().all(|board| !board.alive)
+    }
+
+    /// The player who crossed the goal first, otherwise the one who got further before both died.
+    /// [None] while the race is running or for a draw.
+    pub fn winner(&self) -> Option<usize> {
+        if self.first.is_some() || !self.is_over() {
+            return self.first;
+        }
+        let [left, right] = self.boards.each_ref().map(MapState::rows_crossed);
+        match left.cmp(&right) {
+            std::cmp::Ordering::Greater => Some(0),
+            std::cmp::Ordering::Less => Some(1),
+            std::cmp::Ordering::Equal => None,
+        }
+    }
+
+    /// Both boards next to each other, the first player on the left.
+    pub fn render(&self, theme: &Theme, ascii: bool) -> Grid {
+        let [left, right] = [0, 1].map(|player| {
+            let progress = theme.language.format(Text::PlayerProgress, &[&(player + 1), &self.boards[player].rows_crossed(), &VERSUS_ROWS]);
+            let mut grid = Grid::from_text(&progress);
+            grid.append(self.boards[player].render_animated(theme, &self.hops[player..=player]).bordered(ascii));
+            grid
+        });
+        left.beside(right, BOARD_GAP)
+    }
+
+    /// Who won and how far both players got.
+    pub fn results(&self, language: Language) -> String {
+        let headline = match self.winner() {
+            Some(player) => language.format(Text::PlayerWins, &[&(player + 1)]),
+            None => language.text(Text::Draw).to_string(),
+        };
+        let rows = [0, 1].map(|player| {
+            let board = &self.boards[player];
+            let outcome = match board.death_cause() {
+                Some(cause) if !board.alive => language.text(Text::Cause(cause)),
+                _ => language.text(Text::Alive),
+            };
+            let progress = language.format(Text::PlayerProgress, &[&(player + 1), &board.rows_crossed(), &VERSUS_ROWS]);
+            format!("{progress} ({outcome})")
+        });
+        format!("{headline}\n{}\n{}", rows[0], rows[1])
+    }
+}
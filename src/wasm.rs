@@ -0,0 +1,77 @@
+//! Bindings to embed the engine in web pages, e.g. in front of xterm.js.
+//!
+//! Only the [MapState] is exposed: JavaScript calls [WasmGame::step] on its own timer, forwards
+//! moves and draws the cells [WasmGame::render_to_cells] returns.
+use wasm_bindgen::prelude::wasm_bindgen;
+use crate::color::to_rgb;
+use crate::grid::Cell;
+use crate::map::{Facing, MapState};
+use crate::theme::Theme;
+
+/// Stands in for the terminals default color in [Cells].
+pub const DEFAULT_COLOR: u32 = u32::MAX;
+
+/// The board as [WasmGame::render_to_cells] draws it.
+#[wasm_bindgen(getter_with_clone)]
+pub struct Cells {
+    /// Cells per line.
+    pub width: usize,
+    /// The lines one after another, each cell as three numbers: the code point of the glyph and
+    /// the foreground and background color as `0xRRGGBB` or [DEFAULT_COLOR]. The second column of
+    /// double-width glyphs has the code point 0.
+    pub cells: Vec<u32>,
+}
+
+#[wasm_bindgen]
+pub struct WasmGame {
+    map: MapState,
+    theme: Theme,
+}
+
+#[wasm_bindgen]
+impl WasmGame {
+    #[wasm_bindgen(constructor)]
+    pub fn new(seed: u64) -> WasmGame {
+        WasmGame { map: MapState::with_seed(seed), theme: Theme::default() }
+    }
+
+    /// Advances the simulation by one tick, there are [crate::map::TICKS_PER_SECOND] per second.
+    pub fn step(&mut self) {
+        self.map.update();
+    }
+
+    /// Hops toward `direction`, one of `up`, `right`, `down` and `left`. Returns whether the
+    /// direction is known.
+    #[wasm_bindgen(js_name = "move")]
+    pub fn hop(&mut self, direction: &str) -> bool {
+        let direction = match direction {
+            "up" => Facing::Up,
+            "right" => Facing::Right,
+            "down" => Facing::Down,
+            "left" => Facing::Left,
+            _ => return false,
+        };
+        self.map.hop(0, direction);
+        true
+    }
+
+    pub fn score(&self) -> u64 {
+        self.map.score()
+    }
+
+    pub fn alive(&self) -> bool {
+        self.map.alive
+    }
+
+    pub fn render_to_cells(&self) -> Cells {
+        let color = |color| to_rgb(color).map_or(DEFAULT_COLOR, |(r, g, b)| u32::from_be_bytes([0, r, g, b]));
+        let grid = self.map.render(&self.theme);
+        let lines = grid.lines();
+        Cells {
+            width: lines.first().map_or(0, Vec::len),
+            cells: lines.iter()
+                .flat_map(|line| line.iter().flat_map(|cell: &Cell| [cell.glyph as u32, color(cell.fg), color(cell.bg)]))
+                .collect(),
+        }
+    }
+}
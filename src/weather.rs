@@ -0,0 +1,73 @@
+//! Rain and snow falling over the board.
+//!
+//! Particles are an effects layer composed over the rendered [Grid] and don't interact with
+//! anything. Only [Weather::Ice] changes the game: sideways hops slide one column further.
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use crate::color::Color;
+use crate::grid::{Cell, Grid};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Weather {
+    #[default]
+    Clear,
+    Rain,
+    Snow,
+    /// Snow on frozen ground.
+    Ice,
+}
+
+impl Weather {
+    pub const ALL: [Weather; 4] = [Weather::Clear, Weather::Rain, Weather::Snow, Weather::Ice];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Weather::Clear => "clear",
+            Weather::Rain => "rain",
+            Weather::Snow => "snow",
+            Weather::Ice => "ice",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|weather| weather.name() == name)
+    }
+
+    /// Whether sideways hops slide an extra column.
+    pub fn slippery(self) -> bool {
+        self == Weather::Ice
+    }
+
+    /// Draws the particles at `tick` over `grid`, whose first line shows row `top_y`.
+    ///
+    /// Rain falls a row per tick, snow drifts down and sideways slowly. Particles keep the color
+    /// of the cell below as background.
+    pub fn apply(self, grid: &mut Grid, tick: u64, top_y: u64) {
+        let (glyph, fg, density, fall, drift) = match self {
+            Weather::Clear => return,
+            Weather::Rain => ('\'', Color::Blue, 9, 1, 0),
+            Weather::Snow | Weather::Ice => ('*', Color::White, 14, 3, 5),
+        };
+        grid.map_cells(|x, line, cell, next| {
+            if cell.is_continuation() || next.is_some_and(Cell::is_continuation) {
+                return cell;
+            }
+            let y = top_y.wrapping_sub(line as u64);
+            let column = x as u64 + tick.checked_div(drift).unwrap_or(0);
+            if !particle(column, y.wrapping_add(tick / fall)).is_multiple_of(density) {
+                return cell;
+            }
+            let bg = if cell.bg == Color::Reset { cell.fg } else { cell.bg };
+            Cell { glyph, fg, bg }
+        });
+    }
+}
+
+/// Scrambles a position into a pseudo random number, so particles look scattered.
+fn particle(x: u64, y: u64) -> u64 {
+    let mut n = x.wrapping_mul(0x9e3779b97f4a7c15) ^ y.wrapping_mul(0xc2b2ae3d27d4eb4f);
+    n ^= n >> 29;
+    n = n.wrapping_mul(0xbf58476d1ce4e5b9);
+    n ^ (n >> 32)
+}
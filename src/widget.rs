@@ -0,0 +1,78 @@
+//! Embeds the game in [ratatui] apps.
+//!
+//! ```ignore
+//! frame.render_widget(CrossyWidget::new(&map), area);
+//! ```
+//! The app stays in charge of the game loop and calls [MapState::update] and the movement
+//! methods itself.
+use ratatui::buffer::Buffer;
+use ratatui::layout::Rect;
+use ratatui::style::{Color, Style};
+use ratatui::widgets::Widget;
+use crate::color::Color as EngineColor;
+use crate::grid::Grid;
+use crate::map::MapState;
+use crate::theme::Theme;
+
+/// Renders a [MapState], clipped to the area it is given.
+pub struct CrossyWidget<'a> {
+    map: &'a MapState,
+    theme: Theme,
+}
+
+impl<'a> CrossyWidget<'a> {
+    pub fn new(map: &'a MapState) -> Self {
+        CrossyWidget { map, theme: Theme::default() }
+    }
+
+    pub fn theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+}
+
+impl Widget for CrossyWidget<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        render_grid(&self.map.render(&self.theme), area, buf);
+    }
+}
+
+/// Copies the cells of `grid` into `buf`, starting at the top left of `area`.
+pub fn render_grid(grid: &Grid, area: Rect, buf: &mut Buffer) {
+    for (y, line) in grid.lines().iter().take(area.height as usize).enumerate() {
+        for (x, cell) in line.iter().take(area.width as usize).enumerate() {
+            if cell.is_continuation() {
+                // ratatui skips the cells covered by wide symbols itself.
+                continue;
+            }
+            let style = Style::default().fg(convert(cell.fg)).bg(convert(cell.bg));
+            if let Some(target) = buf.cell_mut((area.x + x as u16, area.y + y as u16)) {
+                target.set_char(cell.glyph).set_style(style);
+            }
+        }
+    }
+}
+
+fn convert(color: EngineColor) -> Color {
+    match color {
+        EngineColor::Reset => Color::Reset,
+        EngineColor::Black => Color::Black,
+        EngineColor::DarkGrey => Color::DarkGray,
+        EngineColor::Red => Color::LightRed,
+        EngineColor::DarkRed => Color::Red,
+        EngineColor::Green => Color::LightGreen,
+        EngineColor::DarkGreen => Color::Green,
+        EngineColor::Yellow => Color::LightYellow,
+        EngineColor::DarkYellow => Color::Yellow,
+        EngineColor::Blue => Color::LightBlue,
+        EngineColor::DarkBlue => Color::Blue,
+        EngineColor::Magenta => Color::LightMagenta,
+        EngineColor::DarkMagenta => Color::Magenta,
+        EngineColor::Cyan => Color::LightCyan,
+        EngineColor::DarkCyan => Color::Cyan,
+        EngineColor::White => Color::White,
+        EngineColor::Grey => Color::Gray,
+        EngineColor::Rgb { r, g, b } => Color::Rgb(r, g, b),
+        EngineColor::AnsiValue(value) => Color::Indexed(value),
+    }
+}